@@ -0,0 +1,569 @@
+//! Chart functions
+//!
+//! sparkline, line_chart, bar_chart, histogram_chart, scatter_chart, qq_chart
+
+use folio_plugin::prelude::*;
+use crate::helpers::{bounds, extract_field, extract_series, linear_fit, split_style, Style};
+use crate::svg::Plot;
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One block character per value, scaled between `lo` and `hi`
+fn blocks(values: &[f64], lo: f64, hi: f64) -> String {
+    values.iter()
+        .map(|v| {
+            let level = ((v - lo) / (hi - lo) * 7.0).round().clamp(0.0, 7.0) as usize;
+            BLOCKS[level]
+        })
+        .collect()
+}
+
+/// Bars grow from zero, so the range always includes it
+fn bar_bounds(values: &[f64]) -> (f64, f64) {
+    bounds(values.iter().copied().chain([0.0]))
+}
+
+fn result(chart: Result<String, FolioError>) -> Value {
+    match chart {
+        Ok(text) => Value::Text(text),
+        Err(e) => Value::Error(e),
+    }
+}
+
+fn text_only_svg(func: &str) -> FolioError {
+    FolioError::domain_error(format!("{}() is only drawn as SVG", func))
+        .with_suggestion("Use line_chart, bar_chart or histogram_chart with \"text\" for a Unicode chart")
+}
+
+// ============ Sparkline ============
+
+pub struct Sparkline;
+
+static SPARKLINE_ARGS: [ArgMeta; 1] = [ArgMeta {
+    name: "list",
+    typ: "List<Number>",
+    description: "Values to draw",
+    optional: false,
+    default: None,
+}];
+
+static SPARKLINE_EXAMPLES: [&str; 1] = ["sparkline([1, 5, 2, 8, 3]) → ▁▅▂█▃"];
+
+static SPARKLINE_RELATED: [&str; 2] = ["line_chart", "bar_chart"];
+
+impl FunctionPlugin for Sparkline {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "sparkline",
+            description: "Unicode sparkline, one block character per value",
+            usage: "sparkline(list)",
+            args: &SPARKLINE_ARGS,
+            returns: "Text",
+            examples: &SPARKLINE_EXAMPLES,
+            category: "chart",
+            source: None,
+            related: &SPARKLINE_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        if args.len() != 1 {
+            return Value::Error(FolioError::arg_count("sparkline", 1, args.len()));
+        }
+        result(extract_series(&args[0], "sparkline", "list").map(|values| {
+            let (lo, hi) = bounds(values.iter().copied());
+            blocks(&values, lo, hi)
+        }))
+    }
+}
+
+// ============ LineChart ============
+
+pub struct LineChart;
+
+static LINE_CHART_ARGS: [ArgMeta; 2] = [
+    ArgMeta {
+        name: "list",
+        typ: "List<Number>",
+        description: "Series to draw, e.g. from fibonacci() or partial_sums()",
+        optional: false,
+        default: None,
+    },
+    ArgMeta {
+        name: "style",
+        typ: "Text",
+        description: "\"svg\" or \"text\" (Unicode sparkline)",
+        optional: true,
+        default: Some("svg"),
+    },
+];
+
+static LINE_CHART_EXAMPLES: [&str; 2] = [
+    "line_chart(fibonacci(10)) → <svg …>",
+    "line_chart([1, 5, 2, 8], \"text\") → ▁▅▂█",
+];
+
+static LINE_CHART_RELATED: [&str; 2] = ["sparkline", "scatter_chart"];
+
+impl FunctionPlugin for LineChart {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "line_chart",
+            description: "Line chart of a series against its index",
+            usage: "line_chart(list, style?)",
+            args: &LINE_CHART_ARGS,
+            returns: "Text",
+            examples: &LINE_CHART_EXAMPLES,
+            category: "chart",
+            source: None,
+            related: &LINE_CHART_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        let (args, style) = split_style(args);
+        if args.len() != 1 {
+            return Value::Error(FolioError::arg_count("line_chart", 1, args.len()));
+        }
+        result(extract_series(&args[0], "line_chart", "list").map(|values| {
+            let (lo, hi) = bounds(values.iter().copied());
+            if style == Style::Text {
+                return blocks(&values, lo, hi);
+            }
+            let mut plot = Plot::new("line", (0.0, (values.len() - 1).max(1) as f64), (lo, hi));
+            let points: Vec<(f64, f64)> = values.iter().enumerate().map(|(i, &v)| (i as f64, v)).collect();
+            plot.polyline(&points);
+            plot.finish()
+        }))
+    }
+}
+
+// ============ BarChart ============
+
+pub struct BarChart;
+
+static BAR_CHART_ARGS: [ArgMeta; 3] = [
+    ArgMeta {
+        name: "values",
+        typ: "List<Number>|Object",
+        description: "Bar heights; an object's keys become labels",
+        optional: false,
+        default: None,
+    },
+    ArgMeta {
+        name: "labels",
+        typ: "List",
+        description: "Label under each bar",
+        optional: true,
+        default: None,
+    },
+    ArgMeta {
+        name: "style",
+        typ: "Text",
+        description: "\"svg\" or \"text\" (Unicode bars)",
+        optional: true,
+        default: Some("svg"),
+    },
+];
+
+static BAR_CHART_EXAMPLES: [&str; 2] = [
+    "bar_chart([3, 7, 2], [\"a\", \"b\", \"c\"]) → <svg …>",
+    "bar_chart({q1: 10, q2: 14}) → <svg …>",
+];
+
+static BAR_CHART_RELATED: [&str; 2] = ["histogram_chart", "sparkline"];
+
+impl FunctionPlugin for BarChart {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "bar_chart",
+            description: "Bar chart of values, optionally labelled",
+            usage: "bar_chart(values, labels?, style?)",
+            args: &BAR_CHART_ARGS,
+            returns: "Text",
+            examples: &BAR_CHART_EXAMPLES,
+            category: "chart",
+            source: None,
+            related: &BAR_CHART_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        let (args, style) = split_style(args);
+        if args.is_empty() || args.len() > 2 {
+            return Value::Error(FolioError::arg_count("bar_chart", 1, args.len()));
+        }
+        result(bar_chart(args, style))
+    }
+}
+
+fn bar_chart(args: &[Value], style: Style) -> Result<String, FolioError> {
+    let (values, mut labels) = match &args[0] {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let list = Value::List(keys.iter().map(|k| map[*k].clone()).collect());
+            (extract_series(&list, "bar_chart", "values")?, keys.into_iter().cloned().collect())
+        }
+        other => (extract_series(other, "bar_chart", "values")?, Vec::new()),
+    };
+    match args.get(1) {
+        Some(Value::List(items)) => labels = items.iter().map(|v| v.to_string()).collect(),
+        Some(Value::Error(e)) => return Err(e.clone()),
+        Some(other) => return Err(FolioError::arg_type("bar_chart", "labels", "List", other.type_name())),
+        None => {}
+    }
+
+    let (lo, hi) = bar_bounds(&values);
+    if style == Style::Text {
+        return Ok(blocks(&values, lo, hi));
+    }
+
+    let mut plot = Plot::new("bar", (0.0, values.len() as f64), (lo, hi)).without_x_labels();
+    let band = plot.span() / values.len() as f64;
+    for (i, &v) in values.iter().enumerate() {
+        let top = plot.py(v.max(0.0));
+        let bottom = plot.py(v.min(0.0));
+        plot.rect(plot.px(i as f64) + band * 0.1, top, band * 0.8, bottom - top);
+        if let Some(text) = labels.get(i) {
+            plot.tick_label(plot.px(i as f64 + 0.5), text);
+        }
+    }
+    Ok(plot.finish())
+}
+
+// ============ HistogramChart ============
+
+pub struct HistogramChart;
+
+static HISTOGRAM_CHART_ARGS: [ArgMeta; 3] = [
+    ArgMeta {
+        name: "data",
+        typ: "List<Number>|Object",
+        description: "Raw data, or the result of histogram()",
+        optional: false,
+        default: None,
+    },
+    ArgMeta {
+        name: "bins",
+        typ: "Number|Text",
+        description: "Bin count or method for raw data, as in histogram()",
+        optional: true,
+        default: Some("auto"),
+    },
+    ArgMeta {
+        name: "style",
+        typ: "Text",
+        description: "\"svg\" or \"text\" (Unicode bars)",
+        optional: true,
+        default: Some("svg"),
+    },
+];
+
+static HISTOGRAM_CHART_EXAMPLES: [&str; 2] = [
+    "histogram_chart(histogram(data, 8)) → <svg …>",
+    "histogram_chart(data, \"sturges\", \"text\") → ▂▅█▆▃▁",
+];
+
+static HISTOGRAM_CHART_RELATED: [&str; 2] = ["histogram", "bar_chart"];
+
+impl FunctionPlugin for HistogramChart {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "histogram_chart",
+            description: "Histogram of raw data or of a histogram() result",
+            usage: "histogram_chart(data, bins?, style?)",
+            args: &HISTOGRAM_CHART_ARGS,
+            returns: "Text",
+            examples: &HISTOGRAM_CHART_EXAMPLES,
+            category: "chart",
+            source: None,
+            related: &HISTOGRAM_CHART_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], ctx: &EvalContext) -> Value {
+        let (args, style) = split_style(args);
+        if args.is_empty() || args.len() > 2 {
+            return Value::Error(FolioError::arg_count("histogram_chart", 1, args.len()));
+        }
+        // Raw data is binned by histogram() from folio-stats
+        let histogram = match &args[0] {
+            Value::List(_) => ctx.registry.call_function("histogram", args, ctx),
+            other => other.clone(),
+        };
+        result(histogram_chart(&histogram, style))
+    }
+}
+
+fn histogram_chart(histogram: &Value, style: Style) -> Result<String, FolioError> {
+    match histogram {
+        Value::Object(_) => {}
+        Value::Error(e) => return Err(e.clone()),
+        other => return Err(FolioError::arg_type("histogram_chart", "data", "List or Object", other.type_name())),
+    }
+    let edges = extract_field(histogram, "edges", "histogram_chart")?;
+    let counts = extract_field(histogram, "counts", "histogram_chart")?;
+    if edges.len() != counts.len() + 1 {
+        return Err(FolioError::shape_mismatch(format!(
+            "histogram_chart(): {} edges for {} counts, expected one more edge than counts",
+            edges.len(), counts.len()
+        )));
+    }
+
+    let (lo, hi) = bar_bounds(&counts);
+    if style == Style::Text {
+        return Ok(blocks(&counts, lo, hi));
+    }
+
+    let mut plot = Plot::new("histogram", bounds(edges.iter().copied()), (lo, hi));
+    for (i, &count) in counts.iter().enumerate() {
+        let (left, right) = (plot.px(edges[i]), plot.px(edges[i + 1]));
+        let top = plot.py(count);
+        plot.rect(left, top, right - left, plot.py(0.0) - top);
+    }
+    Ok(plot.finish())
+}
+
+// ============ ScatterChart ============
+
+pub struct ScatterChart;
+
+static SCATTER_CHART_ARGS: [ArgMeta; 2] = [
+    ArgMeta {
+        name: "x",
+        typ: "List<Number>",
+        description: "Horizontal values",
+        optional: false,
+        default: None,
+    },
+    ArgMeta {
+        name: "y",
+        typ: "List<Number>",
+        description: "Vertical values, same length as x",
+        optional: false,
+        default: None,
+    },
+];
+
+static SCATTER_CHART_EXAMPLES: [&str; 1] = ["scatter_chart([1, 2, 3, 4], [2.1, 3.9, 6.2, 7.8]) → <svg …>"];
+
+static SCATTER_CHART_RELATED: [&str; 2] = ["linear_reg", "qq_chart"];
+
+impl FunctionPlugin for ScatterChart {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "scatter_chart",
+            description: "Scatter plot with a least-squares regression line",
+            usage: "scatter_chart(x, y)",
+            args: &SCATTER_CHART_ARGS,
+            returns: "Text",
+            examples: &SCATTER_CHART_EXAMPLES,
+            category: "chart",
+            source: None,
+            related: &SCATTER_CHART_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        let (args, style) = split_style(args);
+        if style == Style::Text {
+            return Value::Error(text_only_svg("scatter_chart"));
+        }
+        if args.len() != 2 {
+            return Value::Error(FolioError::arg_count("scatter_chart", 2, args.len()));
+        }
+        result((|| {
+            let x = extract_series(&args[0], "scatter_chart", "x")?;
+            let y = extract_series(&args[1], "scatter_chart", "y")?;
+            let fit = linear_fit(&x, &y);
+            scatter("scatter", &x, &y, fit)
+        })())
+    }
+}
+
+/// Points plus a dashed line `y = slope·x + intercept` across the x range
+fn scatter(kind: &'static str, x: &[f64], y: &[f64], fit: Option<(f64, f64)>) -> Result<String, FolioError> {
+    if x.len() != y.len() {
+        return Err(FolioError::shape_mismatch(format!(
+            "{}_chart(): x has {} values but y has {}", kind, x.len(), y.len()
+        )));
+    }
+    let x_range = bounds(x.iter().copied());
+    let line = fit.map(|(slope, intercept)| {
+        ((x_range.0, slope * x_range.0 + intercept), (x_range.1, slope * x_range.1 + intercept))
+    });
+    // Keep the line's ends inside the plot
+    let y_range = bounds(y.iter().copied().chain(line.iter().flat_map(|(a, b)| [a.1, b.1])));
+
+    let mut plot = Plot::new(kind, x_range, y_range);
+    for (&a, &b) in x.iter().zip(y) {
+        plot.circle(a, b);
+    }
+    if let Some((from, to)) = line {
+        plot.reference_line(from, to);
+    }
+    Ok(plot.finish())
+}
+
+// ============ QqChart ============
+
+pub struct QqChart;
+
+static QQ_CHART_ARGS: [ArgMeta; 1] = [ArgMeta {
+    name: "data",
+    typ: "List<Number>|Object",
+    description: "Raw data, or the result of qq_points()",
+    optional: false,
+    default: None,
+}];
+
+static QQ_CHART_EXAMPLES: [&str; 2] = [
+    "qq_chart(data) → <svg …>",
+    "qq_chart(qq_points(data)) → <svg …>",
+];
+
+static QQ_CHART_RELATED: [&str; 2] = ["qq_points", "is_normal"];
+
+impl FunctionPlugin for QqChart {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "qq_chart",
+            description: "Normal Q-Q plot: sample against theoretical quantiles with a reference line",
+            usage: "qq_chart(data)",
+            args: &QQ_CHART_ARGS,
+            returns: "Text",
+            examples: &QQ_CHART_EXAMPLES,
+            category: "chart",
+            source: None,
+            related: &QQ_CHART_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], ctx: &EvalContext) -> Value {
+        let (args, style) = split_style(args);
+        if style == Style::Text {
+            return Value::Error(text_only_svg("qq_chart"));
+        }
+        if args.len() != 1 {
+            return Value::Error(FolioError::arg_count("qq_chart", 1, args.len()));
+        }
+        // Raw data goes through qq_points() from folio-stats
+        let points = match &args[0] {
+            Value::List(_) => ctx.registry.call_function("qq_points", args, ctx),
+            other => other.clone(),
+        };
+        result(qq_chart(&points))
+    }
+}
+
+fn qq_chart(points: &Value) -> Result<String, FolioError> {
+    match points {
+        Value::Object(_) => {}
+        Value::Error(e) => return Err(e.clone()),
+        other => return Err(FolioError::arg_type("qq_chart", "data", "List or Object", other.type_name())),
+    }
+    let theoretical = extract_field(points, "theoretical", "qq_chart")?;
+    let sample = extract_field(points, "sample", "qq_chart")?;
+    // qq_points() reports its own fit; otherwise fit here
+    let fit = match (points.get("slope"), points.get("intercept")) {
+        (Value::Number(slope), Value::Number(intercept)) => slope.to_f64().zip(intercept.to_f64()),
+        _ => linear_fit(&theoretical, &sample),
+    };
+    scatter("qq", &theoretical, &sample, fit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn eval_ctx() -> EvalContext {
+        EvalContext::new(std::sync::Arc::new(folio_plugin::PluginRegistry::new()))
+    }
+
+    fn list(values: &[i64]) -> Value {
+        Value::List(values.iter().map(|&v| Value::Number(Number::from_i64(v))).collect())
+    }
+
+    fn text(value: Value) -> String {
+        match value {
+            Value::Text(s) => s,
+            other => panic!("expected text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(text(Sparkline.call(&[list(&[1, 5, 2, 8, 3])], &eval_ctx())), "▁▅▂█▃");
+        assert_eq!(text(Sparkline.call(&[list(&[4, 4, 4])], &eval_ctx())), "▅▅▅");
+        assert!(Sparkline.call(&[list(&[])], &eval_ctx()).is_error());
+    }
+
+    #[test]
+    fn test_line_chart_snapshot() {
+        let svg = text(LineChart.call(&[list(&[1, 3, 2])], &eval_ctx()));
+        assert_eq!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"folio-chart line\" width=\"320\" height=\"160\" viewBox=\"0 0 320 160\" font-family=\"sans-serif\" font-size=\"10\" fill=\"#333\">\
+             <path d=\"M40 8V140H312\" fill=\"none\" stroke=\"#999\"/>\
+             <text x=\"36\" y=\"12\" text-anchor=\"end\">3</text><text x=\"36\" y=\"140\" text-anchor=\"end\">1</text>\
+             <text x=\"40\" y=\"153\" text-anchor=\"start\">0</text><text x=\"312\" y=\"153\" text-anchor=\"end\">2</text>\
+             <polyline points=\"40,140 176,8 312,74\" fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"1.5\"/></svg>"
+        );
+        assert!(!svg.contains('\n'));
+        assert_eq!(text(LineChart.call(&[list(&[1, 3, 2]), Value::Text("text".into())], &eval_ctx())), "▁█▅");
+    }
+
+    #[test]
+    fn test_bar_chart() {
+        let labels = Value::List(vec![Value::Text("a".into()), Value::Text("b".into())]);
+        let svg = text(BarChart.call(&[list(&[2, 4]), labels], &eval_ctx()));
+        assert!(svg.contains("<rect x=\"53.6\" y=\"74\" width=\"108.8\" height=\"66\""), "{}", svg);
+        assert!(svg.contains("<text x=\"108\" y=\"153\" text-anchor=\"middle\">a</text>"), "{}", svg);
+
+        let mut map = HashMap::new();
+        map.insert("q2".to_string(), Value::Number(Number::from_i64(-1)));
+        map.insert("q1".to_string(), Value::Number(Number::from_i64(3)));
+        let svg = text(BarChart.call(&[Value::Object(map)], &eval_ctx()));
+        assert!(svg.find(">q1<").unwrap() < svg.find(">q2<").unwrap());
+        assert_eq!(text(BarChart.call(&[list(&[0, 7, 3]), Value::Text("text".into())], &eval_ctx())), "▁█▄");
+    }
+
+    #[test]
+    fn test_histogram_chart_from_object() {
+        let mut hist = HashMap::new();
+        hist.insert("edges".to_string(), list(&[0, 10, 20, 30]));
+        hist.insert("counts".to_string(), list(&[1, 4, 2]));
+        let svg = text(HistogramChart.call(&[Value::Object(hist.clone())], &eval_ctx()));
+        assert!(svg.contains("class=\"folio-chart histogram\""));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.contains("<rect x=\"130.7\" y=\"8\" width=\"90.7\" height=\"132\""), "{}", svg);
+        assert_eq!(text(HistogramChart.call(&[Value::Object(hist.clone()), Value::Text("text".into())], &eval_ctx())), "▃█▅");
+
+        hist.insert("edges".to_string(), list(&[0, 10]));
+        match HistogramChart.call(&[Value::Object(hist)], &eval_ctx()) {
+            Value::Error(e) => assert_eq!(e.code, "SHAPE_MISMATCH"),
+            other => panic!("expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_scatter_chart_regression_line() {
+        let svg = text(ScatterChart.call(&[list(&[1, 2, 3]), list(&[3, 5, 7])], &eval_ctx()));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        // y = 2x + 1 runs corner to corner
+        assert!(svg.contains("<line x1=\"40\" y1=\"140\" x2=\"312\" y2=\"8\""), "{}", svg);
+        assert!(ScatterChart.call(&[list(&[1, 2]), list(&[1])], &eval_ctx()).is_error());
+        assert!(ScatterChart.call(&[list(&[1, 2]), list(&[1, 2]), Value::Text("text".into())], &eval_ctx()).is_error());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let data = list(&[5, 1, 4, 1, 5, 9, 2, 6]);
+        let first = text(LineChart.call(std::slice::from_ref(&data), &eval_ctx()));
+        for _ in 0..3 {
+            assert_eq!(text(LineChart.call(std::slice::from_ref(&data), &eval_ctx())), first);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Common chart utilities
+
+use folio_core::{FolioError, Value};
+
+/// How a chart is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Single-line inline SVG
+    Svg,
+    /// Unicode block characters, one per value
+    Text,
+}
+
+impl Style {
+    /// Recognize a trailing "svg" / "text" argument
+    pub fn parse(value: &Value) -> Option<Style> {
+        match value {
+            Value::Text(s) if s.eq_ignore_ascii_case("svg") => Some(Style::Svg),
+            Value::Text(s) if s.eq_ignore_ascii_case("text") => Some(Style::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Split a trailing style argument off the argument list
+pub fn split_style(args: &[Value]) -> (&[Value], Style) {
+    match args.last().and_then(Style::parse) {
+        Some(style) => (&args[..args.len() - 1], style),
+        None => (args, Style::Svg),
+    }
+}
+
+/// Numbers of a list as f64, for drawing
+pub fn extract_series(value: &Value, func: &str, arg: &str) -> Result<Vec<f64>, FolioError> {
+    let items = match value {
+        Value::List(items) => items,
+        Value::Error(e) => return Err(e.clone()),
+        other => return Err(FolioError::arg_type(func, arg, "List<Number>", other.type_name())),
+    };
+    if items.is_empty() {
+        return Err(FolioError::domain_error(format!("{}() needs at least one value", func)));
+    }
+    items.iter()
+        .map(|item| match item {
+            Value::Number(n) => n.to_f64()
+                .filter(|f| f.is_finite())
+                .ok_or_else(|| FolioError::domain_error(format!("{}(): value out of range for charting", func))),
+            Value::Error(e) => Err(e.clone()),
+            other => Err(FolioError::arg_type(func, arg, "List<Number>", other.type_name())),
+        })
+        .collect()
+}
+
+/// Read a list field of an object, e.g. `counts` of a histogram
+pub fn extract_field(value: &Value, field: &str, func: &str) -> Result<Vec<f64>, FolioError> {
+    match value.get(field) {
+        Value::Null => Err(FolioError::domain_error(format!("{}(): object has no '{}' field", func, field))),
+        list => extract_series(&list, func, field),
+    }
+}
+
+/// `(min, max)`, widened when all values are equal so there is something to scale
+pub fn bounds(values: impl IntoIterator<Item = f64>) -> (f64, f64) {
+    let (lo, hi) = values.into_iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if !lo.is_finite() || !hi.is_finite() {
+        (0.0, 1.0)
+    } else if lo == hi {
+        (lo - 1.0, hi + 1.0)
+    } else {
+        (lo, hi)
+    }
+}
+
+/// Least-squares `(slope, intercept)`; None when all x are equal
+pub fn linear_fit(x: &[f64], y: &[f64]) -> Option<(f64, f64)> {
+    let n = x.len().min(y.len()) as f64;
+    if n < 2.0 {
+        return None;
+    }
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let sxx: f64 = x.iter().map(|v| (v - mean_x).powi(2)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+    let sxy: f64 = x.iter().zip(y).map(|(a, b)| (a - mean_x) * (b - mean_y)).sum();
+    let slope = sxy / sxx;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+/// Coordinate with one decimal, without a trailing `.0`
+pub fn coord(v: f64) -> String {
+    trim(format!("{:.1}", v))
+}
+
+/// Axis label with about four significant digits
+pub fn label(v: f64) -> String {
+    if v == 0.0 {
+        return "0".to_string();
+    }
+    let magnitude = v.abs().log10().floor() as i32;
+    let decimals = (3 - magnitude).clamp(0, 6) as usize;
+    trim(format!("{:.*}", decimals, v))
+}
+
+fn trim(mut text: String) -> String {
+    if text.contains('.') {
+        while text.ends_with('0') {
+            text.pop();
+        }
+        if text.ends_with('.') {
+            text.pop();
+        }
+    }
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label() {
+        assert_eq!(label(0.0), "0");
+        assert_eq!(label(1234.5678), "1235");
+        assert_eq!(label(0.012345), "0.01235");
+        assert_eq!(label(-2.5), "-2.5");
+        assert_eq!(coord(-0.04), "0");
+        assert_eq!(coord(12.0), "12");
+    }
+
+    #[test]
+    fn test_linear_fit() {
+        assert_eq!(linear_fit(&[1.0, 2.0, 3.0], &[3.0, 5.0, 7.0]), Some((2.0, 1.0)));
+        assert_eq!(linear_fit(&[1.0, 1.0], &[3.0, 5.0]), None);
+    }
+}
@@ -0,0 +1,22 @@
+//! Folio Chart Plugin
+//!
+//! Charts of lists, histograms and Q-Q points, drawn as single-line inline SVG
+//! or as Unicode block characters so they fit in a result cell. Output is
+//! deterministic and can be compared byte for byte in tests.
+
+mod helpers;
+mod svg;
+mod charts;
+
+use folio_plugin::PluginRegistry;
+
+/// Load chart functions into registry
+pub fn load_chart_library(registry: PluginRegistry) -> PluginRegistry {
+    registry
+        .with_function(charts::Sparkline)
+        .with_function(charts::LineChart)
+        .with_function(charts::BarChart)
+        .with_function(charts::HistogramChart)
+        .with_function(charts::ScatterChart)
+        .with_function(charts::QqChart)
+}
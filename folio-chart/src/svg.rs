@@ -0,0 +1,120 @@
+//! SVG drawing
+//!
+//! Charts are written on a single line so they fit in a markdown table cell.
+//! Coordinates are rounded to one decimal and elements are emitted in data
+//! order, so the same input always gives byte-identical output.
+
+use crate::helpers::{coord, label};
+
+pub const WIDTH: f64 = 320.0;
+pub const HEIGHT: f64 = 160.0;
+const LEFT: f64 = 40.0;
+const RIGHT: f64 = 312.0;
+const TOP: f64 = 8.0;
+const BOTTOM: f64 = 140.0;
+
+pub const COLOR: &str = "#1f77b4";
+pub const ACCENT: &str = "#d62728";
+
+/// Plot area mapping data ranges onto the canvas
+pub struct Plot {
+    kind: &'static str,
+    x: (f64, f64),
+    y: (f64, f64),
+    /// Label the x axis with its range; bar charts label bars instead
+    x_labels: bool,
+    body: String,
+}
+
+impl Plot {
+    pub fn new(kind: &'static str, x: (f64, f64), y: (f64, f64)) -> Self {
+        Self { kind, x, y, x_labels: true, body: String::new() }
+    }
+
+    pub fn without_x_labels(mut self) -> Self {
+        self.x_labels = false;
+        self
+    }
+
+    pub fn px(&self, x: f64) -> f64 {
+        LEFT + (x - self.x.0) / (self.x.1 - self.x.0) * (RIGHT - LEFT)
+    }
+
+    pub fn py(&self, y: f64) -> f64 {
+        BOTTOM - (y - self.y.0) / (self.y.1 - self.y.0) * (BOTTOM - TOP)
+    }
+
+    /// Width of the plot area in pixels
+    pub fn span(&self) -> f64 {
+        RIGHT - LEFT
+    }
+
+    pub fn polyline(&mut self, points: &[(f64, f64)]) {
+        let points: Vec<String> = points.iter()
+            .map(|&(x, y)| format!("{},{}", coord(self.px(x)), coord(self.py(y))))
+            .collect();
+        self.body.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\"/>",
+            points.join(" "), COLOR
+        ));
+    }
+
+    pub fn circle(&mut self, x: f64, y: f64) {
+        self.body.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"2.5\" fill=\"{}\"/>",
+            coord(self.px(x)), coord(self.py(y)), COLOR
+        ));
+    }
+
+    /// Rectangle in pixel units
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.body.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#fff\" stroke-width=\"0.5\"/>",
+            coord(x), coord(y), coord(width), coord(height), COLOR
+        ));
+    }
+
+    /// Dashed reference line between two data points
+    pub fn reference_line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        self.body.push_str(&format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"1.5\" stroke-dasharray=\"4 3\"/>",
+            coord(self.px(from.0)), coord(self.py(from.1)), coord(self.px(to.0)), coord(self.py(to.1)), ACCENT
+        ));
+    }
+
+    /// Text centred under the axis at pixel column `x`
+    pub fn tick_label(&mut self, x: f64, text: &str) {
+        self.body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            coord(x), coord(BOTTOM + 13.0), escape(text)
+        ));
+    }
+
+    pub fn finish(self) -> String {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"folio-chart {0}\" width=\"{1}\" height=\"{2}\" viewBox=\"0 0 {1} {2}\" font-family=\"sans-serif\" font-size=\"10\" fill=\"#333\">",
+            self.kind, WIDTH, HEIGHT
+        );
+        out.push_str(&format!(
+            "<path d=\"M{0} {1}V{2}H{3}\" fill=\"none\" stroke=\"#999\"/>",
+            LEFT, TOP, BOTTOM, RIGHT
+        ));
+        out.push_str(&format!(
+            "<text x=\"{0}\" y=\"{1}\" text-anchor=\"end\">{2}</text><text x=\"{0}\" y=\"{3}\" text-anchor=\"end\">{4}</text>",
+            LEFT - 4.0, coord(TOP + 4.0), label(self.y.1), coord(BOTTOM), label(self.y.0)
+        ));
+        if self.x_labels {
+            out.push_str(&format!(
+                "<text x=\"{0}\" y=\"{1}\" text-anchor=\"start\">{2}</text><text x=\"{3}\" y=\"{1}\" text-anchor=\"end\">{4}</text>",
+                LEFT, coord(BOTTOM + 13.0), label(self.x.0), RIGHT, label(self.x.1)
+            ));
+        }
+        out.push_str(&self.body);
+        out.push_str("</svg>");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('|', "&#124;")
+}
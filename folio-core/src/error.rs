@@ -19,7 +19,9 @@ pub mod codes {
     pub const DOMAIN_ERROR: &str = "DOMAIN_ERROR";
     pub const OVERFLOW: &str = "OVERFLOW";
     pub const CIRCULAR_REF: &str = "CIRCULAR_REF";
+    pub const SHAPE_MISMATCH: &str = "SHAPE_MISMATCH";
     pub const INTERNAL: &str = "INTERNAL";
+    pub const CANCELLED: &str = "CANCELLED";
     // DateTime-specific error codes
     pub const INVALID_DATE: &str = "INVALID_DATE";
     pub const INVALID_TIME: &str = "INVALID_TIME";
@@ -182,6 +184,11 @@ impl FolioError {
         Self::new(codes::DOMAIN_ERROR, format!("Domain error: {}", details.into()))
     }
     
+    pub fn shape_mismatch(details: impl Into<String>) -> Self {
+        Self::new(codes::SHAPE_MISMATCH, format!("Shape mismatch: {}", details.into()))
+            .with_suggestion("Check operand dimensions with shape()")
+    }
+    
     pub fn circular_ref(cells: &[String]) -> Self {
         Self::new(codes::CIRCULAR_REF, 
             format!("Circular reference: {}", cells.join(" → ")))
@@ -195,6 +202,12 @@ impl FolioError {
             .with_severity(Severity::Fatal)
     }
 
+    /// Evaluation was cancelled before the cell ran
+    pub fn cancelled() -> Self {
+        Self::new(codes::CANCELLED, "Evaluation cancelled")
+            .with_severity(Severity::Fatal)
+    }
+
     // ========== DateTime Error Constructors ==========
 
     pub fn invalid_date(details: impl Into<String>) -> Self {
@@ -0,0 +1,106 @@
+//! Extension values registered by plugins
+//!
+//! Plugins can introduce their own runtime types (matrices, quantities,
+//! regression results) by implementing `FolioExtValue` and wrapping them in
+//! `Value::Custom`. The evaluator asks the trait how to display, serialize,
+//! read fields from, and apply operators to the value.
+//!
+//! The trait lives here rather than in `folio-plugin` because `Value` must
+//! name it; `folio-plugin` re-exports it for plugin authors.
+
+use crate::Value;
+use serde::{Serialize, Serializer};
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// Operators an extension value may overload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtOp {
+    Add, Sub, Mul, Div, Pow,
+    /// Element-wise `.*` and `./`
+    ElemMul, ElemDiv,
+    Lt, Gt, Le, Ge, Eq, Ne,
+}
+
+impl ExtOp {
+    /// Operator symbol for error messages
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ExtOp::Add => "+",
+            ExtOp::Sub => "-",
+            ExtOp::Mul => "*",
+            ExtOp::Div => "/",
+            ExtOp::Pow => "^",
+            ExtOp::ElemMul => ".*",
+            ExtOp::ElemDiv => "./",
+            ExtOp::Lt => "<",
+            ExtOp::Gt => ">",
+            ExtOp::Le => "<=",
+            ExtOp::Ge => ">=",
+            ExtOp::Eq => "==",
+            ExtOp::Ne => "!=",
+        }
+    }
+}
+
+/// Opaque value type provided by a plugin
+///
+/// Only `type_name`, `display`, `to_json` and `as_any` are required. Every
+/// other hook defaults to "not supported", which the evaluator reports as a
+/// TYPE_ERROR or UNDEFINED_FIELD.
+pub trait FolioExtValue: fmt::Debug + Send + Sync {
+    /// Type name used in error messages and `typeof()`
+    fn type_name(&self) -> &'static str;
+
+    /// Human-readable form used by `Display` and the renderer
+    fn display(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// JSON form used for serialization and MCP responses
+    fn to_json(&self) -> serde_json::Value;
+
+    /// Read a named field (`value.field`)
+    fn field(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Names of the readable fields, for `fields()` and error suggestions
+    fn field_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Apply `self op rhs`. Return None if the operand combination is unsupported.
+    fn binary_op(&self, _op: ExtOp, _rhs: &Value) -> Option<Value> {
+        None
+    }
+
+    /// Apply `lhs op self` when the left operand did not handle the operator
+    fn binary_op_rhs(&self, _op: ExtOp, _lhs: &Value) -> Option<Value> {
+        None
+    }
+
+    /// Apply unary negation
+    fn neg(&self) -> Option<Value> {
+        None
+    }
+
+    /// Apply postfix transpose (`x^T`)
+    fn transpose(&self) -> Option<Value> {
+        None
+    }
+
+    /// Downcasting support, see `Value::as_custom`
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Serialize an extension value as `{"ext_type": ..., "data": ...}`
+pub(crate) fn serialize_ext<S: Serializer>(
+    ext: &Arc<dyn FolioExtValue>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serde_json::json!({
+        "ext_type": ext.type_name(),
+        "data": ext.to_json(),
+    })
+    .serialize(serializer)
+}
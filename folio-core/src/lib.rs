@@ -6,21 +6,25 @@
 //! - `FolioDateTime`: Nanosecond-precision datetime
 //! - `FolioDuration`: Nanosecond-precision duration
 //! - `FolioError`: Structured errors for LLM consumption
+//! - `FolioExtValue`: Hook for plugin-defined value types
 
 mod number;
 mod value;
 mod error;
 mod datetime;
+mod ext;
 
 pub use number::{Number, NumberError};
 pub use value::Value;
 pub use error::{FolioError, ErrorContext, Severity, codes};
 pub use datetime::{FolioDateTime, FolioDuration, DateTimeError, is_leap_year, days_in_month};
+pub use ext::{FolioExtValue, ExtOp};
 
 /// Prelude for convenient imports
 pub mod prelude {
     pub use crate::{Number, Value, FolioError, Severity};
     pub use crate::{FolioDateTime, FolioDuration, DateTimeError};
+    pub use crate::{FolioExtValue, ExtOp};
     pub use crate::error::codes;
 }
 
@@ -414,6 +418,36 @@ mod tests {
             assert!(matches!(n, Value::Number(_)));
         }
 
+        #[derive(Debug)]
+        struct Meters(i64);
+
+        impl FolioExtValue for Meters {
+            fn type_name(&self) -> &'static str { "Meters" }
+            fn display(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{} m", self.0)
+            }
+            fn to_json(&self) -> serde_json::Value { serde_json::json!(self.0) }
+            fn field(&self, name: &str) -> Option<Value> {
+                (name == "value").then(|| Value::from(self.0))
+            }
+            fn as_any(&self) -> &dyn std::any::Any { self }
+        }
+
+        #[test]
+        fn test_custom_value() {
+            let v = Value::custom(Meters(42));
+            assert_eq!(v.type_name(), "Meters");
+            assert_eq!(v.to_string(), "42 m");
+            assert_eq!(v.as_custom::<Meters>().map(|m| m.0), Some(42));
+            assert_eq!(v.get("value").as_number().unwrap().to_i64(), Some(42));
+            assert!(v.get("missing").is_error());
+
+            let json = serde_json::to_value(&v).unwrap();
+            assert_eq!(json["type"], "Custom");
+            assert_eq!(json["value"]["ext_type"], "Meters");
+            assert_eq!(json["value"]["data"], 42);
+        }
+
         #[test]
         fn test_to_bool_truthy() {
             assert!(matches!(Value::Number(Number::from_i64(1)).to_bool(), Value::Bool(true)));
@@ -398,6 +398,38 @@ impl Number {
         }
     }
 
+    /// Render rounded half away from zero to exactly `places` decimals,
+    /// without going through f64
+    pub fn to_fixed(&self, places: u32) -> String {
+        let scaled = self.abs()
+            .mul(&Self::from_i64(10).pow(places as i32))
+            .add(&Self::from_ratio(1, 2))
+            .floor();
+        let (significand, exponent) = scaled.inner.into_repr().into_parts();
+        let mut digits = if exponent >= 0 {
+            format!("{}{}", significand, "0".repeat(exponent as usize))
+        } else {
+            (significand / IBig::from(10u8).pow(exponent.unsigned_abs())).to_string()
+        };
+
+        let places = places as usize;
+        if digits.len() <= places {
+            digits = format!("{}{}", "0".repeat(places + 1 - digits.len()), digits);
+        }
+        if places > 0 {
+            digits.insert(digits.len() - places, '.');
+        }
+        if self.is_negative() && digits.chars().any(|c| c != '0' && c != '.') {
+            digits.insert(0, '-');
+        }
+        digits
+    }
+
+    /// Render every stored digit, without going through f64
+    pub fn as_exact(&self) -> String {
+        format!("{}", self.inner)
+    }
+
     /// Render with N significant figures
     pub fn as_sigfigs(&self, sigfigs: u32) -> String {
         if let Some(f) = self.to_f64() {
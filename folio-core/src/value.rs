@@ -1,12 +1,13 @@
 //! Runtime values in Folio
 //!
 //! Values can be numbers, text, booleans, datetime, duration, objects
-//! (for DECOMPOSE results), lists, null, or errors. Errors propagate
-//! through computations.
+//! (for DECOMPOSE results), lists, plugin-defined extension values, null,
+//! or errors. Errors propagate through computations.
 
-use crate::{Number, FolioError, FolioDateTime, FolioDuration};
+use crate::{Number, FolioError, FolioDateTime, FolioDuration, FolioExtValue};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Runtime value in Folio
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,11 +20,19 @@ pub enum Value {
     Duration(FolioDuration),
     Object(HashMap<String, Value>),
     List(Vec<Value>),
+    /// Opaque value owned by a plugin (see `FolioExtValue`)
+    #[serde(serialize_with = "crate::ext::serialize_ext", skip_deserializing)]
+    Custom(Arc<dyn FolioExtValue>),
     Null,
     Error(FolioError),
 }
 
 impl Value {
+    /// Wrap a plugin-defined value
+    pub fn custom<T: FolioExtValue + 'static>(ext: T) -> Self {
+        Value::Custom(Arc::new(ext))
+    }
+
     // ========== Safe Accessors (never panic) ==========
     
     pub fn as_number(&self) -> Option<&Number> {
@@ -61,6 +70,13 @@ impl Value {
         }
     }
 
+    pub fn as_custom<T: FolioExtValue + 'static>(&self) -> Option<&T> {
+        match self {
+            Value::Custom(ext) => ext.as_any().downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
     pub fn as_datetime(&self) -> Option<&FolioDateTime> {
         match self {
             Value::DateTime(dt) => Some(dt),
@@ -83,6 +99,10 @@ impl Value {
         matches!(self, Value::Null)
     }
 
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Value::Custom(_))
+    }
+
     pub fn is_datetime(&self) -> bool {
         matches!(self, Value::DateTime(_))
     }
@@ -101,6 +121,9 @@ impl Value {
                     Value::Error(FolioError::undefined_field(key))
                 })
             }
+            Value::Custom(ext) => ext.field(key).unwrap_or_else(|| {
+                Value::Error(FolioError::undefined_field(key))
+            }),
             Value::Error(e) => Value::Error(e.clone()),
             _ => Value::Error(FolioError::type_error("Object", self.type_name())),
         }
@@ -116,6 +139,7 @@ impl Value {
             Value::Duration(_) => "Duration",
             Value::Object(_) => "Object",
             Value::List(_) => "List",
+            Value::Custom(ext) => ext.type_name(),
             Value::Null => "Null",
             Value::Error(_) => "Error",
         }
@@ -155,6 +179,7 @@ impl Value {
             Value::Null => Value::Bool(false),
             Value::List(l) => Value::Bool(!l.is_empty()),
             Value::Object(o) => Value::Bool(!o.is_empty()),
+            Value::Custom(_) => Value::Bool(true),
             Value::Error(e) => Value::Error(e.clone()),
         }
     }
@@ -247,6 +272,7 @@ impl std::fmt::Display for Value {
                     write!(f, "[{}]", items.len())
                 }
             }
+            Value::Custom(ext) => ext.display(f),
             Value::Null => write!(f, "null"),
             Value::Error(e) => write!(f, "#ERROR: {}", e.code),
         }
@@ -296,6 +322,12 @@ impl From<FolioDateTime> for Value {
     }
 }
 
+impl From<Arc<dyn FolioExtValue>> for Value {
+    fn from(ext: Arc<dyn FolioExtValue>) -> Self {
+        Value::Custom(ext)
+    }
+}
+
 impl From<FolioDuration> for Value {
     fn from(d: FolioDuration) -> Self {
         Value::Duration(d)
@@ -744,7 +744,7 @@ mod tests {
             Value::List(vec![Value::Number(Number::from_i64(3)), Value::Number(Number::from_i64(4))]),
         ])];
         let result = f.call(&args, &eval_ctx());
-        assert!(matches!(result, Value::Object(_)));
+        assert!(crate::is_matrix(&result));
     }
 
     #[test]
@@ -752,7 +752,7 @@ mod tests {
         let f = IdentityFn;
         let args = vec![Value::Number(Number::from_i64(3))];
         let result = f.call(&args, &eval_ctx());
-        assert!(matches!(result, Value::Object(_)));
+        assert!(crate::is_matrix(&result));
     }
 
     #[test]
@@ -763,7 +763,7 @@ mod tests {
             Value::Number(Number::from_i64(3)),
         ];
         let result = f.call(&args, &eval_ctx());
-        assert!(matches!(result, Value::Object(_)));
+        assert!(crate::is_matrix(&result));
     }
 
     #[test]
@@ -775,6 +775,6 @@ mod tests {
             Value::Number(Number::from_i64(3)),
         ])];
         let result = f.call(&args, &eval_ctx());
-        assert!(matches!(result, Value::Object(_)));
+        assert!(crate::is_matrix(&result));
     }
 }
@@ -49,9 +49,11 @@ pub fn extract_number_list(value: &Value, func: &str, arg: &str) -> Result<Vec<N
     }
 }
 
-/// Extract a matrix from a Value (either a Matrix object or nested list)
+/// Extract a matrix from a Value (a Matrix, a Matrix object or a nested list)
 pub fn extract_matrix(value: &Value, func: &str, arg: &str) -> Result<Matrix, FolioError> {
     match value {
+        Value::Custom(_) => value.as_custom::<Matrix>().cloned()
+            .ok_or_else(|| FolioError::arg_type(func, arg, "Matrix", value.type_name())),
         Value::Object(obj) => {
             // Check if it's a Matrix object
             if let Some(Value::Text(t)) = obj.get("type") {
@@ -125,6 +127,17 @@ pub fn extract_vector(value: &Value, func: &str, arg: &str) -> Result<Vector, Fo
             }
             Err(FolioError::arg_type(func, arg, "Vector", "Object"))
         }
+        Value::Custom(_) if value.as_custom::<Matrix>().is_some() => {
+            let m = extract_matrix(value, func, arg)?;
+            if m.rows() == 1 {
+                return Ok(m.get_row(0).unwrap());
+            } else if m.cols() == 1 {
+                return Ok(m.get_col(0).unwrap());
+            }
+            Err(FolioError::domain_error(format!(
+                "{}: {} must be a vector (1×n or n×1 matrix)", func, arg
+            )))
+        }
         _ => Err(FolioError::arg_type(func, arg, "Vector or List", value.type_name())),
     }
 }
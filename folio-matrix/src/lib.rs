@@ -9,6 +9,7 @@
 //! - Decompositions (lu, qr, svd, cholesky, eigen)
 //! - Linear solvers (solve, lstsq)
 //! - Vector operations (dot, cross, outer, angle, project)
+//! - Formula operators (A * B, A + B, 2 * A, A .* B, A ./ B, A', A^T)
 //!
 //! Uses dual precision: exact Number arithmetic for small matrices (≤10×10),
 //! f64 floating point for larger matrices.
@@ -22,9 +23,11 @@ mod decompose;
 mod solve;
 mod norms;
 mod vector_ops;
+mod operators;
 
 pub use types::{Matrix, Vector, MatrixMode};
 pub use helpers::extract_matrix;
+pub use operators::is_matrix;
 
 use folio_plugin::PluginRegistry;
 
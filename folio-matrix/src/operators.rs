@@ -0,0 +1,249 @@
+//! Formula operators on matrices: A + B, A * B, 2 * A, A .* B, A', A^T
+//!
+//! Matrices are extension values, so the evaluator reaches these through the
+//! `FolioExtValue` operator hooks on `Matrix`. Each operator dispatches to
+//! the same plugins users can call by name (matmul, mat_add, scale,
+//! hadamard, ...). Vectors are plain lists: `M * v` treats `v` as a column
+//! and `v * M` treats it as a row.
+
+use std::sync::Arc;
+use folio_plugin::prelude::*;
+use crate::types::{Matrix, Vector, MatrixMode};
+use crate::helpers::*;
+use crate::ops::{
+    MatAddFn, MatSubFn, MatmulFn, ScaleFn, HadamardFn, ElementDivFn,
+    MatPowerFn, InverseFn, TransposeFn,
+};
+
+/// Check if a value is a Matrix
+pub fn is_matrix(value: &Value) -> bool {
+    value.as_custom::<Matrix>().is_some()
+        || matches!(value, Value::Object(obj) if matches!(obj.get("type"), Some(Value::Text(t)) if t == "Matrix"))
+}
+
+/// Apply `matrix op other`, or `other op matrix` when `matrix_on_left` is false
+pub(crate) fn matrix_op(op: ExtOp, matrix: &Matrix, other: &Value, matrix_on_left: bool) -> Value {
+    let ctx = context();
+    let matrix = matrix.to_value();
+    match (is_matrix(other), matrix_on_left) {
+        (true, true) => matrix_matrix(op, &matrix, other, &ctx),
+        (true, false) => matrix_matrix(op, other, &matrix, &ctx),
+        (false, _) => matrix_other(op, &matrix, other, &ctx, matrix_on_left),
+    }
+}
+
+/// `m^T`
+pub(crate) fn transpose(matrix: &Matrix) -> Value {
+    TransposeFn.call(&[matrix.to_value()], &context())
+}
+
+/// The matrix functions ignore their context, and operator hooks have none to pass
+fn context() -> EvalContext {
+    EvalContext::new(Arc::new(PluginRegistry::new()))
+}
+
+fn matrix_matrix(op: ExtOp, left: &Value, right: &Value, ctx: &EvalContext) -> Value {
+    let a = match extract_matrix(left, "operator", "left") { Ok(m) => m, Err(e) => return Value::Error(e) };
+    let b = match extract_matrix(right, "operator", "right") { Ok(m) => m, Err(e) => return Value::Error(e) };
+    let args = [left.clone(), right.clone()];
+
+    match op {
+        ExtOp::Add | ExtOp::Sub | ExtOp::ElemMul | ExtOp::ElemDiv => {
+            if a.rows() != b.rows() || a.cols() != b.cols() {
+                return Value::Error(FolioError::shape_mismatch(format!(
+                    "{} {} {} (element-wise operators need equal shapes)",
+                    shape(&a), op.symbol(), shape(&b)
+                )));
+            }
+            match op {
+                ExtOp::Add => MatAddFn.call(&args, ctx),
+                ExtOp::Sub => MatSubFn.call(&args, ctx),
+                ExtOp::ElemMul => HadamardFn.call(&args, ctx),
+                _ => ElementDivFn.call(&args, ctx),
+            }
+        }
+        ExtOp::Mul => {
+            if let Some(e) = inner_dims_error(&a, &b, op) { return e; }
+            MatmulFn.call(&args, ctx)
+        }
+        ExtOp::Eq | ExtOp::Ne => {
+            let equal = a.rows() == b.rows() && a.cols() == b.cols()
+                && a.to_nested_list() == b.to_nested_list();
+            Value::Bool(equal == (op == ExtOp::Eq))
+        }
+        _ => Value::Error(unsupported(op, "Matrix", "Matrix")),
+    }
+}
+
+/// `matrix op other` (or `other op matrix` when `matrix_on_left` is false)
+fn matrix_other(op: ExtOp, matrix: &Value, other: &Value, ctx: &EvalContext, matrix_on_left: bool) -> Value {
+    match other {
+        Value::Number(k) => {
+            let m = matrix.clone();
+            match (op, matrix_on_left) {
+                (ExtOp::Mul | ExtOp::ElemMul, _) => ScaleFn.call(&[m, other.clone()], ctx),
+                (ExtOp::Div | ExtOp::ElemDiv, true) => match Number::from_i64(1).checked_div(k) {
+                    Ok(inv) => ScaleFn.call(&[m, Value::Number(inv)], ctx),
+                    Err(_) => Value::Error(FolioError::div_zero().with_note("dividing a matrix by zero")),
+                },
+                (ExtOp::Pow, true) => matrix_power(matrix, k, ctx),
+                _ => {
+                    let (l, r) = if matrix_on_left { ("Matrix", "Number") } else { ("Number", "Matrix") };
+                    Value::Error(unsupported(op, l, r)
+                        .with_suggestion("Only Matrix * Number, Number * Matrix and Matrix / Number are defined for scalars"))
+                }
+            }
+        }
+        Value::List(_) if op == ExtOp::Mul => {
+            let a = match extract_matrix(matrix, "operator", "matrix") { Ok(m) => m, Err(e) => return Value::Error(e) };
+            let v = match extract_vector(other, "operator", "vector") { Ok(v) => v, Err(e) => return Value::Error(e) };
+            // M * v uses v as a column, v * M uses it as a row
+            let (vm, result_is_column) = if matrix_on_left {
+                (v.to_column_matrix(), true)
+            } else {
+                (v.to_row_matrix(), false)
+            };
+            let (lhs, rhs) = if matrix_on_left { (&a, &vm) } else { (&vm, &a) };
+            if let Some(e) = inner_dims_error(lhs, rhs, op) { return e; }
+
+            let product = MatmulFn.call(&[lhs.to_value(), rhs.to_value()], ctx);
+            let product = match extract_matrix(&product, "operator", "result") { Ok(m) => m, Err(e) => return Value::Error(e) };
+            let vector = if result_is_column { product.get_col(0) } else { product.get_row(0) };
+            vector
+                .map(|v| Vector::from_list(v.to_list(), MatrixMode::Exact).into())
+                .unwrap_or_else(|| Value::List(Vec::new()))
+        }
+        _ => {
+            let (l, r) = if matrix_on_left { ("Matrix", other.type_name()) } else { (other.type_name(), "Matrix") };
+            Value::Error(unsupported(op, l, r))
+        }
+    }
+}
+
+/// `M ^ n` for integer n; negative powers use the inverse
+fn matrix_power(matrix: &Value, n: &Number, ctx: &EvalContext) -> Value {
+    let Some(n) = n.to_i64() else {
+        return Value::Error(FolioError::domain_error("matrix power requires an integer exponent")
+            .with_suggestion("Use A' for transpose"));
+    };
+    let base = if n < 0 {
+        InverseFn.call(std::slice::from_ref(matrix), ctx)
+    } else {
+        matrix.clone()
+    };
+    if base.is_error() {
+        return base;
+    }
+    MatPowerFn.call(&[base, Value::Number(Number::from_i64(n.abs()))], ctx)
+}
+
+/// SHAPE_MISMATCH error when the inner dimensions of `a * b` differ
+fn inner_dims_error(a: &Matrix, b: &Matrix, op: ExtOp) -> Option<Value> {
+    (a.cols() != b.rows()).then(|| Value::Error(FolioError::shape_mismatch(format!(
+        "{} {} {} (inner dimensions {} and {} differ)",
+        shape(a), op.symbol(), shape(b), a.cols(), b.rows()
+    ))))
+}
+
+fn shape(m: &Matrix) -> String {
+    format!("{}×{}", m.rows(), m.cols())
+}
+
+fn unsupported(op: ExtOp, left: &str, right: &str) -> FolioError {
+    FolioError::type_error(
+        "operands supporting this operator",
+        &format!("{} {} {}", left, op.symbol(), right),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[i64]]) -> Value {
+        let data = rows.iter()
+            .map(|r| r.iter().map(|&x| Number::from_i64(x)).collect())
+            .collect();
+        Matrix::from_nested_list(data, MatrixMode::Auto).unwrap().into()
+    }
+
+    fn numbers(value: &Value) -> Vec<Vec<f64>> {
+        extract_matrix(value, "test", "m").unwrap().to_nested_list().iter()
+            .map(|r| r.iter().map(|n| n.to_f64().unwrap()).collect())
+            .collect()
+    }
+
+    /// Dispatch like the evaluator: left operand's hook first, then the right's
+    fn apply(op: ExtOp, left: &Value, right: &Value) -> Option<Value> {
+        match (left, right) {
+            (Value::Custom(ext), _) => ext.binary_op(op, right),
+            (_, Value::Custom(ext)) => ext.binary_op_rhs(op, left),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_matrices_are_extension_values() {
+        let a = matrix(&[&[1, 2], &[3, 4]]);
+        assert!(is_matrix(&a));
+        assert_eq!(a.type_name(), "Matrix");
+        assert_eq!(a.get("rows").as_number().unwrap().to_i64(), Some(2));
+        assert_eq!(a.to_string(), "[[1.0000, 2.0000], [3.0000, 4.0000]]");
+    }
+
+    #[test]
+    fn test_add_and_matmul() {
+        let a = matrix(&[&[1, 2], &[3, 4]]);
+        let b = matrix(&[&[5, 6], &[7, 8]]);
+        let sum = apply(ExtOp::Add, &a, &b).unwrap();
+        assert_eq!(numbers(&sum), vec![vec![6.0, 8.0], vec![10.0, 12.0]]);
+        let product = apply(ExtOp::Mul, &a, &b).unwrap();
+        assert_eq!(numbers(&product), vec![vec![19.0, 22.0], vec![43.0, 50.0]]);
+        let hadamard = apply(ExtOp::ElemMul, &a, &b).unwrap();
+        assert_eq!(numbers(&hadamard), vec![vec![5.0, 12.0], vec![21.0, 32.0]]);
+    }
+
+    #[test]
+    fn test_scalar_and_vector() {
+        let a = matrix(&[&[1, 2], &[3, 4]]);
+        let two = Value::Number(Number::from_i64(2));
+        let scaled = apply(ExtOp::Mul, &two, &a).unwrap();
+        assert_eq!(numbers(&scaled), vec![vec![2.0, 4.0], vec![6.0, 8.0]]);
+
+        let v = Value::List(vec![Value::Number(Number::from_i64(1)), Value::Number(Number::from_i64(1))]);
+        let column = apply(ExtOp::Mul, &a, &v).unwrap();
+        let column: Vec<i64> = column.as_list().unwrap().iter()
+            .map(|x| x.as_number().unwrap().to_f64().unwrap().round() as i64).collect();
+        assert_eq!(column, vec![3, 7]);
+    }
+
+    #[test]
+    fn test_shape_mismatch() {
+        let a = matrix(&[&[1, 2, 3], &[4, 5, 6]]);
+        let err = apply(ExtOp::Mul, &a, &a).unwrap();
+        match err {
+            Value::Error(e) => {
+                assert_eq!(e.code, codes::SHAPE_MISMATCH);
+                assert!(e.message.contains("2×3 * 2×3"), "{}", e.message);
+            }
+            other => panic!("expected error, got {:?}", other),
+        }
+        let b = matrix(&[&[1, 2], &[3, 4]]);
+        let err = apply(ExtOp::Add, &a, &b).unwrap();
+        assert!(matches!(err, Value::Error(e) if e.code == codes::SHAPE_MISMATCH));
+    }
+
+    #[test]
+    fn test_power_negation_and_transpose() {
+        let a = matrix(&[&[1, 2], &[3, 4]]);
+        let two = Value::Number(Number::from_i64(2));
+        let squared = apply(ExtOp::Pow, &a, &two).unwrap();
+        assert_eq!(numbers(&squared), vec![vec![7.0, 10.0], vec![15.0, 22.0]]);
+
+        let Value::Custom(ext) = &a else { panic!("matrix should be an extension value") };
+        let t = ext.transpose().unwrap();
+        assert_eq!(numbers(&t), vec![vec![1.0, 3.0], vec![2.0, 4.0]]);
+        let negated = ext.neg().unwrap();
+        assert_eq!(numbers(&negated), vec![vec![-1.0, -2.0], vec![-3.0, -4.0]]);
+    }
+}
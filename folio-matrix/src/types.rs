@@ -1,7 +1,9 @@
 //! Core matrix and vector types
 
+use std::any::Any;
 use std::fmt;
-use folio_core::{Number, Value, FolioError};
+use folio_core::{Number, Value, FolioError, FolioExtValue, ExtOp};
+use crate::operators;
 use nalgebra::{DMatrix, DVector};
 use serde::{Serialize, Deserialize};
 
@@ -401,20 +403,65 @@ impl fmt::Display for Vector {
 /// Convert Matrix to Value
 impl From<Matrix> for Value {
     fn from(m: Matrix) -> Value {
-        let mut obj = std::collections::HashMap::new();
-        obj.insert("type".to_string(), Value::Text("Matrix".to_string()));
-        obj.insert("rows".to_string(), Value::Number(Number::from_i64(m.rows() as i64)));
-        obj.insert("cols".to_string(), Value::Number(Number::from_i64(m.cols() as i64)));
-
-        // Store data as nested list
-        let data: Vec<Value> = m.to_nested_list().iter()
-            .map(|row| {
-                Value::List(row.iter().map(|n| Value::Number(n.clone())).collect())
-            })
-            .collect();
-        obj.insert("data".to_string(), Value::List(data));
+        Value::custom(m)
+    }
+}
+
+/// Matrices are extension values: formula operators reach them through the
+/// hooks below, and `m.rows`, `m.cols` and `m.data` read their shape and cells
+impl FolioExtValue for Matrix {
+    fn type_name(&self) -> &'static str {
+        "Matrix"
+    }
+
+    fn display(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "Matrix",
+            "rows": self.rows(),
+            "cols": self.cols(),
+            "data": self.to_nested_list().iter()
+                .map(|row| row.iter().map(|n| n.to_string()).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "rows" => Some(Value::Number(Number::from_i64(self.rows() as i64))),
+            "cols" => Some(Value::Number(Number::from_i64(self.cols() as i64))),
+            "data" => Some(Value::List(self.to_nested_list().into_iter()
+                .map(|row| Value::List(row.into_iter().map(Value::Number).collect()))
+                .collect())),
+            _ => None,
+        }
+    }
+
+    fn field_names(&self) -> Vec<String> {
+        vec!["rows".to_string(), "cols".to_string(), "data".to_string()]
+    }
+
+    fn binary_op(&self, op: ExtOp, rhs: &Value) -> Option<Value> {
+        Some(operators::matrix_op(op, self, rhs, true))
+    }
+
+    fn binary_op_rhs(&self, op: ExtOp, lhs: &Value) -> Option<Value> {
+        Some(operators::matrix_op(op, self, lhs, false))
+    }
+
+    fn neg(&self) -> Option<Value> {
+        Some(operators::matrix_op(ExtOp::Mul, self, &Value::Number(Number::from_i64(-1)), true))
+    }
+
+    fn transpose(&self) -> Option<Value> {
+        Some(operators::transpose(self))
+    }
 
-        Value::Object(obj)
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
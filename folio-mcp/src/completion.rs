@@ -0,0 +1,153 @@
+//! Argument completion
+//!
+//! `completion/complete` suggests values while a client fills in a prompt
+//! argument or a resource template from `resources/templates/list`: document
+//! names (what `eval_file` takes), function names (what `folio(name)` takes)
+//! and unit names. Matching is a case-insensitive prefix; for functions with no
+//! match, the most similar names are offered instead.
+
+use crate::{list_fmd_files, list_prompts, McpError};
+use folio::Folio;
+use serde_json::{json, Value as JsonValue};
+
+/// Values per response, as MCP allows
+pub const MAX_VALUES: usize = 100;
+
+pub const DOCUMENTS: &str = "folio://documents/{name}";
+pub const FUNCTIONS: &str = "folio://functions/{name}";
+#[cfg_attr(not(feature = "units"), allow(dead_code))]
+pub const UNIT_NAMES: &str = "folio://units/{name}";
+
+/// Entries for `resources/templates/list`
+pub fn templates() -> JsonValue {
+    #[allow(unused_mut)]
+    let mut templates = vec![
+        json!({
+            "uriTemplate": DOCUMENTS,
+            "name": "document",
+            "description": "A .fmd document by name, as eval_file takes it",
+            "mimeType": "text/markdown"
+        }),
+        json!({
+            "uriTemplate": FUNCTIONS,
+            "name": "function",
+            "description": "Help for a function, command or constant, as folio(name) gives it",
+            "mimeType": "text/markdown"
+        }),
+    ];
+    #[cfg(feature = "units")]
+    templates.push(json!({
+        "uriTemplate": UNIT_NAMES,
+        "name": "unit",
+        "description": "A unit by symbol or alias, with its category and SI factor",
+        "mimeType": "text/markdown"
+    }));
+    json!({ "resourceTemplates": templates })
+}
+
+pub fn complete(folio: &Folio, params: &Option<JsonValue>) -> Result<JsonValue, McpError> {
+    let params = params.as_ref().ok_or_else(|| invalid("Missing params".to_string()))?;
+    let reference = params.get("ref").ok_or_else(|| invalid("Missing ref parameter".to_string()))?;
+    let argument = params.get("argument").and_then(|a| a.get("name")).and_then(|n| n.as_str())
+        .ok_or_else(|| invalid("Missing argument.name parameter".to_string()))?;
+    let value = params.get("argument").and_then(|a| a.get("value")).and_then(|v| v.as_str()).unwrap_or("");
+
+    let values = match reference.get("type").and_then(|t| t.as_str()) {
+        Some("ref/resource") => {
+            let uri = reference.get("uri").and_then(|u| u.as_str()).unwrap_or("");
+            resource_values(folio, uri, value)?
+        }
+        Some("ref/prompt") => {
+            let name = reference.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            prompt_values(name, argument, value)?
+        }
+        other => return Err(invalid(format!("Unsupported ref type: {}", other.unwrap_or("none")))),
+    };
+    Ok(result(values))
+}
+
+fn resource_values(folio: &Folio, uri: &str, value: &str) -> Result<Vec<String>, McpError> {
+    match uri {
+        DOCUMENTS => Ok(matching(list_fmd_files().into_iter().map(|f| f.name), value)),
+        FUNCTIONS => Ok(folio.complete(value)),
+        #[cfg(feature = "units")]
+        UNIT_NAMES => Ok(matching(folio_units::UNITS.names().into_iter().map(String::from), value)),
+        _ => Err(invalid(format!("Unknown resource template: {}", uri))),
+    }
+}
+
+fn prompt_values(name: &str, argument: &str, value: &str) -> Result<Vec<String>, McpError> {
+    let prompts = list_prompts();
+    let prompt = prompts.iter().find(|p| p.name == name)
+        .ok_or_else(|| invalid(format!("Unknown prompt: {}", name)))?;
+    let argument = prompt.arguments.iter().find(|a| a.name == argument)
+        .ok_or_else(|| invalid(format!("Prompt '{}' has no argument '{}'", name, argument)))?;
+    Ok(argument.suggestions().into_iter().filter(|s| starts_with(s, value)).collect())
+}
+
+/// Sorted, distinct candidates starting with `prefix`
+fn matching(candidates: impl Iterator<Item = String>, prefix: &str) -> Vec<String> {
+    let mut values: Vec<String> = candidates.filter(|c| starts_with(c, prefix)).collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
+fn starts_with(candidate: &str, prefix: &str) -> bool {
+    candidate.to_lowercase().starts_with(&prefix.to_lowercase())
+}
+
+fn result(mut values: Vec<String>) -> JsonValue {
+    let total = values.len();
+    values.truncate(MAX_VALUES);
+    json!({
+        "completion": { "values": values, "total": total, "hasMore": total > MAX_VALUES }
+    })
+}
+
+fn invalid(message: String) -> McpError {
+    McpError { code: -32602, message, data: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(response: &JsonValue) -> Vec<&str> {
+        response["completion"]["values"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect()
+    }
+
+    fn request(uri: &str, value: &str) -> Option<JsonValue> {
+        Some(json!({ "ref": { "type": "ref/resource", "uri": uri }, "argument": { "name": "name", "value": value } }))
+    }
+
+    #[test]
+    fn test_complete_functions() {
+        let folio = Folio::default();
+        let functions = complete(&folio, &request(FUNCTIONS, "SQ")).unwrap();
+        assert!(values(&functions).contains(&"sqrt"));
+        assert!(values(&functions).iter().all(|v| v.starts_with("sq")));
+    }
+
+    #[test]
+    #[cfg(feature = "units")]
+    fn test_complete_units() {
+        let folio = Folio::default();
+        let units = complete(&folio, &request(UNIT_NAMES, "kilom")).unwrap();
+        assert!(values(&units).contains(&"kilometres"));
+        assert_eq!(units["completion"]["hasMore"], false);
+
+        let everything = complete(&folio, &request(UNIT_NAMES, "")).unwrap();
+        assert_eq!(values(&everything).len(), MAX_VALUES);
+        assert_eq!(everything["completion"]["hasMore"], true);
+        assert!(everything["completion"]["total"].as_u64().unwrap() > MAX_VALUES as u64);
+    }
+
+    #[test]
+    fn test_rejects_unknown_refs() {
+        let folio = Folio::default();
+        assert_eq!(complete(&folio, &request("folio://nowhere/{name}", "")).unwrap_err().code, -32602);
+        let params = Some(json!({ "ref": { "type": "ref/tool" }, "argument": { "name": "x", "value": "" } }));
+        assert_eq!(complete(&folio, &params).unwrap_err().code, -32602);
+    }
+}
@@ -0,0 +1,260 @@
+//! Concurrent, cancellable request handling
+//!
+//! Both transports hand every message to a [`Dispatcher`] from a worker, so a
+//! slow `eval` doesn't hold up `ping` or `tools/list`. Each request runs with
+//! its own [`CancelToken`]; `notifications/cancelled` naming its id cancels it,
+//! and the evaluator stops at the next cell or function call. A cancelled
+//! request is answered with a `-32800` error rather than partial results.
+//!
+//! Workers come from the blocking pool of [`runtime`], which caps how many
+//! requests run at once; the rest wait for a free worker. An id may only be in
+//! flight once per client, so a cancellation always names one request.
+//!
+//! When a request carries `_meta.progressToken`, evaluations report
+//! `notifications/progress` through the transport's [`Notifier`]. Changes that
+//! concern every client, like a saved document, go to the broadcast notifier.
+
+use crate::notebook::Notebooks;
+use crate::{handle_request, McpError, McpRequest, McpResponse};
+use folio::{CancelToken, Folio};
+use serde_json::{json, Value as JsonValue};
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::HashMap;
+use std::io;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// JSON-RPC error code for a request cancelled by the client
+pub const REQUEST_CANCELLED: i32 = -32800;
+
+/// Requests evaluated at once, across clients
+pub const MAX_WORKERS: usize = 64;
+
+/// Sends a server-to-client notification while a request runs
+pub type Notifier = Arc<dyn Fn(JsonValue) + Send + Sync>;
+
+pub struct Dispatcher {
+    folio: Folio,
+    /// Running requests by (scope, JSON-encoded id)
+    in_flight: Mutex<HashMap<(String, String), CancelToken>>,
+    /// Reaches every connected client
+    broadcast: Notifier,
+    notebooks: Arc<Notebooks>,
+}
+
+/// What a handler can use besides its arguments
+pub struct RequestContext {
+    pub progress: ProgressReporter,
+    pub notebooks: Arc<Notebooks>,
+    broadcast: Notifier,
+}
+
+impl RequestContext {
+    /// Ask every client to refresh `resources/list`
+    pub fn resources_changed(&self) {
+        (self.broadcast)(json!({ "jsonrpc": "2.0", "method": "notifications/resources/list_changed" }));
+    }
+}
+
+impl Dispatcher {
+    pub fn new(folio: Folio, broadcast: Notifier) -> Self {
+        let limits = &crate::config::get().limits;
+        let notebooks = Arc::new(Notebooks::new(limits.session_ttl(), limits.max_sessions));
+        Self { folio, in_flight: Mutex::new(HashMap::new()), broadcast, notebooks }
+    }
+
+    fn context(&self, progress: ProgressReporter) -> RequestContext {
+        RequestContext { progress, notebooks: self.notebooks.clone(), broadcast: self.broadcast.clone() }
+    }
+
+    /// Handle one message, blocking until it is done
+    ///
+    /// `scope` keeps request ids apart between clients (the HTTP session id;
+    /// empty for stdio). Notifications return `None`.
+    pub fn handle(&self, scope: &str, request: &McpRequest, notify: Notifier) -> Option<McpResponse> {
+        if request.method == "notifications/cancelled" {
+            if let Some(id) = request.params.as_ref().and_then(|p| p.get("requestId")) {
+                self.cancel(scope, id);
+            }
+            return None;
+        }
+        if request.id.is_none() {
+            handle_request(&self.folio, request, &self.context(ProgressReporter::default()));
+            return None;
+        }
+        match self.register(scope, request) {
+            Ok(token) => Some(self.run(scope, request, token, notify)),
+            Err(response) => Some(response),
+        }
+    }
+
+    /// Mark a request as in flight, so a cancellation read right after it
+    /// finds it even if its worker hasn't started yet. A request reusing the
+    /// id of one still running gets the error response back instead.
+    pub fn register(&self, scope: &str, request: &McpRequest) -> Result<CancelToken, McpResponse> {
+        match self.in_flight.lock().unwrap().entry(key(scope, &request.id)) {
+            Entry::Vacant(slot) => Ok(slot.insert(CancelToken::new()).clone()),
+            Entry::Occupied(_) => Err(McpResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id.clone(),
+                result: None,
+                error: Some(McpError {
+                    code: -32600,
+                    message: format!("Request id {} is already in flight", request.id.as_ref().map(|id| id.to_string()).unwrap_or_default()),
+                    data: None,
+                }),
+            }),
+        }
+    }
+
+    /// Run a [`register`](Self::register)ed request
+    pub fn run(&self, scope: &str, request: &McpRequest, token: CancelToken, notify: Notifier) -> McpResponse {
+        let context = self.context(ProgressReporter::new(request, notify));
+        let response = handle_request(&self.folio.clone().with_cancel(token.clone()), request, &context);
+        self.in_flight.lock().unwrap().remove(&key(scope, &request.id));
+
+        if token.is_cancelled() {
+            eprintln!("Cancelled: {}", request.method);
+            return McpResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id.clone(),
+                result: None,
+                error: Some(McpError { code: REQUEST_CANCELLED, message: "Request cancelled".to_string(), data: None }),
+            };
+        }
+        response
+    }
+
+    fn cancel(&self, scope: &str, id: &JsonValue) {
+        if let Some(token) = self.in_flight.lock().unwrap().get(&key(scope, &Some(id.clone()))) {
+            token.cancel();
+        }
+    }
+
+    /// Cancel everything still running for `scope`, e.g. when its session ends
+    pub fn cancel_scope(&self, scope: &str) {
+        for ((s, _), token) in self.in_flight.lock().unwrap().iter() {
+            if s == scope {
+                token.cancel();
+            }
+        }
+    }
+}
+
+/// Runtime whose blocking pool runs requests, at most [`MAX_WORKERS`] at once
+pub fn runtime() -> io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .max_blocking_threads(MAX_WORKERS)
+        .build()
+}
+
+fn key(scope: &str, id: &Option<JsonValue>) -> (String, String) {
+    (scope.to_string(), id.as_ref().map(|id| id.to_string()).unwrap_or_default())
+}
+
+/// 128 bits from the randomly seeded std hasher; unguessable enough for
+/// HTTP and notebook sessions, while the bearer token is what guards access
+pub fn new_session_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let half = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u64(count);
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", half(), half())
+}
+
+/// `notifications/progress` for one request; inert unless the client sent a `progressToken`
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    target: Option<(JsonValue, Notifier)>,
+    /// Highest progress sent, since MCP requires it to increase
+    last: Arc<AtomicUsize>,
+}
+
+impl ProgressReporter {
+    pub fn new(request: &McpRequest, notify: Notifier) -> Self {
+        let token = request.params.as_ref()
+            .and_then(|p| p.get("_meta"))
+            .and_then(|m| m.get("progressToken"))
+            .cloned();
+        Self { target: token.map(|t| (t, notify)), last: Arc::default() }
+    }
+
+    pub fn report(&self, progress: usize, total: usize, message: String) {
+        let Some((token, notify)) = &self.target else {
+            return;
+        };
+        if self.last.fetch_max(progress, Ordering::Relaxed) >= progress {
+            return;
+        }
+        notify(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": { "progressToken": token, "progress": progress, "total": total, "message": message }
+        }));
+    }
+
+    /// `folio` reporting cells evaluated out of total
+    pub fn cells(&self, folio: &Folio) -> Folio {
+        if self.target.is_none() {
+            return folio.clone();
+        }
+        let reporter = self.clone();
+        folio.clone().with_progress(move |p| reporter.report(p.done, p.total, format!("{}/{} cells", p.done, p.total)))
+    }
+
+    /// `folio` for set `index` of a batch of `count`; progress counts cells
+    /// across the whole batch, so each completed set is a step forward
+    pub fn batch_item(&self, folio: &Folio, index: usize, count: usize) -> Folio {
+        if self.target.is_none() {
+            return folio.clone();
+        }
+        let reporter = self.clone();
+        folio.clone().with_progress(move |p| {
+            let message = if p.done == p.total {
+                format!("{}/{} sets", index + 1, count)
+            } else {
+                format!("set {}/{}: {}/{} cells", index + 1, count, p.done, p.total)
+            };
+            reporter.report(index * p.total + p.done, count * p.total, message)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: u64) -> McpRequest {
+        McpRequest { jsonrpc: "2.0".to_string(), id: Some(json!(id)), method: "ping".to_string(), params: None }
+    }
+
+    #[test]
+    fn test_duplicate_ids_rejected() {
+        let dispatcher = Dispatcher::new(Folio::default(), Arc::new(|_| {}));
+        let first = dispatcher.register("", &request(1)).unwrap();
+        let duplicate = dispatcher.register("", &request(1)).unwrap_err();
+        assert_eq!(duplicate.error.unwrap().code, -32600);
+        assert!(dispatcher.register("other", &request(1)).is_ok());
+
+        // The first request still owns the id until it finishes
+        dispatcher.cancel("", &json!(1));
+        assert!(first.is_cancelled());
+        dispatcher.run("", &request(1), first, Arc::new(|_| {}));
+        assert!(dispatcher.register("", &request(1)).is_ok());
+    }
+
+    #[test]
+    fn test_session_ids_differ() {
+        let a = new_session_id();
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, new_session_id());
+    }
+}
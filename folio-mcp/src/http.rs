@@ -0,0 +1,463 @@
+//! Streamable HTTP transport
+//!
+//! A single endpoint (`/mcp`) takes one JSON-RPC message per POST. Requests
+//! are answered with `application/json`, or with an SSE stream when the client
+//! accepts `text/event-stream`: progress notifications as they happen, then the
+//! response. Notifications and client responses
+//! get `202 Accepted`. `initialize` issues an `Mcp-Session-Id` that every later
+//! request must carry, and DELETE ends the session. A GET with the session id
+//! opens a standalone SSE stream for server-initiated notifications such as
+//! `notifications/resources/list_changed`. Sessions close after
+//! `limits.session_ttl_secs` idle and at most `limits.max_sessions` are open at
+//! once. Each connection runs on its
+//! own task and evaluation runs on the blocking pool, so sessions don't wait on
+//! each other. Connections are closed after every response.
+
+use crate::dispatch::{new_session_id, Dispatcher, Notifier};
+use crate::{McpError, McpRequest, McpResponse};
+use folio::Folio;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+const ENDPOINT: &str = "/mcp";
+const SESSION_HEADER: &str = "mcp-session-id";
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+/// Time allowed to send a whole request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Settings for [`serve`]
+pub struct HttpConfig {
+    /// Address to bind, e.g. `127.0.0.1:8080`; port 0 picks a free port
+    pub addr: String,
+    /// When set, every request must send `Authorization: Bearer <token>`
+    pub token: Option<String>,
+}
+
+/// Open sessions by id
+type Sessions = Arc<Mutex<HashMap<String, Session>>>;
+
+struct Session {
+    /// Standalone GET streams
+    streams: Vec<mpsc::UnboundedSender<String>>,
+    last_used: Instant,
+}
+
+struct Server {
+    dispatcher: Dispatcher,
+    token: Option<String>,
+    sessions: Sessions,
+}
+
+/// Run the HTTP transport until the process is stopped
+pub fn serve(folio: Folio, config: HttpConfig) -> io::Result<()> {
+    let sessions = Sessions::default();
+    let streams = sessions.clone();
+    let broadcast: Notifier = Arc::new(move |message| {
+        let data = message.to_string();
+        for session in streams.lock().unwrap().values_mut() {
+            session.streams.retain(|tx| tx.send(data.clone()).is_ok());
+        }
+    });
+    let dispatcher = Dispatcher::new(folio, broadcast);
+
+    let runtime = crate::dispatch::runtime()?;
+    runtime.block_on(async move {
+        let listener = TcpListener::bind(&config.addr).await?;
+        eprintln!("Listening on http://{}{}", listener.local_addr()?, ENDPOINT);
+        if config.token.is_some() {
+            eprintln!("Bearer token required");
+        }
+
+        let server = Arc::new(Server {
+            dispatcher,
+            token: config.token,
+            sessions,
+        });
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(server, stream).await {
+                    eprintln!("Connection {}: {}", peer, e);
+                }
+            });
+        }
+    })
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    /// Names lowercased
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+struct HttpResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Body,
+}
+
+enum Body {
+    Bytes(Vec<u8>),
+    /// JSON-RPC messages sent as SSE events until the channel closes
+    Events(mpsc::UnboundedReceiver<String>),
+}
+
+impl HttpResponse {
+    fn empty(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Body::Bytes(Vec::new()) }
+    }
+
+    fn json(status: u16, body: &impl serde::Serialize) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: Body::Bytes(serde_json::to_vec(body).unwrap_or_default()),
+        }
+    }
+
+    /// JSON-RPC error without an id, for requests rejected before dispatch
+    fn rpc_error(status: u16, code: i32, message: &str) -> Self {
+        Self::json(status, &json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": code, "message": message }
+        }))
+    }
+
+    fn sse(message: &impl serde::Serialize) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send(serde_json::to_string(message).unwrap_or_default());
+        Self::stream(rx)
+    }
+
+    fn stream(events: mpsc::UnboundedReceiver<String>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![
+                ("Content-Type", "text/event-stream".to_string()),
+                ("Cache-Control", "no-cache".to_string()),
+            ],
+            body: Body::Events(events),
+        }
+    }
+
+    fn with_header(mut self, name: &'static str, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+async fn handle_connection(server: Arc<Server>, mut stream: TcpStream) -> io::Result<()> {
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await
+        .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "Timed out reading the request")));
+    let response = match request {
+        Ok(Some(request)) => route(server, request).await,
+        Ok(None) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => HttpResponse::rpc_error(400, -32600, &e.to_string()),
+        Err(e) => return Err(e),
+    };
+    write_response(&mut stream, response).await
+}
+
+/// Read one request; `None` when the client closed without sending anything
+async fn read_request(stream: &mut TcpStream) -> io::Result<Option<HttpRequest>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(m), Some(t)) => (m.to_string(), t.to_string()),
+        _ => return Err(invalid("Malformed request line")),
+    };
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    let mut header_bytes = line.len();
+    loop {
+        line.clear();
+        let n = reader.read_line(&mut line).await?;
+        header_bytes += n;
+        if n == 0 || header_bytes > MAX_HEADER_BYTES {
+            return Err(invalid("Incomplete or oversized headers"));
+        }
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = trimmed.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = HttpRequest { method, path, headers, body: Vec::new() };
+    if request.header("transfer-encoding").is_some() {
+        return Err(invalid("Chunked request bodies are not supported; send Content-Length"));
+    }
+    let length = match request.header("content-length") {
+        Some(value) => value.parse::<usize>().map_err(|_| invalid("Invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(invalid("Request body too large"));
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body).await?;
+    Ok(Some(request))
+}
+
+async fn write_response(stream: &mut TcpStream, response: HttpResponse) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", response.status, reason(response.status));
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    match response.body {
+        Body::Bytes(body) => {
+            head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(&body).await?;
+        }
+        // No length: the stream ends when the connection closes
+        Body::Events(mut events) => {
+            head.push_str("Connection: close\r\n\r\n");
+            stream.write_all(head.as_bytes()).await?;
+            while let Some(data) = events.recv().await {
+                stream.write_all(format!("event: message\ndata: {}\n\n", data).as_bytes()).await?;
+                stream.flush().await?;
+            }
+        }
+    }
+    stream.flush().await?;
+    stream.shutdown().await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+async fn route(server: Arc<Server>, request: HttpRequest) -> HttpResponse {
+    if request.path != ENDPOINT {
+        return HttpResponse::empty(404);
+    }
+    if !server.authorized(&request) {
+        return HttpResponse::rpc_error(401, -32001, "Missing or invalid bearer token")
+            .with_header("WWW-Authenticate", "Bearer".to_string());
+    }
+    if server.token.is_none() && request.header("origin").is_some_and(|o| !is_local_origin(o)) {
+        return HttpResponse::rpc_error(403, -32001, "Cross-origin requests need a bearer token");
+    }
+
+    match request.method.as_str() {
+        "POST" => handle_post(server, request).await,
+        "GET" => {
+            if !request.header("accept").is_some_and(|a| a.contains("text/event-stream")) {
+                return HttpResponse::empty(405).with_header("Allow", "GET, POST, DELETE".to_string());
+            }
+            match request.header(SESSION_HEADER) {
+                Some(id) => match server.session(id, |session| {
+                    let (tx, rx) = mpsc::unbounded_channel();
+                    session.streams.push(tx);
+                    rx
+                }) {
+                    Some(rx) => HttpResponse::stream(rx),
+                    None => HttpResponse::rpc_error(404, -32001, "Unknown session"),
+                },
+                None => HttpResponse::rpc_error(400, -32600, "Missing Mcp-Session-Id header"),
+            }
+        }
+        // Dropping the session's senders ends its GET streams
+        "DELETE" => match request.header(SESSION_HEADER) {
+            Some(id) if server.sessions.lock().unwrap().remove(id).is_some() => {
+                server.dispatcher.cancel_scope(id);
+                eprintln!("Session closed: {}", id);
+                HttpResponse::empty(200)
+            }
+            Some(_) => HttpResponse::rpc_error(404, -32001, "Unknown session"),
+            None => HttpResponse::rpc_error(400, -32600, "Missing Mcp-Session-Id header"),
+        },
+        _ => HttpResponse::empty(405).with_header("Allow", "GET, POST, DELETE".to_string()),
+    }
+}
+
+async fn handle_post(server: Arc<Server>, request: HttpRequest) -> HttpResponse {
+    let message: JsonValue = match serde_json::from_slice(&request.body) {
+        Ok(m) => m,
+        Err(e) => return HttpResponse::rpc_error(400, -32700, &format!("Parse error: {}", e)),
+    };
+    if message.is_array() {
+        return HttpResponse::rpc_error(400, -32600, "Batched messages are not supported");
+    }
+    // A client's response to a server request; nothing is waiting for it
+    if message.get("method").is_none() {
+        return HttpResponse::empty(202);
+    }
+    let mcp_request: McpRequest = match serde_json::from_value(message) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::rpc_error(400, -32600, &format!("Invalid request: {}", e)),
+    };
+
+    let initialize = mcp_request.method == "initialize";
+    if !initialize {
+        match request.header(SESSION_HEADER) {
+            Some(id) if server.session(id, |_| ()).is_some() => {}
+            Some(_) => return HttpResponse::rpc_error(404, -32001, "Unknown session"),
+            None => return HttpResponse::rpc_error(400, -32600, "Missing Mcp-Session-Id header"),
+        }
+    }
+
+    eprintln!("Processing: {}", mcp_request.method);
+    let worker = server.clone();
+    // Before a session exists each request is its own client
+    let scope = request.header(SESSION_HEADER).map_or_else(new_session_id, str::to_string);
+    let accepts_sse = request.header("accept").is_some_and(|a| a.contains("text/event-stream"));
+
+    // Stream notifications ahead of the response; initialize stays
+    // unstreamed because its session header depends on the outcome
+    if accepts_sse && !initialize && mcp_request.id.is_some() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let events = tx.clone();
+        let notify: Notifier = Arc::new(move |message| {
+            let _ = events.send(message.to_string());
+        });
+        tokio::task::spawn_blocking(move || {
+            let response = match worker.dispatcher.register(&scope, &mcp_request) {
+                Ok(token) => worker.dispatcher.run(&scope, &mcp_request, token, notify),
+                Err(response) => response,
+            };
+            let _ = tx.send(serde_json::to_string(&response).unwrap_or_default());
+        });
+        return HttpResponse::stream(rx);
+    }
+
+    let response = match tokio::task::spawn_blocking(move || worker.dispatcher.handle(&scope, &mcp_request, Arc::new(|_| {}))).await {
+        Ok(Some(response)) => response,
+        Ok(None) => return HttpResponse::empty(202),
+        Err(e) => McpResponse {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            result: None,
+            error: Some(McpError { code: -32603, message: format!("Internal error: {}", e), data: None }),
+        },
+    };
+
+    let session = if initialize && response.error.is_none() {
+        match server.open_session() {
+            Some(id) => Some(id),
+            None => {
+                let max = crate::config::get().limits.max_sessions;
+                return HttpResponse::rpc_error(503, -32001, &format!("{} sessions are already open", max));
+            }
+        }
+    } else {
+        None
+    };
+
+    let http_response = if accepts_sse { HttpResponse::sse(&response) } else { HttpResponse::json(200, &response) };
+    match session {
+        Some(id) => http_response.with_header("Mcp-Session-Id", id),
+        None => http_response,
+    }
+}
+
+impl Server {
+    /// Run `f` on an open session and mark it used; `None` when it is unknown or expired
+    fn session<T>(&self, id: &str, f: impl FnOnce(&mut Session) -> T) -> Option<T> {
+        let mut sessions = self.sessions.lock().unwrap();
+        self.expire(&mut sessions);
+        let session = sessions.get_mut(id)?;
+        session.last_used = Instant::now();
+        Some(f(session))
+    }
+
+    /// Start a session, or `None` when `limits.max_sessions` are already open
+    fn open_session(&self) -> Option<String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        self.expire(&mut sessions);
+        if sessions.len() >= crate::config::get().limits.max_sessions {
+            return None;
+        }
+        let id = new_session_id();
+        sessions.insert(id.clone(), Session { streams: Vec::new(), last_used: Instant::now() });
+        eprintln!("Session opened: {}", id);
+        Some(id)
+    }
+
+    /// Close sessions idle longer than `limits.session_ttl_secs`
+    fn expire(&self, sessions: &mut HashMap<String, Session>) {
+        let ttl = crate::config::get().limits.session_ttl();
+        sessions.retain(|id, session| {
+            let open = session.last_used.elapsed() < ttl;
+            if !open {
+                self.dispatcher.cancel_scope(id);
+                eprintln!("Session expired: {}", id);
+            }
+            open
+        });
+    }
+
+    fn authorized(&self, request: &HttpRequest) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        request.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split("://").nth(1).unwrap_or(origin);
+    let host = host.split('/').next().unwrap_or(host);
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if port.chars().all(|c| c.is_ascii_digit()) => h,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_origin() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://127.0.0.1"));
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("https://evil.example"));
+        assert!(!is_local_origin("http://localhost.evil.example"));
+    }
+}
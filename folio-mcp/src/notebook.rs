@@ -0,0 +1,194 @@
+//! Stateful notebook sessions
+//!
+//! `open_session` parses a template once and keeps the [`Document`], its inputs
+//! and the last evaluation in memory under a random id. Edits re-evaluate the
+//! document and answer with an [`EvalDiff`] against the previous evaluation, so
+//! a client sees only what moved. Sessions idle for longer than the TTL are
+//! dropped the next time any session is looked up.
+
+use crate::dispatch::new_session_id;
+use folio::{Document, EvalDiff, EvalResult, Folio};
+use folio_core::{codes, FolioError, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const SESSION_NOT_FOUND: &str = "SESSION_NOT_FOUND";
+pub const SESSION_LIMIT: &str = "SESSION_LIMIT";
+
+/// Idle time after which a session is closed, unless configured
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Open sessions per server, unless configured
+pub const MAX_SESSIONS: usize = 64;
+
+pub struct Notebook {
+    pub document: Document,
+    pub variables: HashMap<String, Value>,
+    pub result: EvalResult,
+}
+
+impl Notebook {
+    pub fn open(folio: &Folio, template: &str, variables: HashMap<String, Value>) -> Result<Self, FolioError> {
+        let document = Document::parse(template)?;
+        let result = evaluate(folio, &document, &variables)?;
+        Ok(Self { document, variables, result })
+    }
+
+    /// Set a cell's formula, adding the cell if it is new; returns whether it
+    /// was added and what changed
+    pub fn set_cell(&mut self, folio: &Folio, name: &str, formula: &str) -> Result<(bool, EvalDiff), FolioError> {
+        let mut document = self.document.clone();
+        let added = document.set_cell(name, formula)?;
+        let diff = self.commit(folio, document, self.variables.clone())?;
+        Ok((added, diff))
+    }
+
+    /// Merge inputs; `None` removes one
+    pub fn set_inputs(&mut self, folio: &Folio, inputs: HashMap<String, Option<Value>>) -> Result<EvalDiff, FolioError> {
+        let mut variables = self.variables.clone();
+        for (name, value) in inputs {
+            match value {
+                Some(value) => variables.insert(name, value),
+                None => variables.remove(&name),
+            };
+        }
+        self.commit(folio, self.document.clone(), variables)
+    }
+
+    /// Keep the new state only if its evaluation ran to completion
+    fn commit(&mut self, folio: &Folio, document: Document, variables: HashMap<String, Value>) -> Result<EvalDiff, FolioError> {
+        let result = evaluate(folio, &document, &variables)?;
+        let diff = EvalDiff::between(&self.result, &result);
+        *self = Self { document, variables, result };
+        Ok(diff)
+    }
+}
+
+fn evaluate(folio: &Folio, document: &Document, variables: &HashMap<String, Value>) -> Result<EvalResult, FolioError> {
+    let result = folio.eval_document(document, variables);
+    if result.values.values().any(|v| matches!(v, Value::Error(e) if e.code == codes::CANCELLED)) {
+        return Err(FolioError::cancelled());
+    }
+    Ok(result)
+}
+
+struct Entry {
+    notebook: Arc<Mutex<Notebook>>,
+    last_used: Instant,
+}
+
+pub struct Notebooks {
+    ttl: Duration,
+    max_sessions: usize,
+    open: Mutex<HashMap<String, Entry>>,
+}
+
+impl Notebooks {
+    pub fn new(ttl: Duration, max_sessions: usize) -> Self {
+        Self { ttl, max_sessions, open: Mutex::new(HashMap::new()) }
+    }
+
+    /// Register a notebook and return its session id
+    pub fn insert(&self, notebook: Notebook) -> Result<String, FolioError> {
+        let mut open = self.open.lock().unwrap();
+        self.expire(&mut open);
+        if open.len() >= self.max_sessions {
+            return Err(FolioError::new(SESSION_LIMIT, format!("{} sessions are already open", self.max_sessions))
+                .with_suggestion("Close sessions you no longer need with close_session"));
+        }
+        let id = new_session_id();
+        open.insert(id.clone(), Entry { notebook: Arc::new(Mutex::new(notebook)), last_used: Instant::now() });
+        Ok(id)
+    }
+
+    /// Look up a session, keeping it alive
+    pub fn get(&self, id: &str) -> Result<Arc<Mutex<Notebook>>, FolioError> {
+        let mut open = self.open.lock().unwrap();
+        self.expire(&mut open);
+        let entry = open.get_mut(id).ok_or_else(|| self.not_found(id))?;
+        entry.last_used = Instant::now();
+        Ok(entry.notebook.clone())
+    }
+
+    pub fn close(&self, id: &str) -> Result<(), FolioError> {
+        let mut open = self.open.lock().unwrap();
+        self.expire(&mut open);
+        open.remove(id).map(drop).ok_or_else(|| self.not_found(id))
+    }
+
+    fn expire(&self, open: &mut HashMap<String, Entry>) {
+        open.retain(|_, entry| entry.last_used.elapsed() < self.ttl);
+    }
+
+    fn not_found(&self, id: &str) -> FolioError {
+        FolioError::new(SESSION_NOT_FOUND, format!(
+            "Session '{}' is not open; sessions close after {} minutes idle", id, self.ttl.as_secs() / 60
+        )).with_suggestion("Open a new session with open_session")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folio::ChangeKind;
+
+    const LOAN: &str = "| name | formula | result |\n|---|---|---|\n| principal | 1000 | |\n| interest | principal * rate / 100 | |\n";
+
+    fn loan(folio: &Folio) -> Notebook {
+        Notebook::open(folio, LOAN, HashMap::from([("rate".to_string(), Value::from(5))])).unwrap()
+    }
+
+    #[test]
+    fn test_edits_return_changes() {
+        let folio = Folio::default();
+        let mut notebook = loan(&folio);
+
+        let (added, diff) = notebook.set_cell(&folio, "principal", "2000").unwrap();
+        assert!(!added);
+        assert_eq!(diff.changes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["principal", "interest"]);
+
+        let (added, diff) = notebook.set_cell(&folio, "total", "principal + interest").unwrap();
+        assert!(added);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].kind, ChangeKind::Added);
+
+        let diff = notebook.set_inputs(&folio, HashMap::from([("rate".to_string(), Some(Value::from(10)))])).unwrap();
+        assert_eq!(diff.changes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["interest", "total"]);
+        assert_eq!(notebook.result.values["total"].as_number().unwrap().to_i64(), Some(2200));
+
+        // A rejected edit leaves the notebook as it was
+        assert!(notebook.set_cell(&folio, "bad|name", "1").is_err());
+        assert!(notebook.document.cell("bad|name").is_none());
+    }
+
+    #[test]
+    fn test_cancelled_edit_is_not_kept() {
+        let folio = Folio::default();
+        let mut notebook = loan(&folio);
+        let token = folio::CancelToken::new();
+        token.cancel();
+
+        let error = notebook.set_cell(&folio.clone().with_cancel(token), "principal", "5").unwrap_err();
+        assert_eq!(error.code, codes::CANCELLED);
+        assert_eq!(notebook.document.cell("principal").unwrap().raw_text, "1000");
+    }
+
+    #[test]
+    fn test_sessions_expire() {
+        let folio = Folio::default();
+        let notebooks = Notebooks::new(Duration::from_millis(50), 2);
+        let id = notebooks.insert(loan(&folio)).unwrap();
+        assert!(notebooks.get(&id).is_ok());
+        std::thread::sleep(Duration::from_millis(80));
+        assert_eq!(notebooks.get(&id).err().unwrap().code, SESSION_NOT_FOUND);
+
+        let id = notebooks.insert(loan(&folio)).unwrap();
+        assert!(notebooks.close(&id).is_ok());
+        assert!(notebooks.close(&id).is_err());
+
+        notebooks.insert(loan(&folio)).unwrap();
+        notebooks.insert(loan(&folio)).unwrap();
+        assert_eq!(notebooks.insert(loan(&folio)).err().unwrap().code, SESSION_LIMIT);
+    }
+}
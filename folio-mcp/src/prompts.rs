@@ -0,0 +1,255 @@
+//! Prompts declared by documents
+//!
+//! A document in the data directory becomes an MCP prompt when it starts with
+//! YAML front-matter:
+//!
+//! ```yaml
+//! ---
+//! name: mortgage_calculator
+//! description: Calculate monthly mortgage payment
+//! arguments:
+//!   - name: rate
+//!     description: Annual rate (e.g. 0.065)
+//!     type: number
+//!     default: 0.065
+//!     values: [0.05, 0.065, 0.08]
+//!     variable: annual_rate
+//! ---
+//! ```
+//!
+//! `name` defaults to the file name and `type` to `number`; an argument is
+//! required unless it has a `default`. Arguments are checked against their
+//! type and passed to the document as external variables, named `variable`
+//! when it differs from the argument name. `values` are only suggestions,
+//! offered through `completion/complete`.
+
+use folio::split_front_matter;
+use folio_core::{Number, Value};
+use serde::Deserialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+pub struct Prompt {
+    #[serde(default)]
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<Argument>,
+    /// File stem of the declaring document
+    #[serde(skip)]
+    pub document: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Argument {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type", default)]
+    pub kind: ArgumentType,
+    pub default: Option<serde_yaml_ng::Value>,
+    pub required: Option<bool>,
+    pub variable: Option<String>,
+    /// Suggestions for `completion/complete`
+    #[serde(default)]
+    pub values: Vec<serde_yaml_ng::Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgumentType {
+    #[default]
+    Number,
+    Text,
+    Bool,
+}
+
+impl Prompt {
+    /// `None` when the document has no front-matter
+    pub fn from_document(document: &str, content: &str) -> Option<Result<Self, String>> {
+        let (front_matter, _) = split_front_matter(content);
+        let parsed = serde_yaml_ng::from_str::<Prompt>(front_matter?)
+            .map_err(|e| format!("Invalid front-matter in '{}': {}", document, e))
+            .map(|mut prompt| {
+                if prompt.name.is_empty() {
+                    prompt.name = document.to_string();
+                }
+                prompt.document = document.to_string();
+                prompt
+            });
+        Some(parsed)
+    }
+
+    /// A document without front-matter, used by file name and taking no arguments
+    pub fn bare(document: &str) -> Self {
+        Self { name: document.to_string(), description: None, arguments: Vec::new(), document: document.to_string() }
+    }
+
+    /// Entry for `prompts/list`
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "name": self.name,
+            "description": self.description.clone().unwrap_or_else(|| format!("Evaluate {}.fmd", self.document)),
+            "arguments": self.arguments.iter().map(|a| json!({
+                "name": a.name,
+                "description": a.describe(),
+                "required": a.is_required()
+            })).collect::<Vec<_>>()
+        })
+    }
+
+    /// External variables for `prompts/get` arguments (strings, as MCP sends them)
+    ///
+    /// Unknown, missing and ill-typed arguments are all reported at once.
+    pub fn variables(&self, given: Option<&Map<String, JsonValue>>) -> Result<HashMap<String, Value>, Vec<String>> {
+        let empty = Map::new();
+        let given = given.unwrap_or(&empty);
+        let mut problems: Vec<String> = given.keys()
+            .filter(|k| !self.arguments.iter().any(|a| &a.name == *k))
+            .map(|k| format!("Unknown argument '{}'", k))
+            .collect();
+
+        let mut variables = HashMap::new();
+        for argument in &self.arguments {
+            let raw = match given.get(&argument.name) {
+                Some(JsonValue::String(s)) => Some(s.clone()),
+                Some(JsonValue::Null) | None => argument.default.as_ref().and_then(yaml_scalar),
+                Some(other) => Some(other.to_string()),
+            };
+            match raw {
+                Some(raw) => match argument.kind.parse(&raw) {
+                    Ok(value) => {
+                        variables.insert(argument.variable().to_string(), value);
+                    }
+                    Err(expected) => problems.push(format!("Argument '{}' must be {}, got '{}'", argument.name, expected, raw)),
+                },
+                None if argument.is_required() => problems.push(format!("Missing required argument '{}'", argument.name)),
+                None => {}
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(variables)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+impl Argument {
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(self.default.is_none())
+    }
+
+    /// Completion candidates: declared values, the default, or `true`/`false`
+    pub fn suggestions(&self) -> Vec<String> {
+        let mut suggestions: Vec<String> = self.values.iter().chain(&self.default).filter_map(yaml_scalar).collect();
+        if self.kind == ArgumentType::Bool {
+            suggestions.extend(["true".to_string(), "false".to_string()]);
+        }
+        let mut seen = std::collections::HashSet::new();
+        suggestions.retain(|s| seen.insert(s.clone()));
+        suggestions
+    }
+
+    /// Variable the value is injected as
+    pub fn variable(&self) -> &str {
+        self.variable.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Description with the type and default, since MCP arguments carry neither
+    fn describe(&self) -> String {
+        let mut details = vec![self.kind.name().to_string()];
+        if let Some(default) = self.default.as_ref().and_then(yaml_scalar) {
+            details.push(format!("default {}", default));
+        }
+        match &self.description {
+            Some(description) => format!("{} ({})", description, details.join(", ")),
+            None => details.join(", "),
+        }
+    }
+}
+
+impl ArgumentType {
+    fn name(self) -> &'static str {
+        match self {
+            ArgumentType::Number => "number",
+            ArgumentType::Text => "text",
+            ArgumentType::Bool => "true or false",
+        }
+    }
+
+    /// The value, or what was expected
+    fn parse(self, raw: &str) -> Result<Value, &'static str> {
+        let raw = raw.trim();
+        match self {
+            ArgumentType::Number => Number::from_str(raw).map(Value::Number).map_err(|_| "a number"),
+            ArgumentType::Text => Ok(Value::Text(raw.to_string())),
+            ArgumentType::Bool => match raw.to_ascii_lowercase().as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(self.name()),
+            },
+        }
+    }
+}
+
+fn yaml_scalar(value: &serde_yaml_ng::Value) -> Option<String> {
+    match value {
+        serde_yaml_ng::Value::String(s) => Some(s.clone()),
+        serde_yaml_ng::Value::Number(n) => Some(n.to_string()),
+        serde_yaml_ng::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOAN: &str = "---\ndescription: Monthly payment\narguments:\n  - name: principal\n  - name: rate\n    variable: annual_rate\n    default: 0.065\n  - name: label\n    type: text\n    required: false\n---\n# Loan\n";
+
+    fn loan() -> Prompt {
+        Prompt::from_document("loan", LOAN).unwrap().unwrap()
+    }
+
+    fn args(value: JsonValue) -> Map<String, JsonValue> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let prompt = loan();
+        assert_eq!(prompt.name, "loan");
+        assert_eq!(prompt.arguments[1].kind, ArgumentType::Number);
+        assert!(prompt.arguments[0].is_required());
+        assert!(!prompt.arguments[1].is_required());
+
+        let listed = prompt.to_json();
+        assert_eq!(listed["description"], "Monthly payment");
+        assert_eq!(listed["arguments"][1]["description"], "number, default 0.065");
+        assert_eq!(listed["arguments"][2]["required"], false);
+
+        assert_eq!(prompt.arguments[1].suggestions(), vec!["0.065".to_string()]);
+
+        assert!(Prompt::from_document("plain", "# Plain\n").is_none());
+        assert!(Prompt::from_document("broken", "---\narguments: 3\n---\n").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        let prompt = loan();
+        let variables = prompt.variables(Some(&args(json!({ "principal": "250000" })))).unwrap();
+        assert_eq!(variables["principal"].as_number().unwrap().to_i64(), Some(250000));
+        assert!(variables.contains_key("annual_rate"));
+        assert!(!variables.contains_key("rate"));
+        assert!(!variables.contains_key("label"));
+
+        let problems = prompt.variables(Some(&args(json!({ "principal": "lots", "years": "30" })))).unwrap_err();
+        assert_eq!(problems, vec![
+            "Unknown argument 'years'".to_string(),
+            "Argument 'principal' must be a number, got 'lots'".to_string(),
+        ]);
+        assert_eq!(prompt.variables(None).unwrap_err(), vec!["Missing required argument 'principal'".to_string()]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Confine file access to the data directory
+//!
+//! Every path is canonicalized before use and must land inside one of the
+//! sandbox roots: the data directory (`FOLIO_DATA_PATH`), plus the allowed
+//! roots (`FOLIO_ALLOWED_ROOTS`, separated like `PATH`), or as configured. `..` components are refused
+//! outright, symlinks are followed and judged by their target, and only `.fmd`
+//! files can be read. Violations are `FORBIDDEN` errors rather than a silent
+//! fallback to some other file.
+
+use folio_core::FolioError;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub const FORBIDDEN: &str = "FORBIDDEN";
+pub const NOT_FOUND: &str = "NOT_FOUND";
+
+pub struct Sandbox {
+    /// Canonical roots, the data directory first
+    roots: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// Roots that don't exist are dropped
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut canonical: Vec<PathBuf> = Vec::new();
+        for root in roots {
+            if let Ok(root) = fs::canonicalize(&root) {
+                if !canonical.contains(&root) {
+                    canonical.push(root);
+                }
+            }
+        }
+        Self { roots: canonical }
+    }
+
+    pub fn from_config() -> Self {
+        let config = crate::config::get();
+        Self::new(std::iter::once(config.data_path.clone()).chain(config.allowed_roots.iter().cloned()))
+    }
+
+    /// Directories searched for documents by name: each root and its `examples`
+    pub fn search_dirs(&self) -> Vec<PathBuf> {
+        self.roots.iter().flat_map(|r| [r.clone(), r.join("examples")]).collect()
+    }
+
+    /// Whether `path` resolves to a `.fmd` file inside the sandbox
+    pub fn allows(&self, path: &Path) -> bool {
+        self.check(path).is_ok()
+    }
+
+    /// Find a document by name (`mortgage`), file name (`mortgage.fmd`),
+    /// relative path (`examples/mortgage.fmd`) or absolute path
+    pub fn resolve(&self, input: &str) -> Result<PathBuf, FolioError> {
+        let input = input.trim();
+        let path = Path::new(input);
+        if path.components().any(|c| c == Component::ParentDir) || input.split(['/', '\\']).any(|part| part == "..") {
+            return Err(forbidden(format!("'{}': '..' is not allowed in document paths", input)));
+        }
+
+        if path.is_absolute() {
+            if self.roots.iter().any(|root| path.starts_with(root)) {
+                for candidate in [path.to_path_buf(), with_extension(path)] {
+                    if candidate.exists() {
+                        return self.check(&candidate);
+                    }
+                }
+                return Err(FolioError::new(NOT_FOUND, format!("File '{}' not found", strip_extension(&file_name(input)))));
+            }
+            // Nothing outside the roots is touched, so the error doesn't tell
+            // whether the file exists. A host path passed to a server in a
+            // container is looked up by name.
+            return self.find(&file_name(input)).map_err(|e| match e.code.as_str() {
+                NOT_FOUND => forbidden(format!("'{}' is outside the data directory", input)),
+                _ => e,
+            });
+        }
+        if input.contains('\\') || input.contains(':') {
+            // Windows path
+            return self.find(&file_name(input));
+        }
+        self.find(input)
+    }
+
+    /// Read a document, see [`resolve`](Self::resolve)
+    pub fn read(&self, input: &str) -> Result<String, FolioError> {
+        let path = self.resolve(input)?;
+        fs::read_to_string(&path)
+            .map_err(|e| FolioError::new(NOT_FOUND, format!("Failed to read '{}': {}", path.display(), e)))
+    }
+
+    fn find(&self, relative: &str) -> Result<PathBuf, FolioError> {
+        let stem = strip_extension(relative);
+        if stem.is_empty() {
+            return Err(FolioError::new(NOT_FOUND, format!(
+                "Invalid file reference: '{}'. Please provide a file name like 'mortgage' or 'mortgage.fmd'", relative
+            )));
+        }
+        for dir in self.search_dirs() {
+            for name in [stem.to_string(), stem.to_lowercase()] {
+                let candidate = dir.join(format!("{}.fmd", name));
+                if candidate.exists() {
+                    return self.check(&candidate);
+                }
+            }
+        }
+        Err(FolioError::new(NOT_FOUND, format!("File '{}' not found", stem)))
+    }
+
+    /// Canonical path, if it is a `.fmd` file under a root
+    fn check(&self, path: &Path) -> Result<PathBuf, FolioError> {
+        let canonical = fs::canonicalize(path)
+            .map_err(|e| FolioError::new(NOT_FOUND, format!("'{}': {}", path.display(), e)))?;
+        if !self.roots.iter().any(|root| canonical.starts_with(root)) {
+            return Err(forbidden(format!("'{}' is outside the data directory", path.display())));
+        }
+        if !canonical.extension().is_some_and(|e| e.eq_ignore_ascii_case("fmd")) || !canonical.is_file() {
+            return Err(forbidden(format!("'{}' is not a .fmd document", path.display())));
+        }
+        Ok(canonical)
+    }
+}
+
+fn forbidden(message: String) -> FolioError {
+    FolioError::new(FORBIDDEN, message)
+        .with_suggestion("Use a document name from resources/list")
+}
+
+fn with_extension(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".fmd");
+    PathBuf::from(name)
+}
+
+pub fn strip_extension(name: &str) -> &str {
+    match name.len().checked_sub(4).and_then(|i| name.get(i..).map(|ext| (i, ext))) {
+        Some((i, ext)) if ext.eq_ignore_ascii_case(".fmd") => &name[..i],
+        _ => name,
+    }
+}
+
+/// Last component of a Unix or Windows path
+fn file_name(input: &str) -> String {
+    input.rsplit(['/', '\\']).next().unwrap_or(input).to_string()
+}
+
+/// Fresh directory with an `examples` subdirectory, removed on drop
+#[cfg(test)]
+pub struct TempDir(pub PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(label: &str) -> Self {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let path = std::env::temp_dir().join(format!("folio-{}-{}-{}", label, std::process::id(), nanos));
+        fs::create_dir_all(path.join("examples")).unwrap();
+        TempDir(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(result: Result<PathBuf, FolioError>) -> String {
+        result.map(|p| p.display().to_string()).unwrap_or_else(|e| e.code)
+    }
+
+    #[test]
+    fn test_resolve_inside_data_dir() {
+        let data = TempDir::new("data");
+        fs::write(data.0.join("loan.fmd"), "# Loan").unwrap();
+        fs::write(data.0.join("examples/Mortgage.fmd"), "# Mortgage").unwrap();
+        let sandbox = Sandbox::new([data.0.clone()]);
+
+        let loan = fs::canonicalize(data.0.join("loan.fmd")).unwrap();
+        assert_eq!(sandbox.resolve("loan").unwrap(), loan);
+        assert_eq!(sandbox.resolve("loan.fmd").unwrap(), loan);
+        assert_eq!(sandbox.resolve(loan.to_str().unwrap()).unwrap(), loan);
+        assert_eq!(sandbox.resolve("/host/elsewhere/loan.fmd").unwrap(), loan);
+        assert_eq!(sandbox.resolve("C:\\Users\\me\\loan.fmd").unwrap(), loan);
+        assert!(sandbox.resolve("examples/Mortgage.fmd").is_ok());
+        assert!(sandbox.resolve("Mortgage").is_ok());
+        assert_eq!(sandbox.read("loan").unwrap(), "# Loan");
+        assert_eq!(code(sandbox.resolve("missing")), NOT_FOUND);
+    }
+
+    #[test]
+    fn test_rejects_escapes() {
+        let data = TempDir::new("data");
+        let outside = TempDir::new("outside");
+        fs::write(outside.0.join("secret.fmd"), "secret").unwrap();
+        fs::write(outside.0.join("passwd"), "root").unwrap();
+        fs::write(data.0.join("notes.txt"), "text").unwrap();
+        let sandbox = Sandbox::new([data.0.clone()]);
+
+        assert_eq!(code(sandbox.resolve("../secret")), FORBIDDEN);
+        assert_eq!(code(sandbox.resolve("examples/../../secret.fmd")), FORBIDDEN);
+        assert_eq!(code(sandbox.resolve(outside.0.join("secret.fmd").to_str().unwrap())), FORBIDDEN);
+        assert_eq!(code(sandbox.resolve(outside.0.join("secret").to_str().unwrap())), FORBIDDEN);
+        // Same error whether or not the file exists
+        let missing = sandbox.resolve(outside.0.join("missing.fmd").to_str().unwrap()).unwrap_err();
+        let existing = sandbox.resolve(outside.0.join("secret.fmd").to_str().unwrap()).unwrap_err();
+        assert_eq!(missing.code, FORBIDDEN);
+        assert_eq!(missing.message.replace("missing", "secret"), existing.message);
+        assert_eq!(code(sandbox.resolve(data.0.join("missing.fmd").to_str().unwrap())), NOT_FOUND);
+        // Only documents, even inside the sandbox
+        assert_eq!(code(sandbox.resolve(data.0.join("notes.txt").to_str().unwrap())), FORBIDDEN);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.0.join("secret.fmd"), data.0.join("link.fmd")).unwrap();
+            std::os::unix::fs::symlink(outside.0.join("passwd"), data.0.join("passwd.fmd")).unwrap();
+            assert_eq!(code(sandbox.resolve("link")), FORBIDDEN);
+            assert_eq!(code(sandbox.resolve("passwd.fmd")), FORBIDDEN);
+            assert!(!sandbox.allows(&data.0.join("link.fmd")));
+        }
+    }
+
+    #[test]
+    fn test_allowed_roots() {
+        let data = TempDir::new("data");
+        let shared = TempDir::new("shared");
+        fs::write(shared.0.join("team.fmd"), "# Team").unwrap();
+        let path = shared.0.join("team.fmd");
+
+        assert_eq!(code(Sandbox::new([data.0.clone()]).resolve(path.to_str().unwrap())), FORBIDDEN);
+        let sandbox = Sandbox::new([data.0.clone(), shared.0.clone()]);
+        assert!(sandbox.resolve(path.to_str().unwrap()).is_ok());
+        assert!(sandbox.resolve("team").is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+//! Tool output schemas
+//!
+//! `tools/list` declares these as `outputSchema`, and the tools return a
+//! matching `structuredContent` next to their text, so clients can read
+//! results without parsing markdown. Values are encoded as by `value_to_json`.
+
+use serde_json::{json, Value as JsonValue};
+
+/// A value as `value_to_json` writes it
+fn value() -> JsonValue {
+    json!({
+        "description": "Numbers are decimal strings; datetimes, durations and custom types are { _type, value }; errors are { _error: { code, message } }"
+    })
+}
+
+fn values() -> JsonValue {
+    json!({ "type": "object", "additionalProperties": value() })
+}
+
+fn errors() -> JsonValue {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "required": ["code", "message"],
+            "properties": { "code": { "type": "string" }, "message": { "type": "string" } }
+        }
+    })
+}
+
+fn strings() -> JsonValue {
+    json!({ "type": "array", "items": { "type": "string" } })
+}
+
+/// `eval` and `eval_file`: cell values whatever the output format
+pub fn eval() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["values", "errors"],
+        "properties": {
+            "values": values(),
+            "errors": errors(),
+            "source_file": { "type": "string", "description": "eval_file only" }
+        }
+    })
+}
+
+pub fn fmt() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["template"],
+        "properties": {
+            "template": { "type": "string", "description": "The formatted document" }
+        }
+    })
+}
+
+pub fn eval_batch() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["results"],
+        "properties": {
+            "results": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["index", "variables", "values", "has_errors"],
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "variables": { "type": "object" },
+                        "values": values(),
+                        "has_errors": { "type": "boolean" }
+                    }
+                }
+            },
+            "comparison": {
+                "type": "array",
+                "description": "compare_field across the sets that computed it",
+                "items": {
+                    "type": "object",
+                    "required": ["index", "variables", "value"],
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "variables": { "type": "object" },
+                        "value": value()
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// `folio`: help for one name, or the overview
+pub fn folio() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["type"],
+        "properties": {
+            "type": { "enum": ["function", "command", "constant", "overview"] },
+            "name": { "type": "string" },
+            "description": { "type": "string" },
+            "usage": { "type": "string" },
+            "returns": { "type": "string" },
+            "category": { "type": "string" },
+            "args": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "type": { "type": "string" },
+                        "description": { "type": "string" },
+                        "optional": { "type": "boolean" }
+                    }
+                }
+            },
+            "examples": strings(),
+            "formula": { "type": "string" },
+            "source": { "type": "string" },
+            "overview": { "type": "string", "description": "Markdown, when no name was given" }
+        }
+    })
+}
+
+/// `quick`: the reference card as markdown
+pub fn quick() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["reference"],
+        "properties": {
+            "reference": { "type": "string" }
+        }
+    })
+}
+
+pub fn list_functions() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["functions"],
+        "properties": {
+            "functions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "description", "usage", "category"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "description": { "type": "string" },
+                        "usage": { "type": "string" },
+                        "category": { "type": "string" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+pub fn list_constants() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["constants"],
+        "properties": {
+            "constants": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "formula", "source"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "formula": { "type": "string" },
+                        "source": { "type": "string" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+pub fn decompose() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["value", "patterns"],
+        "properties": {
+            "value": { "type": "string" },
+            "patterns": { "type": "object", "description": "Detected patterns by name" }
+        }
+    })
+}
+
+pub fn save_document() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["name", "version", "hash", "created"],
+        "properties": {
+            "name": { "type": "string" },
+            "version": { "type": "integer" },
+            "hash": { "type": "string", "description": "expected_hash for the next save or delete" },
+            "created": { "type": "boolean" }
+        }
+    })
+}
+
+pub fn delete_document() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["name", "deleted"],
+        "properties": {
+            "name": { "type": "string" },
+            "deleted": { "type": "boolean" }
+        }
+    })
+}
+
+pub fn list_versions() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["name", "current_hash", "versions"],
+        "properties": {
+            "name": { "type": "string" },
+            "current_hash": { "type": ["string", "null"], "description": "null when deleted or never saved" },
+            "versions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["version", "hash", "size"],
+                    "properties": {
+                        "version": { "type": "integer" },
+                        "hash": { "type": "string" },
+                        "size": { "type": "integer" },
+                        "saved_at": { "type": ["integer", "null"], "description": "Seconds since the Unix epoch" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+pub fn open_session() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["session_id", "values", "errors"],
+        "properties": {
+            "session_id": { "type": "string" },
+            "values": values(),
+            "errors": errors()
+        }
+    })
+}
+
+/// `set_cell` and `set_inputs`: the cells whose values changed
+pub fn edit() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["changes", "errors"],
+        "properties": {
+            "changes": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "change", "cause"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "change": { "enum": ["added", "removed", "changed"] },
+                        "old": value(),
+                        "new": value(),
+                        "cause": {
+                            "description": "\"formula\", or { upstream: [names] } for a change carried from cells it reads"
+                        }
+                    }
+                }
+            },
+            "errors": errors(),
+            "added": { "type": "boolean", "description": "set_cell only: whether the cell is new" }
+        }
+    })
+}
+
+/// `render`: the document with results, or its source
+pub fn render() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["format", "text"],
+        "properties": {
+            "format": { "enum": ["markdown", "source"] },
+            "text": { "type": "string" }
+        }
+    })
+}
+
+pub fn close_session() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["session_id", "closed"],
+        "properties": {
+            "session_id": { "type": "string" },
+            "closed": { "type": "boolean" }
+        }
+    })
+}
+
+pub fn get_cell() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["name", "formula", "references", "value"],
+        "properties": {
+            "name": { "type": "string" },
+            "formula": { "type": "string" },
+            "references": strings(),
+            "value": value()
+        }
+    })
+}
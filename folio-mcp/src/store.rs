@@ -0,0 +1,374 @@
+//! Writable document store
+//!
+//! Documents are saved as `<name>.fmd` in the data directory (or updated in
+//! `examples/` when that is where the name lives). Every save is also kept as a
+//! numbered snapshot in `.versions/<name>/<n>.fmd`, up to [`MAX_VERSIONS`].
+//! Writers guard against lost updates with the content hash they last saw:
+//! replacing or deleting a document requires the hash of its current content.
+//! Directories written to must resolve inside the data directory, so a
+//! symlinked `examples/` or `.versions/` can't redirect writes elsewhere, and
+//! `allow_writes = false` makes the store read-only.
+
+use crate::sandbox::{strip_extension, Sandbox, FORBIDDEN, NOT_FOUND};
+use folio_core::FolioError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+pub const CONFLICT: &str = "CONFLICT";
+pub const INVALID_NAME: &str = "INVALID_NAME";
+pub const READ_ONLY: &str = "READ_ONLY";
+
+/// Snapshots kept per document; older ones are pruned
+pub const MAX_VERSIONS: u32 = 20;
+
+const HISTORY_DIR: &str = ".versions";
+
+/// Serializes check-then-write between concurrent requests
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct Store {
+    /// Canonical data directory
+    root: PathBuf,
+    sandbox: Sandbox,
+    writable: bool,
+}
+
+/// A snapshot in a document's history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub number: u32,
+    pub hash: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub saved_at: Option<u64>,
+}
+
+/// Outcome of [`Store::save`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saved {
+    pub version: u32,
+    pub hash: String,
+    pub created: bool,
+}
+
+impl Store {
+    pub fn new(root: &Path, sandbox: Sandbox) -> Result<Self, FolioError> {
+        let root = fs::canonicalize(root).map_err(|e| {
+            FolioError::new(NOT_FOUND, format!("Data directory '{}' is not available: {}", root.display(), e))
+        })?;
+        Ok(Self { root, sandbox, writable: true })
+    }
+
+    pub fn from_config() -> Result<Self, FolioError> {
+        Ok(Self::new(&crate::data_path(), Sandbox::from_config())?.with_writes(crate::config::get().allow_writes))
+    }
+
+    /// Refuse [`save`](Self::save) and [`delete`](Self::delete) unless `allowed`
+    pub fn with_writes(mut self, allowed: bool) -> Self {
+        self.writable = allowed;
+        self
+    }
+
+    /// Create or replace a document
+    ///
+    /// Replacing needs `expected_hash` to match the current content; creating
+    /// needs it absent, so a concurrent delete isn't silently undone.
+    pub fn save(&self, name: &str, content: &str, expected_hash: Option<&str>) -> Result<Saved, FolioError> {
+        if !self.writable {
+            return Err(read_only());
+        }
+        let name = validate_name(name)?;
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.document_path(name)?;
+
+        let current = read_existing(&path)?;
+        check_hash(name, current.as_deref(), expected_hash)?;
+
+        // Keep what was on disk if it never went through the store (e.g. edited by hand)
+        let versions = self.versions(name)?;
+        if let Some(current) = &current {
+            if versions.last().map(|v| v.hash.as_str()) != Some(content_hash(current).as_str()) {
+                self.snapshot(name, current, &versions)?;
+            }
+        }
+
+        write_atomic(&path, content)?;
+        let versions = self.versions(name)?;
+        let version = self.snapshot(name, content, &versions)?;
+        Ok(Saved { version, hash: content_hash(content), created: current.is_none() })
+    }
+
+    /// Remove a document; its history is kept
+    pub fn delete(&self, name: &str, expected_hash: Option<&str>) -> Result<(), FolioError> {
+        if !self.writable {
+            return Err(read_only());
+        }
+        let name = validate_name(name)?;
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.document_path(name)?;
+
+        let Some(current) = read_existing(&path)? else {
+            return Err(FolioError::new(NOT_FOUND, format!("Document '{}' does not exist", name)));
+        };
+        check_hash(name, Some(&current), expected_hash)?;
+        fs::remove_file(&path).map_err(|e| io_error(&path, e))
+    }
+
+    /// Hash of the current content, `None` when the document doesn't exist
+    pub fn current_hash(&self, name: &str) -> Result<Option<String>, FolioError> {
+        let name = validate_name(name)?;
+        Ok(read_existing(&self.document_path(name)?)?.map(|c| content_hash(&c)))
+    }
+
+    /// Snapshots, oldest first
+    pub fn versions(&self, name: &str) -> Result<Vec<Version>, FolioError> {
+        let name = validate_name(name)?;
+        let dir = self.history_dir(name);
+        if !self.contains(&dir) {
+            return Err(outside(&dir));
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Ok(Vec::new());
+        };
+        let mut versions: Vec<Version> = entries.flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let number = path.file_stem()?.to_str()?.parse().ok()?;
+                let content = fs::read_to_string(&path).ok()?;
+                let saved_at = entry.metadata().ok()
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs());
+                Some(Version { number, hash: content_hash(&content), size: content.len() as u64, saved_at })
+            })
+            .collect();
+        versions.sort_by_key(|v| v.number);
+        Ok(versions)
+    }
+
+    /// Where `name` lives: the data directory, or `examples/` if only there
+    fn document_path(&self, name: &str) -> Result<PathBuf, FolioError> {
+        let top = self.root.join(format!("{}.fmd", name));
+        let example = self.root.join("examples").join(format!("{}.fmd", name));
+        let path = if !top.exists() && example.exists() { example } else { top };
+        if !path.parent().is_some_and(|parent| self.contains(parent)) || (path.exists() && !self.sandbox.allows(&path)) {
+            return Err(outside(&path));
+        }
+        Ok(path)
+    }
+
+    fn history_dir(&self, name: &str) -> PathBuf {
+        self.root.join(HISTORY_DIR).join(name)
+    }
+
+    /// Whether the deepest existing ancestor of `dir` resolves inside the data
+    /// directory; what is missing below it gets created as plain directories
+    fn contains(&self, dir: &Path) -> bool {
+        dir.ancestors()
+            .find(|a| a.exists())
+            .and_then(|existing| fs::canonicalize(existing).ok())
+            .is_some_and(|canonical| canonical.starts_with(&self.root))
+    }
+
+    /// Store `content` as the next version and prune old ones
+    fn snapshot(&self, name: &str, content: &str, versions: &[Version]) -> Result<u32, FolioError> {
+        let dir = self.history_dir(name);
+        if !self.contains(&dir) {
+            return Err(outside(&dir));
+        }
+        fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+        let number = versions.last().map_or(1, |v| v.number + 1);
+        write_atomic(&dir.join(format!("{}.fmd", number)), content)?;
+        for old in versions.iter().filter(|v| v.number + MAX_VERSIONS <= number) {
+            let _ = fs::remove_file(dir.join(format!("{}.fmd", old.number)));
+        }
+        Ok(number)
+    }
+}
+
+/// FNV-1a over the UTF-8 bytes, as 16 hex digits; stable across runs and platforms
+pub fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+/// A plain file name, optionally with `.fmd`
+fn validate_name(name: &str) -> Result<&str, FolioError> {
+    let name = strip_extension(name.trim());
+    let valid = !name.is_empty()
+        && name.len() <= 128
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(name)
+    } else {
+        Err(FolioError::new(INVALID_NAME, format!("Invalid document name '{}'", name))
+            .with_suggestion("Use letters, digits, '_', '-' and '.', without a path"))
+    }
+}
+
+fn check_hash(name: &str, current: Option<&str>, expected: Option<&str>) -> Result<(), FolioError> {
+    let current = current.map(content_hash);
+    match (current.as_deref(), expected) {
+        (None, None) => Ok(()),
+        (Some(actual), Some(expected)) if actual == expected => Ok(()),
+        (Some(actual), _) => Err(FolioError::new(CONFLICT, format!(
+            "Document '{}' has changed: current hash is {}", name, actual
+        )).with_suggestion("Re-read the document, merge your changes and pass its current hash as expected_hash")),
+        (None, Some(_)) => Err(FolioError::new(CONFLICT, format!("Document '{}' no longer exists", name))
+            .with_suggestion("Save without expected_hash to create it again")),
+    }
+}
+
+fn read_existing(path: &Path) -> Result<Option<String>, FolioError> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(path, e)),
+    }
+}
+
+/// Write beside the target and rename, so readers never see half a file
+fn write_atomic(path: &Path, content: &str) -> Result<(), FolioError> {
+    let tmp = path.with_extension("fmd.tmp");
+    fs::write(&tmp, content).map_err(|e| io_error(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| io_error(path, e))
+}
+
+fn read_only() -> FolioError {
+    FolioError::new(READ_ONLY, "Documents are read-only on this server")
+        .with_suggestion("Set allow_writes = true in the server configuration")
+}
+
+fn outside(path: &Path) -> FolioError {
+    FolioError::new(FORBIDDEN, format!("'{}' points outside the data directory", path.display()))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FolioError {
+    FolioError::new("IO_ERROR", format!("'{}': {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::TempDir;
+
+    fn store(dir: &TempDir) -> Store {
+        Store::new(&dir.0, Sandbox::new([dir.0.clone()])).unwrap()
+    }
+
+    #[test]
+    fn test_save_and_versions() {
+        let dir = TempDir::new("store");
+        let store = store(&dir);
+
+        let first = store.save("loan", "# v1\n", None).unwrap();
+        assert_eq!(first, Saved { version: 1, hash: content_hash("# v1\n"), created: true });
+        assert_eq!(fs::read_to_string(dir.0.join("loan.fmd")).unwrap(), "# v1\n");
+
+        let second = store.save("loan.fmd", "# v2\n", Some(&first.hash)).unwrap();
+        assert_eq!((second.version, second.created), (2, false));
+        assert_eq!(store.current_hash("loan").unwrap(), Some(second.hash.clone()));
+
+        let versions = store.versions("loan").unwrap();
+        assert_eq!(versions.iter().map(|v| v.number).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(versions[0].hash, first.hash);
+        assert_eq!(versions[1].size, 5);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let dir = TempDir::new("store");
+        let store = store(&dir);
+        let saved = store.save("loan", "# v1\n", None).unwrap();
+
+        let code = |r: Result<Saved, FolioError>| r.unwrap_err().code;
+        assert_eq!(code(store.save("loan", "# v2\n", None)), CONFLICT);
+        assert_eq!(code(store.save("loan", "# v2\n", Some("0000000000000000"))), CONFLICT);
+        assert_eq!(code(store.save("other", "# x\n", Some(&saved.hash))), CONFLICT);
+        assert_eq!(store.delete("loan", Some("stale")).unwrap_err().code, CONFLICT);
+
+        store.delete("loan", Some(&saved.hash)).unwrap();
+        assert!(!dir.0.join("loan.fmd").exists());
+        assert_eq!(store.current_hash("loan").unwrap(), None);
+        assert_eq!(store.delete("loan", None).unwrap_err().code, NOT_FOUND);
+        // History survives, and numbering continues
+        assert_eq!(store.save("loan", "# v3\n", None).unwrap().version, 2);
+    }
+
+    #[test]
+    fn test_hand_edits_are_kept() {
+        let dir = TempDir::new("store");
+        fs::write(dir.0.join("examples/rates.fmd"), "# original\n").unwrap();
+        let store = store(&dir);
+
+        let hash = store.current_hash("rates").unwrap().unwrap();
+        let saved = store.save("rates", "# edited\n", Some(&hash)).unwrap();
+        assert_eq!(saved.version, 2);
+        assert_eq!(fs::read_to_string(dir.0.join("examples/rates.fmd")).unwrap(), "# edited\n");
+        assert_eq!(store.versions("rates").unwrap()[0].hash, hash);
+    }
+
+    #[test]
+    fn test_prunes_history() {
+        let dir = TempDir::new("store");
+        let store = store(&dir);
+        let mut hash = None;
+        for i in 0..MAX_VERSIONS + 5 {
+            hash = Some(store.save("log", &format!("# {}\n", i), hash.as_deref()).unwrap().hash);
+        }
+        let versions = store.versions("log").unwrap();
+        assert_eq!(versions.len() as u32, MAX_VERSIONS);
+        assert_eq!(versions.last().unwrap().number, MAX_VERSIONS + 5);
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let dir = TempDir::new("store");
+        let store = store(&dir);
+        for name in ["", "../x", "a/b", ".versions", "a b", "C:\\x"] {
+            assert_eq!(store.save(name, "x", None).unwrap_err().code, INVALID_NAME, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_read_only() {
+        let dir = TempDir::new("store");
+        let saved = store(&dir).save("loan", "# v1\n", None).unwrap();
+        let store = store(&dir).with_writes(false);
+
+        assert_eq!(store.save("loan", "# v2\n", Some(&saved.hash)).unwrap_err().code, READ_ONLY);
+        assert_eq!(store.save("new", "# x\n", None).unwrap_err().code, READ_ONLY);
+        assert_eq!(store.delete("loan", Some(&saved.hash)).unwrap_err().code, READ_ONLY);
+        assert_eq!(fs::read_to_string(dir.0.join("loan.fmd")).unwrap(), "# v1\n");
+        assert!(!dir.0.join("new.fmd").exists());
+        // Reading still works
+        assert_eq!(store.current_hash("loan").unwrap(), Some(saved.hash));
+        assert_eq!(store.versions("loan").unwrap().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_dirs_are_refused() {
+        let dir = TempDir::new("store");
+        let outside = TempDir::new("outside");
+        let store = store(&dir);
+
+        // History redirected elsewhere
+        std::os::unix::fs::symlink(&outside.0, dir.0.join(HISTORY_DIR)).unwrap();
+        assert_eq!(store.save("loan", "# v1\n", None).unwrap_err().code, FORBIDDEN);
+        assert_eq!(store.versions("loan").unwrap_err().code, FORBIDDEN);
+        assert!(!dir.0.join("loan.fmd").exists());
+        assert!(fs::read_dir(&outside.0).unwrap().all(|e| e.unwrap().file_name() == "examples"));
+
+        // An examples directory pointing outside
+        fs::remove_file(dir.0.join(HISTORY_DIR)).unwrap();
+        fs::remove_dir_all(dir.0.join("examples")).unwrap();
+        fs::write(outside.0.join("examples/rates.fmd"), "# outside\n").unwrap();
+        std::os::unix::fs::symlink(outside.0.join("examples"), dir.0.join("examples")).unwrap();
+        assert_eq!(store.save("rates", "# edited\n", None).unwrap_err().code, FORBIDDEN);
+        assert_eq!(fs::read_to_string(outside.0.join("examples/rates.fmd")).unwrap(), "# outside\n");
+    }
+}
@@ -0,0 +1,320 @@
+//! Streamable HTTP transport against a spawned server
+
+use serde_json::{json, Value as JsonValue};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(token: Option<&str>) -> Self {
+        Self::start_in(token, &std::env::temp_dir())
+    }
+
+    fn start_in(token: Option<&str>, data: &std::path::Path) -> Self {
+        Self::spawn(token, data, &[])
+    }
+
+    /// Start with a config file holding `toml`
+    fn start_with_config(label: &str, toml: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("folio-http-{}-{}.toml", label, std::process::id()));
+        std::fs::write(&path, toml).unwrap();
+        Self::spawn(None, &std::env::temp_dir(), &["--config", path.to_str().unwrap()])
+    }
+
+    fn spawn(token: Option<&str>, data: &std::path::Path, args: &[&str]) -> Self {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_folio-mcp"));
+        command.args(["--http", "127.0.0.1:0"])
+            .args(args)
+            .env("FOLIO_DATA_PATH", data)
+            .env_remove("FOLIO_MCP_TOKEN")
+            .env_remove("FOLIO_MCP_CONFIG")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        if let Some(token) = token {
+            command.args(["--token", token]);
+        }
+        let mut child = command.spawn().expect("spawn folio-mcp");
+
+        let mut lines = BufReader::new(child.stderr.take().unwrap()).lines();
+        let addr = lines.by_ref()
+            .map_while(Result::ok)
+            .find_map(|line| line.strip_prefix("Listening on http://").map(|rest| rest.trim_end_matches("/mcp").to_string()))
+            .expect("server address");
+        // Keep draining the log so the server never blocks on a full pipe
+        thread::spawn(move || lines.for_each(drop));
+        Server { child, addr }
+    }
+
+    fn request(&self, method: &str, headers: &[(&str, &str)], body: &str) -> Response {
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        let mut head = format!("{} /mcp HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n", method, self.addr, body.len());
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes()).unwrap();
+        stream.write_all(body.as_bytes()).unwrap();
+
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+        let status = lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+        let headers = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(n, v)| (n.trim().to_ascii_lowercase(), v.trim().to_string()))
+            .collect();
+        Response { status, headers, body: body.to_string() }
+    }
+
+    /// `initialize` and return the session id
+    fn initialize(&self, auth: &[(&str, &str)]) -> String {
+        let body = json!({
+            "jsonrpc": "2.0", "id": 0, "method": "initialize",
+            "params": { "protocolVersion": "2025-11-25", "clientInfo": { "name": "test" } }
+        });
+        let response = self.request("POST", auth, &body.to_string());
+        assert_eq!(response.status, 200, "{}", response.body);
+        response.header("mcp-session-id").expect("session id").to_string()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// JSON body, or the data of the first SSE event
+    fn message(&self) -> JsonValue {
+        let text = match self.header("content-type") {
+            Some("text/event-stream") => self.body.lines()
+                .find_map(|l| l.strip_prefix("data: "))
+                .expect("SSE data line"),
+            _ => self.body.as_str(),
+        };
+        serde_json::from_str(text).unwrap()
+    }
+}
+
+fn call(id: u64, tool: &str, arguments: JsonValue) -> String {
+    json!({
+        "jsonrpc": "2.0", "id": id, "method": "tools/call",
+        "params": { "name": tool, "arguments": arguments }
+    }).to_string()
+}
+
+#[test]
+fn test_session_lifecycle() {
+    let server = Server::start(None);
+    let session = server.initialize(&[]);
+    let with_session = [("Mcp-Session-Id", session.as_str())];
+
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }).to_string();
+    assert_eq!(server.request("POST", &with_session, &notification).status, 202);
+
+    let list = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }).to_string();
+    let response = server.request("POST", &with_session, &list);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    assert!(response.message()["result"]["tools"].as_array().unwrap().iter().any(|t| t["name"] == "eval"));
+
+    assert_eq!(server.request("DELETE", &with_session, "").status, 200);
+    assert_eq!(server.request("POST", &with_session, &list).status, 404);
+    assert_eq!(server.request("POST", &[], &list).status, 400);
+}
+
+#[test]
+fn test_session_limit() {
+    let server = Server::start_with_config("limit", "[limits]\nmax_sessions = 1\n");
+    let session = server.initialize(&[]);
+
+    let body = json!({
+        "jsonrpc": "2.0", "id": 0, "method": "initialize",
+        "params": { "protocolVersion": "2025-11-25", "clientInfo": { "name": "test" } }
+    }).to_string();
+    let refused = server.request("POST", &[], &body);
+    assert_eq!(refused.status, 503);
+    assert!(refused.header("mcp-session-id").is_none());
+
+    // Closing the session frees its slot
+    assert_eq!(server.request("DELETE", &[("Mcp-Session-Id", session.as_str())], "").status, 200);
+    server.initialize(&[]);
+}
+
+#[test]
+fn test_idle_sessions_expire() {
+    let server = Server::start_with_config("ttl", "[limits]\nsession_ttl_secs = 0\n");
+    let session = server.initialize(&[]);
+    let list = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }).to_string();
+    assert_eq!(server.request("POST", &[("Mcp-Session-Id", session.as_str())], &list).status, 404);
+}
+
+#[test]
+fn test_sse_response() {
+    let server = Server::start(None);
+    let session = server.initialize(&[]);
+    let headers = [("Mcp-Session-Id", session.as_str()), ("Accept", "application/json, text/event-stream")];
+
+    let template = "| name | formula | result |\n|------|---------|--------|\n| x | 6 * 7 | |\n";
+    let response = server.request("POST", &headers, &call(2, "eval", json!({ "template": template })));
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("content-type"), Some("text/event-stream"));
+    assert!(response.body.starts_with("event: message\n"));
+    let message = response.message();
+    assert_eq!(message["id"], 2);
+    assert!(message["result"]["content"][0]["text"].as_str().unwrap().contains("42"));
+}
+
+#[test]
+fn test_bearer_token() {
+    let server = Server::start(Some("s3cret"));
+    let init = json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }).to_string();
+
+    let response = server.request("POST", &[], &init);
+    assert_eq!(response.status, 401);
+    assert_eq!(response.header("www-authenticate"), Some("Bearer"));
+    assert_eq!(server.request("POST", &[("Authorization", "Bearer wrong")], &init).status, 401);
+
+    server.initialize(&[("Authorization", "Bearer s3cret")]);
+}
+
+#[test]
+fn test_origin_check() {
+    let server = Server::start(None);
+    let init = json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }).to_string();
+    assert_eq!(server.request("POST", &[("Origin", "https://evil.example")], &init).status, 403);
+    assert_eq!(server.request("POST", &[("Origin", "http://localhost:3000")], &init).status, 200);
+}
+
+#[test]
+fn test_concurrent_sessions() {
+    let server = Server::start(None);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (1..=8u64)
+            .map(|n| {
+                let server = &server;
+                scope.spawn(move || {
+                    let session = server.initialize(&[]);
+                    let template = format!("| name | formula | result |\n|------|---------|--------|\n| x | {} * 1000 | |\n", n);
+                    let response = server.request(
+                        "POST",
+                        &[("Mcp-Session-Id", session.as_str())],
+                        &call(n, "eval", json!({ "template": template })),
+                    );
+                    let message = response.message();
+                    assert_eq!(message["id"], n);
+                    assert!(message["result"]["content"][0]["text"].as_str().unwrap().contains(&format!("{}000", n)));
+                    session
+                })
+            })
+            .collect();
+        let mut sessions: Vec<String> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        sessions.sort();
+        sessions.dedup();
+        assert_eq!(sessions.len(), 8);
+    });
+}
+
+#[test]
+fn test_malformed_body() {
+    let server = Server::start(None);
+    let response = server.request("POST", &[], "{not json");
+    assert_eq!(response.status, 400);
+    assert_eq!(response.message()["error"]["code"], -32700);
+    assert_eq!(server.request("GET", &[], "").status, 405);
+}
+
+#[test]
+fn test_cancel_request() {
+    let server = Server::start(None);
+    let session = server.initialize(&[]);
+    let with_session = [("Mcp-Session-Id", session.as_str())];
+
+    let rows: String = (0..400).map(|i| format!("| c{} | exp({}.5) | |\n", i, i)).collect();
+    let template = format!("## Slow @precision:200\n| name | formula | result |\n|---|---|---|\n{}", rows);
+    let started = std::time::Instant::now();
+    thread::scope(|scope| {
+        let slow = scope.spawn(|| server.request("POST", &with_session, &call(7, "eval", json!({ "template": template }))));
+
+        // Other requests in the session are served meanwhile
+        let ping = json!({ "jsonrpc": "2.0", "id": 8, "method": "ping" }).to_string();
+        assert_eq!(server.request("POST", &with_session, &ping).message()["id"], 8);
+        // Separate connections aren't ordered; give the evaluation time to start
+        thread::sleep(std::time::Duration::from_millis(300));
+
+        let cancel = json!({ "jsonrpc": "2.0", "method": "notifications/cancelled", "params": { "requestId": 7 } });
+        assert_eq!(server.request("POST", &with_session, &cancel.to_string()).status, 202);
+
+        let message = slow.join().unwrap().message();
+        assert_eq!(message["id"], 7);
+        assert_eq!(message["error"]["code"], -32800);
+    });
+    assert!(started.elapsed() < std::time::Duration::from_secs(10), "took {:?}", started.elapsed());
+}
+
+#[test]
+fn test_progress_over_sse() {
+    let server = Server::start(None);
+    let session = server.initialize(&[]);
+    let headers = [("Mcp-Session-Id", session.as_str()), ("Accept", "application/json, text/event-stream")];
+
+    let template = "| name | formula | result |\n|---|---|---|\n| a | 2 | |\n| b | a * 3 | |\n";
+    let body = json!({
+        "jsonrpc": "2.0", "id": 9, "method": "tools/call",
+        "params": { "name": "eval", "arguments": { "template": template }, "_meta": { "progressToken": 1 } }
+    });
+    let response = server.request("POST", &headers, &body.to_string());
+    let events: Vec<JsonValue> = response.body.lines()
+        .filter_map(|l| l.strip_prefix("data: "))
+        .map(|d| serde_json::from_str(d).unwrap())
+        .collect();
+    assert_eq!(events.len(), 3, "{}", response.body);
+    assert_eq!(events[0]["method"], "notifications/progress");
+    assert_eq!(events[1]["params"]["progress"], 2);
+    assert_eq!(events[1]["params"]["total"], 2);
+    assert_eq!(events[2]["id"], 9);
+}
+
+#[test]
+fn test_list_changed_on_get_stream() {
+    let data = std::env::temp_dir().join(format!("folio-http-store-{}", std::process::id()));
+    std::fs::create_dir_all(&data).unwrap();
+    let server = Server::start_in(None, &data);
+    let session = server.initialize(&[]);
+    let with_session = [("Mcp-Session-Id", session.as_str())];
+
+    thread::scope(|scope| {
+        let stream = scope.spawn(|| server.request("GET", &[("Mcp-Session-Id", session.as_str()), ("Accept", "text/event-stream")], ""));
+        // Give the stream time to open
+        thread::sleep(std::time::Duration::from_millis(300));
+
+        let response = server.request("POST", &with_session, &call(30, "save_document", json!({ "name": "shared", "content": "# Shared\n" })));
+        assert_eq!(response.message()["result"]["structuredContent"]["created"], true);
+        // Ending the session closes the stream
+        assert_eq!(server.request("DELETE", &with_session, "").status, 200);
+
+        let stream = stream.join().unwrap();
+        assert_eq!(stream.header("content-type"), Some("text/event-stream"));
+        assert_eq!(stream.message()["method"], "notifications/resources/list_changed");
+    });
+    let _ = std::fs::remove_dir_all(&data);
+}
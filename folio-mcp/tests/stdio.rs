@@ -0,0 +1,467 @@
+//! Concurrency and cancellation over stdio
+
+use serde_json::{json, Value as JsonValue};
+use std::io::{BufRead, BufReader, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl Server {
+    fn start() -> Self {
+        Self::start_in(&std::env::temp_dir())
+    }
+
+    fn start_in(data: &Path) -> Self {
+        Self::start_with(data, &[])
+    }
+
+    fn start_with(data: &Path, args: &[&str]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_folio-mcp"))
+            .args(args)
+            .env("FOLIO_DATA_PATH", data)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("spawn folio-mcp");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        Server { child, stdin, stdout }
+    }
+
+    fn send(&mut self, message: JsonValue) {
+        writeln!(self.stdin, "{}", message).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> JsonValue {
+        serde_json::from_str(&self.stdout.next().unwrap().unwrap()).unwrap()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Hundreds of high-precision cells: tens of seconds unless cancelled
+fn slow_eval(id: u64) -> JsonValue {
+    let rows: String = (0..400).map(|i| format!("| c{} | exp({}.5) | |\n", i, i)).collect();
+    let template = format!("## Slow @precision:200\n| name | formula | result |\n|---|---|---|\n{}", rows);
+    json!({
+        "jsonrpc": "2.0", "id": id, "method": "tools/call",
+        "params": { "name": "eval", "arguments": { "template": template } }
+    })
+}
+
+#[test]
+fn test_cancel_while_others_proceed() {
+    let mut server = Server::start();
+    let started = Instant::now();
+    server.send(slow_eval(1));
+    server.send(json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }));
+
+    // The ping is answered while the evaluation is still running
+    assert_eq!(server.receive()["id"], 2);
+
+    server.send(json!({
+        "jsonrpc": "2.0", "method": "notifications/cancelled",
+        "params": { "requestId": 1, "reason": "test" }
+    }));
+    let response = server.receive();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["error"]["code"], -32800);
+    assert!(started.elapsed() < Duration::from_secs(10), "took {:?}", started.elapsed());
+}
+
+#[test]
+fn test_duplicate_id_rejected() {
+    let mut server = Server::start();
+    server.send(slow_eval(1));
+    server.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }));
+    let duplicate = server.receive();
+    assert_eq!(duplicate["id"], 1);
+    assert_eq!(duplicate["error"]["code"], -32600);
+
+    // The cancellation still reaches the request that owns the id
+    server.send(json!({ "jsonrpc": "2.0", "method": "notifications/cancelled", "params": { "requestId": 1 } }));
+    assert_eq!(server.receive()["error"]["code"], -32800);
+}
+
+#[test]
+fn test_cancel_unknown_request_is_ignored() {
+    let mut server = Server::start();
+    server.send(json!({ "jsonrpc": "2.0", "method": "notifications/cancelled", "params": { "requestId": 99 } }));
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+        "params": { "name": "eval", "arguments": { "template": "| name | formula | result |\n|---|---|---|\n| x | 2 + 2 | |\n" } }
+    }));
+    let response = server.receive();
+    assert_eq!(response["id"], 3);
+    assert!(response["result"]["content"][0]["text"].as_str().unwrap().contains('4'));
+}
+
+/// Messages up to and including the response to `id`
+fn until_response(server: &mut Server, id: u64) -> Vec<JsonValue> {
+    let mut messages = Vec::new();
+    loop {
+        let message = server.receive();
+        let done = message["id"] == id;
+        messages.push(message);
+        if done {
+            return messages;
+        }
+    }
+}
+
+fn progress(messages: &[JsonValue]) -> Vec<(u64, u64, String)> {
+    messages.iter()
+        .filter(|m| m["method"] == "notifications/progress")
+        .map(|m| {
+            let p = &m["params"];
+            assert_eq!(p["progressToken"], "tok");
+            (p["progress"].as_u64().unwrap(), p["total"].as_u64().unwrap(), p["message"].as_str().unwrap().to_string())
+        })
+        .collect()
+}
+
+const TWO_CELLS: &str = "| name | formula | result |\n|---|---|---|\n| a | 2 | |\n| b | a * x | |\n";
+
+#[test]
+fn test_progress_notifications() {
+    let mut server = Server::start();
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 4, "method": "tools/call",
+        "params": { "name": "eval", "arguments": { "template": TWO_CELLS, "variables": { "x": 3 } }, "_meta": { "progressToken": "tok" } }
+    }));
+    let messages = until_response(&mut server, 4);
+    assert_eq!(progress(&messages), vec![(1, 2, "1/2 cells".to_string()), (2, 2, "2/2 cells".to_string())]);
+
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 5, "method": "tools/call",
+        "params": {
+            "name": "eval_batch",
+            "arguments": { "template": TWO_CELLS, "variable_sets": [{ "x": 1 }, { "x": 2 }] },
+            "_meta": { "progressToken": "tok" }
+        }
+    }));
+    let messages = until_response(&mut server, 5);
+    let steps = progress(&messages);
+    assert_eq!(steps.iter().map(|s| (s.0, s.1)).collect::<Vec<_>>(), vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+    assert_eq!(steps[1].2, "1/2 sets");
+    assert_eq!(steps[2].2, "set 2/2: 1/2 cells");
+    assert_eq!(steps[3].2, "2/2 sets");
+
+    // No token, no notifications
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 6, "method": "tools/call",
+        "params": { "name": "eval", "arguments": { "template": TWO_CELLS, "variables": { "x": 3 } } }
+    }));
+    assert_eq!(until_response(&mut server, 6).len(), 1);
+}
+
+#[test]
+fn test_file_access_is_sandboxed() {
+    let mut server = Server::start();
+    for (id, name) in [(10, "/etc/passwd"), (11, "../../etc/passwd")] {
+        server.send(json!({
+            "jsonrpc": "2.0", "id": id, "method": "tools/call",
+            "params": { "name": "eval_file", "arguments": { "name": name } }
+        }));
+        let response = server.receive();
+        assert_eq!(response["error"]["data"]["code"], "FORBIDDEN", "{}", response);
+        assert!(response["error"]["message"].as_str().unwrap().starts_with("FORBIDDEN: "));
+    }
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 12, "method": "resources/read",
+        "params": { "uri": "folio://documents/../../etc/passwd" }
+    }));
+    assert_eq!(server.receive()["error"]["data"]["code"], "FORBIDDEN");
+}
+
+/// Fresh data directory, removed on drop
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(label: &str) -> Self {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let path = std::env::temp_dir().join(format!("folio-{}-{}-{}", label, std::process::id(), nanos));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn tool(id: u64, name: &str, arguments: JsonValue) -> JsonValue {
+    json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": name, "arguments": arguments } })
+}
+
+#[test]
+fn test_save_document_versions() {
+    let data = TempDir::new("store");
+    let mut server = Server::start_in(&data.0);
+
+    server.send(tool(20, "save_document", json!({ "name": "budget", "content": TWO_CELLS })));
+    let messages = until_response(&mut server, 20);
+    assert_eq!(messages[0]["method"], "notifications/resources/list_changed");
+    let saved = &messages[1]["result"]["structuredContent"];
+    assert_eq!(saved["version"], 1);
+    assert_eq!(saved["created"], true);
+    let hash = saved["hash"].as_str().unwrap().to_string();
+    assert!(data.0.join("budget.fmd").exists());
+
+    // A stale hash is a conflict and changes nothing
+    server.send(tool(21, "save_document", json!({ "name": "budget", "content": "# Other", "expected_hash": "0000000000000000" })));
+    let conflict = server.receive();
+    assert_eq!(conflict["result"]["isError"], true);
+    assert!(conflict["result"]["content"][0]["text"].as_str().unwrap().starts_with("CONFLICT: "));
+    assert!(conflict["result"].get("error").is_none(), "{}", conflict);
+    assert!(conflict["result"]["content"][0]["text"].as_str().unwrap().contains(&hash));
+
+    server.send(tool(22, "save_document", json!({ "name": "budget", "content": "# Budget\n", "expected_hash": hash })));
+    let messages = until_response(&mut server, 22);
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1]["result"]["structuredContent"]["version"], 2);
+
+    server.send(tool(23, "list_versions", json!({ "name": "budget" })));
+    let listing = server.receive()["result"]["structuredContent"].clone();
+    let versions = listing["versions"].as_array().unwrap();
+    assert_eq!(versions.iter().map(|v| v["version"].as_u64().unwrap()).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(versions[0]["hash"], hash.as_str());
+    let current = listing["current_hash"].as_str().unwrap().to_string();
+
+    server.send(tool(24, "delete_document", json!({ "name": "budget", "expected_hash": current })));
+    let messages = until_response(&mut server, 24);
+    assert_eq!(messages[0]["method"], "notifications/resources/list_changed");
+    assert_eq!(messages[1]["result"]["structuredContent"]["deleted"], true);
+    assert!(!data.0.join("budget.fmd").exists());
+
+    server.send(tool(25, "save_document", json!({ "name": "../escape", "content": "# x" })));
+    assert!(server.receive()["result"]["content"][0]["text"].as_str().unwrap().starts_with("INVALID_NAME: "));
+}
+
+#[test]
+fn test_notebook_session() {
+    let mut server = Server::start();
+    server.send(tool(30, "open_session", json!({ "template": TWO_CELLS, "variables": { "x": 3 } })));
+    let opened = server.receive()["result"]["structuredContent"].clone();
+    assert!(opened["values"]["b"].as_str().unwrap().starts_with("6"));
+    let id = opened["session_id"].as_str().unwrap().to_string();
+
+    server.send(tool(31, "set_cell", json!({ "session_id": id, "name": "a", "formula": "5" })));
+    let edited = server.receive()["result"]["structuredContent"].clone();
+    assert_eq!(edited["added"], false);
+    let changed: Vec<&str> = edited["changes"].as_array().unwrap().iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(changed, vec!["a", "b"]);
+    assert!(edited["changes"][1]["new"].as_str().unwrap().starts_with("15"));
+    assert_eq!(edited["changes"][1]["cause"]["upstream"], json!(["a"]));
+
+    server.send(tool(32, "set_inputs", json!({ "session_id": id, "variables": { "x": 2 } })));
+    let edited = server.receive()["result"]["structuredContent"].clone();
+    assert_eq!(edited["changes"].as_array().unwrap().len(), 1);
+    assert!(edited["changes"][0]["new"].as_str().unwrap().starts_with("10"));
+
+    server.send(tool(33, "get_cell", json!({ "session_id": id, "name": "b" })));
+    let cell = server.receive()["result"]["structuredContent"].clone();
+    assert_eq!(cell["formula"], "a * x");
+    assert_eq!(cell["references"], json!(["a", "x"]));
+
+    server.send(tool(34, "render", json!({ "session_id": id, "format": "source" })));
+    let rendered = server.receive()["result"].clone();
+    let source = rendered["content"][0]["text"].as_str().unwrap().to_string();
+    assert!(source.contains("| a    | 5"), "{}", source);
+    assert_eq!(rendered["structuredContent"], json!({ "format": "source", "text": source }));
+
+    server.send(tool(35, "close_session", json!({ "session_id": id })));
+    let closed = server.receive()["result"].clone();
+    assert!(closed["isError"].is_null());
+    assert_eq!(closed["structuredContent"]["closed"], true);
+    server.send(tool(36, "get_cell", json!({ "session_id": id, "name": "b" })));
+    let missing = server.receive()["result"].clone();
+    assert_eq!(missing["isError"], true);
+    assert!(missing["content"][0]["text"].as_str().unwrap().starts_with("SESSION_NOT_FOUND: "), "{}", missing);
+}
+
+#[test]
+fn test_prompts_from_front_matter() {
+    let data = TempDir::new("prompts");
+    std::fs::write(data.0.join("tip.fmd"), "---\nname: tip\ndescription: Split a bill\narguments:\n  - name: bill\n  - name: percent\n    variable: rate\n    default: 15\n---\n# Tip\n\n| name | formula | result |\n|---|---|---|\n| rate | 10 | |\n| tip | bill * rate / 100 | |\n").unwrap();
+    std::fs::write(data.0.join("plain.fmd"), "# Plain\n").unwrap();
+    let mut server = Server::start_in(&data.0);
+
+    server.send(json!({ "jsonrpc": "2.0", "id": 40, "method": "prompts/list" }));
+    let prompts = server.receive()["result"]["prompts"].clone();
+    assert_eq!(prompts.as_array().unwrap().len(), 1);
+    assert_eq!(prompts[0]["name"], "tip");
+    assert_eq!(prompts[0]["arguments"][0]["required"], true);
+    assert_eq!(prompts[0]["arguments"][1]["required"], false);
+
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 41, "method": "prompts/get",
+        "params": { "name": "tip", "arguments": { "bill": "80" } }
+    }));
+    let response = server.receive();
+    assert_eq!(response["result"]["description"], "Split a bill");
+    let text = response["result"]["messages"][0]["content"]["text"].as_str().unwrap();
+    assert!(text.contains("| tip"), "{}", text);
+    assert!(text.contains("12"), "{}", text);
+    assert!(!text.contains("name: tip"), "{}", text);
+
+    server.send(json!({
+        "jsonrpc": "2.0", "id": 42, "method": "prompts/get",
+        "params": { "name": "tip", "arguments": { "bill": "a lot", "tax": "1" } }
+    }));
+    let error = server.receive()["error"].clone();
+    assert_eq!(error["code"], -32602);
+    assert_eq!(error["data"]["problems"].as_array().unwrap().len(), 2);
+}
+
+fn complete(id: u64, reference: JsonValue, name: &str, value: &str) -> JsonValue {
+    json!({
+        "jsonrpc": "2.0", "id": id, "method": "completion/complete",
+        "params": { "ref": reference, "argument": { "name": name, "value": value } }
+    })
+}
+
+fn values(response: &JsonValue) -> Vec<String> {
+    serde_json::from_value(response["result"]["completion"]["values"].clone()).unwrap()
+}
+
+#[test]
+fn test_completions() {
+    let data = TempDir::new("complete");
+    std::fs::write(data.0.join("tip.fmd"), "---\narguments:\n  - name: percent\n    values: [10, 15, 20]\n    default: 18\n---\n# Tip\n").unwrap();
+    std::fs::write(data.0.join("tax.fmd"), "# Tax\n").unwrap();
+    std::fs::write(data.0.join("budget.fmd"), "# Budget\n").unwrap();
+    let mut server = Server::start_in(&data.0);
+
+    server.send(json!({ "jsonrpc": "2.0", "id": 50, "method": "initialize", "params": {} }));
+    assert!(server.receive()["result"]["capabilities"]["completions"].is_object());
+
+    let documents = json!({ "type": "ref/resource", "uri": "folio://documents/{name}" });
+    server.send(complete(51, documents, "name", "T"));
+    assert_eq!(values(&server.receive()), vec!["tax", "tip"]);
+
+    server.send(complete(52, json!({ "type": "ref/resource", "uri": "folio://functions/{name}" }), "name", "sqr"));
+    assert!(values(&server.receive()).contains(&"sqrt".to_string()));
+
+    #[cfg(feature = "units")]
+    {
+        server.send(complete(53, json!({ "type": "ref/resource", "uri": "folio://units/{name}" }), "name", "kilom"));
+        assert!(values(&server.receive()).contains(&"kilometres".to_string()));
+    }
+
+    server.send(complete(54, json!({ "type": "ref/prompt", "name": "tip" }), "percent", "1"));
+    assert_eq!(values(&server.receive()), vec!["10", "15", "18"]);
+
+    server.send(complete(55, json!({ "type": "ref/prompt", "name": "nope" }), "percent", ""));
+    assert_eq!(server.receive()["error"]["code"], -32602);
+
+    server.send(json!({ "jsonrpc": "2.0", "id": 56, "method": "resources/templates/list" }));
+    let templates = if cfg!(feature = "units") { 3 } else { 2 };
+    assert_eq!(server.receive()["result"]["resourceTemplates"].as_array().unwrap().len(), templates);
+
+    server.send(json!({ "jsonrpc": "2.0", "id": 57, "method": "resources/read", "params": { "uri": "folio://functions/sqrt" } }));
+    let text = server.receive()["result"]["contents"][0]["text"].as_str().unwrap().to_string();
+    assert!(text.starts_with("# sqrt"), "{}", text);
+
+    #[cfg(feature = "units")]
+    {
+        server.send(json!({ "jsonrpc": "2.0", "id": 58, "method": "resources/read", "params": { "uri": "folio://units/km" } }));
+        let text = server.receive()["result"]["contents"][0]["text"].as_str().unwrap().to_string();
+        assert!(text.contains("**SI factor:** 1000"), "{}", text);
+    }
+}
+
+#[test]
+fn test_structured_content() {
+    let mut server = Server::start();
+    server.send(json!({ "jsonrpc": "2.0", "id": 60, "method": "tools/list" }));
+    let tools = server.receive()["result"]["tools"].as_array().unwrap().clone();
+    let schema = |name: &str| tools.iter().find(|t| t["name"] == name).unwrap()["outputSchema"].clone();
+
+    let template = "| name | formula | result |\n|---|---|---|\n| a | 6 | |\n| b | a * 7 | |\n";
+    let calls = [
+        ("eval", json!({ "template": template })),
+        ("eval", json!({ "template": template, "format": "html" })),
+        ("eval_batch", json!({ "template": template, "variable_sets": [{}, {}], "compare_field": "b" })),
+        ("folio", json!({ "name": "sqrt" })),
+        ("folio", json!({ "compact": true })),
+        ("list_functions", json!({})),
+        ("list_constants", json!({})),
+        ("decompose", json!({ "value": "1.618" })),
+        ("fmt", json!({ "template": template })),
+        ("quick", json!({})),
+    ];
+    for (id, (name, arguments)) in (61..).zip(calls) {
+        let schema = schema(name);
+        assert_eq!(schema["type"], "object", "{}", name);
+        server.send(tool(id, name, arguments));
+        let result = server.receive()["result"].clone();
+        let content = &result["structuredContent"];
+        for key in schema["required"].as_array().unwrap() {
+            assert!(!content[key.as_str().unwrap()].is_null(), "{} lacks {}: {}", name, key, result);
+        }
+        if name == "eval" {
+            assert!(content["values"]["b"].as_str().unwrap().starts_with("42"), "{}", content);
+        }
+    }
+
+    server.send(tool(75, "folio", json!({ "name": "no_such_function" })));
+    assert_eq!(server.receive()["result"]["isError"], true);
+
+    // Every tool declares what it returns
+    for tool in &tools {
+        assert_eq!(tool["outputSchema"]["type"], "object", "{}", tool["name"]);
+    }
+}
+
+#[test]
+fn test_config_file() {
+    let data = TempDir::new("config");
+    let config = data.0.join("folio.toml");
+    std::fs::write(&config, "libraries = []\n\n[limits]\nmax_batch_sets = 1\nmax_document_bytes = 200\n\n[instructions]\nserver = \"Show your work.\"\nresults = \"\"\n").unwrap();
+    let mut server = Server::start_with(&data.0, &["--config", config.to_str().unwrap()]);
+
+    server.send(json!({ "jsonrpc": "2.0", "id": 80, "method": "initialize", "params": {} }));
+    assert_eq!(server.receive()["result"]["instructions"], "Show your work.");
+
+    let template = "| name | formula | result |\n|---|---|---|\n| a | sqrt(16) | |\n";
+    server.send(tool(81, "eval", json!({ "template": template })));
+    let text = server.receive()["result"]["content"][0]["text"].as_str().unwrap().to_string();
+    assert!(text.starts_with("| name"), "{}", text);
+
+    // Only the standard library is loaded
+    server.send(tool(82, "folio", json!({ "name": "covariance" })));
+    assert_eq!(server.receive()["result"]["isError"], true);
+
+    server.send(tool(83, "eval_batch", json!({ "template": template, "variable_sets": [{}, {}] })));
+    assert_eq!(server.receive()["error"]["code"], -32602);
+
+    std::fs::write(data.0.join("big.fmd"), format!("# Big\n\n{}", template.repeat(4))).unwrap();
+    server.send(tool(84, "eval_file", json!({ "name": "big" })));
+    let response = server.receive();
+    assert_eq!(response["error"]["code"], -32602, "{}", response);
+
+    std::fs::write(&config, "libraries = [\"astrology\"]\n").unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_folio-mcp"))
+        .args(["--config", config.to_str().unwrap()])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
@@ -3,6 +3,7 @@
 use folio_core::Value;
 use crate::PluginRegistry;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 /// Evaluation context passed to plugins
@@ -12,6 +13,45 @@ pub struct EvalContext {
     pub registry: Arc<PluginRegistry>,
     pub tracing: bool,
     pub trace: Vec<TraceStep>,
+    /// Checked between cells and before function calls
+    pub cancel: CancelToken,
+    /// Called after each cell is evaluated
+    pub progress: Option<ProgressCallback>,
+}
+
+/// Evaluation progress, reported after each cell
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Cell just evaluated
+    pub cell: String,
+    /// Cells evaluated so far, including this one
+    pub done: usize,
+    pub total: usize,
+}
+
+pub type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Shared flag asking a running evaluation to stop
+///
+/// Clones share the flag, so one can be handed to the evaluation and another
+/// kept to cancel it from a different thread. Cancellation is cooperative:
+/// cells not yet evaluated become `CANCELLED` errors, but a function that is
+/// already running finishes first.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 /// Single step in evaluation trace
@@ -21,6 +61,18 @@ pub struct TraceStep {
     pub formula: String,
     pub result: Value,
     pub dependencies: Vec<String>,
+    /// The formula typeset as LaTeX and MathML, when the evaluator provides it
+    pub typeset: Option<Typeset>,
+}
+
+/// A formula typeset for display
+#[derive(Debug, Clone)]
+pub struct Typeset {
+    pub latex: String,
+    pub mathml: String,
+    /// Formula with its dependencies' values substituted, then the result:
+    /// `300000 × 0.005 = 1500`
+    pub work: String,
 }
 
 impl EvalContext {
@@ -31,6 +83,8 @@ impl EvalContext {
             registry,
             tracing: false,
             trace: Vec::new(),
+            cancel: CancelToken::new(),
+            progress: None,
         }
     }
     
@@ -48,6 +102,26 @@ impl EvalContext {
         self.tracing = enabled;
         self
     }
+
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
+        self.progress = Some(callback);
+        self
+    }
+
+    pub fn report_progress(&self, cell: &str, done: usize, total: usize) {
+        if let Some(callback) = &self.progress {
+            callback(&Progress { cell: cell.to_string(), done, total });
+        }
+    }
     
     pub fn get_var(&self, name: &str) -> Value {
         let parts: Vec<&str> = name.split('.').collect();
@@ -116,7 +190,14 @@ impl EvalContext {
     
     pub fn record_trace(&mut self, cell: String, formula: String, result: Value, dependencies: Vec<String>) {
         if self.tracing {
-            self.trace.push(TraceStep { cell, formula, result, dependencies });
+            self.trace.push(TraceStep { cell, formula, result, dependencies, typeset: None });
+        }
+    }
+
+    /// Record a trace step whose formula has been typeset
+    pub fn record_trace_typeset(&mut self, cell: String, formula: String, result: Value, dependencies: Vec<String>, typeset: Typeset) {
+        if self.tracing {
+            self.trace.push(TraceStep { cell, formula, result, dependencies, typeset: Some(typeset) });
         }
     }
 }
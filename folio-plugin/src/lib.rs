@@ -4,6 +4,7 @@
 //! - Functions (pure computation)
 //! - Analyzers (pattern detection)
 //! - Commands (side effects)
+//! - Value types (`FolioExtValue`, carried as `Value::Custom`)
 
 mod traits;
 mod registry;
@@ -16,7 +17,8 @@ pub use traits::{
     ArgMeta,
 };
 pub use registry::{PluginRegistry, ConstantDef};
-pub use context::{EvalContext, TraceStep};
+pub use context::{EvalContext, TraceStep, Typeset, CancelToken, Progress, ProgressCallback};
+pub use folio_core::{FolioExtValue, ExtOp};
 
 /// Re-export core types for plugin authors
 pub mod prelude {
@@ -24,7 +26,7 @@ pub mod prelude {
         FunctionPlugin, FunctionMeta,
         AnalyzerPlugin, AnalyzerMeta,
         CommandPlugin, CommandMeta,
-        ArgMeta, PluginRegistry, EvalContext, TraceStep,
+        ArgMeta, PluginRegistry, EvalContext, TraceStep, Typeset, CancelToken,
     };
     pub use folio_core::prelude::*;
 }
@@ -88,6 +88,24 @@ impl PluginRegistry {
         }
     }
 
+    /// Names [`help`](Self::help) knows that start with `prefix`, sorted;
+    /// when none does, the function names most similar to it
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        let mut names: Vec<String> = self.functions.keys()
+            .chain(self.commands.keys())
+            .chain(self.constants.keys())
+            .filter(|name| name.starts_with(&prefix))
+            .cloned()
+            .collect();
+        if names.is_empty() && !prefix.is_empty() {
+            return self.find_similar_functions(&prefix).into_iter().take(10).collect();
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
     /// Find function names similar to the given name (for error suggestions)
     fn find_similar_functions(&self, name: &str) -> Vec<String> {
         let name_lower = name.to_lowercase();
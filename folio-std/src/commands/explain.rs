@@ -46,6 +46,11 @@ impl CommandPlugin for Explain {
         if !trace_steps.is_empty() {
             let step = trace_steps.last().unwrap();
             result.insert("formula".to_string(), Value::Text(step.formula.clone()));
+            if let Some(typeset) = &step.typeset {
+                result.insert("latex".to_string(), Value::Text(typeset.latex.clone()));
+                result.insert("mathml".to_string(), Value::Text(typeset.mathml.clone()));
+                result.insert("work".to_string(), Value::Text(format!("{} = {}", cell_name, typeset.work)));
+            }
             result.insert("dependencies".to_string(),
                 Value::List(step.dependencies.iter().map(|d| Value::Text(d.clone())).collect()));
 
@@ -60,6 +65,9 @@ impl CommandPlugin for Explain {
                 // Find formula for dependency
                 if let Some(dep_step) = ctx.trace.iter().find(|s| &s.cell == dep_name) {
                     dep_info.insert("formula".to_string(), Value::Text(dep_step.formula.clone()));
+                    if let Some(typeset) = &dep_step.typeset {
+                        dep_info.insert("latex".to_string(), Value::Text(typeset.latex.clone()));
+                    }
                 }
 
                 dep_chain.push(Value::Object(dep_info));
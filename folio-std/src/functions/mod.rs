@@ -9,7 +9,8 @@ mod utility;
 pub use math::{Sqrt, Ln, Exp, Pow, Abs, Round, Floor, Ceil};
 pub use trig::{Sin, Cos, Tan};
 pub use aggregate::Sum;
-pub use utility::{FieldsFn, HeadFn, TailFn, TakeFn, TypeofFn, DescribeFn, LenFn, NthFn};
+pub use utility::{FieldsFn, HeadFn, TailFn, TakeFn, TypeofFn, DescribeFn, LenFn, NthFn, FilterFn,
+    MergeFn, KeysFn, ValuesFn, EntriesFn};
 
 // DateTime functions
 pub use datetime::{
@@ -1,4 +1,5 @@
-//! Utility functions for LLM experience: fields, head, tail, typeof, describe
+//! Utility functions for LLM experience: fields, head, tail, typeof, describe, filter,
+//! and record helpers (merge, keys, values, entries)
 
 use folio_plugin::prelude::*;
 use std::collections::HashMap;
@@ -45,6 +46,11 @@ impl FunctionPlugin for FieldsFn {
                 keys.sort();
                 Value::List(keys.into_iter().map(Value::Text).collect())
             }
+            Value::Custom(ext) => {
+                let mut keys = ext.field_names();
+                keys.sort();
+                Value::List(keys.into_iter().map(Value::Text).collect())
+            }
             Value::Error(e) => Value::Error(e.clone()),
             other => Value::Error(
                 FolioError::arg_type("fields", "object", "Object", other.type_name())
@@ -475,3 +481,275 @@ impl FunctionPlugin for NthFn {
         list[index as usize].clone()
     }
 }
+
+// ============================================================================
+// filter(list, mask) → List
+// ============================================================================
+
+pub struct FilterFn;
+
+static FILTER_ARGS: [ArgMeta; 2] = [
+    ArgMeta {
+        name: "list",
+        typ: "List",
+        description: "List to filter",
+        optional: false,
+        default: None,
+    },
+    ArgMeta {
+        name: "mask",
+        typ: "List<Bool>",
+        description: "Same-length list of Bool, e.g. from a comparison like prices > 10",
+        optional: false,
+        default: None,
+    },
+];
+static FILTER_EXAMPLES: [&str; 2] = [
+    "filter([5, 12, 30], [false, true, true]) → [12, 30]",
+    "filter(prices, prices > 10)",
+];
+static FILTER_RELATED: [&str; 2] = ["nth", "take"];
+
+impl FunctionPlugin for FilterFn {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "filter",
+            description: "Keep the elements whose mask entry is true",
+            usage: "filter(list, mask)",
+            args: &FILTER_ARGS,
+            returns: "List",
+            examples: &FILTER_EXAMPLES,
+            category: "utility",
+            source: None,
+            related: &FILTER_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        if args.len() != 2 {
+            return Value::Error(FolioError::arg_count("filter", 2, args.len()));
+        }
+
+        let list = match &args[0] {
+            Value::List(l) => l,
+            Value::Error(e) => return Value::Error(e.clone()),
+            other => return Value::Error(FolioError::arg_type("filter", "list", "List", other.type_name())),
+        };
+
+        let mask = match &args[1] {
+            Value::List(m) => m,
+            Value::Error(e) => return Value::Error(e.clone()),
+            other => return Value::Error(FolioError::arg_type("filter", "mask", "List<Bool>", other.type_name())),
+        };
+
+        if list.len() != mask.len() {
+            return Value::Error(FolioError::shape_mismatch(format!(
+                "mask of length {} for list of length {}",
+                mask.len(), list.len()
+            )));
+        }
+
+        let mut kept = Vec::new();
+        for (item, flag) in list.iter().zip(mask) {
+            match flag {
+                Value::Bool(true) => kept.push(item.clone()),
+                Value::Bool(false) => {}
+                Value::Error(e) => return Value::Error(e.clone()),
+                other => return Value::Error(FolioError::arg_type("filter", "mask", "List<Bool>", &format!("List containing {}", other.type_name()))),
+            }
+        }
+        Value::List(kept)
+    }
+}
+
+// ============================================================================
+// Record helpers: merge, keys, values, entries
+// ============================================================================
+
+/// Extract the single Object argument of a record helper
+fn object_arg<'a>(func: &str, args: &'a [Value]) -> Result<&'a HashMap<String, Value>, Value> {
+    if args.len() != 1 {
+        return Err(Value::Error(FolioError::arg_count(func, 1, args.len())));
+    }
+    match &args[0] {
+        Value::Object(map) => Ok(map),
+        Value::Error(e) => Err(Value::Error(e.clone())),
+        other => Err(Value::Error(
+            FolioError::arg_type(func, "object", "Object", other.type_name())
+                .with_suggestion("Build objects with a record literal: {name: expr, ...}")
+        )),
+    }
+}
+
+/// Keys in sorted order so results are deterministic
+fn sorted_keys(map: &HashMap<String, Value>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+pub struct MergeFn;
+
+static MERGE_ARGS: [ArgMeta; 2] = [
+    ArgMeta {
+        name: "base",
+        typ: "Object",
+        description: "Object to start from",
+        optional: false,
+        default: None,
+    },
+    ArgMeta {
+        name: "others",
+        typ: "Object",
+        description: "Objects whose fields override earlier ones",
+        optional: false,
+        default: None,
+    },
+];
+static MERGE_EXAMPLES: [&str; 2] = [
+    "merge({a: 1}, {b: 2}) → {a: 1, b: 2}",
+    "merge(defaults, {rate: 0.05}) → defaults with rate replaced",
+];
+static MERGE_RELATED: [&str; 3] = ["keys", "values", "entries"];
+
+impl FunctionPlugin for MergeFn {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "merge",
+            description: "Combine objects; later fields win",
+            usage: "merge(base, other, ...)",
+            args: &MERGE_ARGS,
+            returns: "Object",
+            examples: &MERGE_EXAMPLES,
+            category: "utility",
+            source: None,
+            related: &MERGE_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        if args.len() < 2 {
+            return Value::Error(FolioError::arg_count("merge", 2, args.len())
+                .with_suggestion("Usage: merge(base, other, ...)"));
+        }
+
+        let mut merged = HashMap::new();
+        for (i, arg) in args.iter().enumerate() {
+            match arg {
+                Value::Object(map) => merged.extend(map.iter().map(|(k, v)| (k.clone(), v.clone()))),
+                Value::Error(e) => return Value::Error(e.clone()),
+                other => return Value::Error(FolioError::arg_type(
+                    "merge", &format!("argument {}", i + 1), "Object", other.type_name(),
+                )),
+            }
+        }
+        Value::Object(merged)
+    }
+}
+
+pub struct KeysFn;
+
+static KEYS_ARGS: [ArgMeta; 1] = [ArgMeta {
+    name: "object",
+    typ: "Object",
+    description: "Object to list keys of",
+    optional: false,
+    default: None,
+}];
+static KEYS_EXAMPLES: [&str; 1] = ["keys({b: 2, a: 1}) → [\"a\", \"b\"]"];
+static KEYS_RELATED: [&str; 3] = ["values", "entries", "fields"];
+
+impl FunctionPlugin for KeysFn {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "keys",
+            description: "Sorted field names of an Object",
+            usage: "keys(object)",
+            args: &KEYS_ARGS,
+            returns: "List<Text>",
+            examples: &KEYS_EXAMPLES,
+            category: "utility",
+            source: None,
+            related: &KEYS_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        match object_arg("keys", args) {
+            Ok(map) => Value::List(sorted_keys(map).into_iter().map(|k| Value::Text(k.clone())).collect()),
+            Err(e) => e,
+        }
+    }
+}
+
+pub struct ValuesFn;
+
+static VALUES_ARGS: [ArgMeta; 1] = [ArgMeta {
+    name: "object",
+    typ: "Object",
+    description: "Object to list values of",
+    optional: false,
+    default: None,
+}];
+static VALUES_EXAMPLES: [&str; 1] = ["values({b: 2, a: 1}) → [1, 2]"];
+static VALUES_RELATED: [&str; 2] = ["keys", "entries"];
+
+impl FunctionPlugin for ValuesFn {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "values",
+            description: "Field values of an Object, in sorted key order",
+            usage: "values(object)",
+            args: &VALUES_ARGS,
+            returns: "List",
+            examples: &VALUES_EXAMPLES,
+            category: "utility",
+            source: None,
+            related: &VALUES_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        match object_arg("values", args) {
+            Ok(map) => Value::List(sorted_keys(map).into_iter().map(|k| map[k].clone()).collect()),
+            Err(e) => e,
+        }
+    }
+}
+
+pub struct EntriesFn;
+
+static ENTRIES_ARGS: [ArgMeta; 1] = [ArgMeta {
+    name: "object",
+    typ: "Object",
+    description: "Object to list entries of",
+    optional: false,
+    default: None,
+}];
+static ENTRIES_EXAMPLES: [&str; 1] = ["entries({b: 2, a: 1}) → [[\"a\", 1], [\"b\", 2]]"];
+static ENTRIES_RELATED: [&str; 2] = ["keys", "values"];
+
+impl FunctionPlugin for EntriesFn {
+    fn meta(&self) -> FunctionMeta {
+        FunctionMeta {
+            name: "entries",
+            description: "[key, value] pairs of an Object, in sorted key order",
+            usage: "entries(object)",
+            args: &ENTRIES_ARGS,
+            returns: "List<List>",
+            examples: &ENTRIES_EXAMPLES,
+            category: "utility",
+            source: None,
+            related: &ENTRIES_RELATED,
+        }
+    }
+
+    fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+        match object_arg("entries", args) {
+            Ok(map) => Value::List(sorted_keys(map).into_iter()
+                .map(|k| Value::List(vec![Value::Text(k.clone()), map[k].clone()]))
+                .collect()),
+            Err(e) => e,
+        }
+    }
+}
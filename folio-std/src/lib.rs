@@ -97,6 +97,11 @@ pub fn load_standard_library(registry: PluginRegistry) -> PluginRegistry {
         .with_function(functions::DescribeFn)
         .with_function(functions::LenFn)
         .with_function(functions::NthFn)
+        .with_function(functions::FilterFn)
+        .with_function(functions::MergeFn)
+        .with_function(functions::KeysFn)
+        .with_function(functions::ValuesFn)
+        .with_function(functions::EntriesFn)
         // Analyzers
         .with_analyzer(analyzers::PhiAnalyzer)
         .with_analyzer(analyzers::PiAnalyzer)
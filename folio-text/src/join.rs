@@ -305,6 +305,7 @@ fn value_to_string(value: &Value) -> String {
         Value::Duration(d) => format!("{} seconds", d.as_secs()),
         Value::List(_) => "[List]".to_string(),
         Value::Object(_) => "[Object]".to_string(),
+        Value::Custom(_) => value.to_string(),
         Value::Error(e) => format!("[Error: {}]", e.message),
     }
 }
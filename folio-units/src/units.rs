@@ -49,6 +49,13 @@ impl UnitRegistry {
         self.units.keys().map(|s| s.as_str()).collect()
     }
 
+    /// Get all symbols and aliases, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.units.keys().chain(self.aliases.keys()).map(|s| s.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
     fn register(&mut self, unit: Unit) {
         self.units.insert(unit.symbol.clone(), unit);
     }
@@ -579,6 +586,11 @@ mod tests {
 
         // Test unknown unit
         assert!(reg.get("unknown_xyz").is_none());
+
+        // Symbols and aliases, for completion
+        let names = reg.names();
+        assert!(names.contains(&"km") && names.contains(&"kilometres"));
+        assert!(names.windows(2).all(|w| w[0] <= w[1]));
     }
 
     #[test]
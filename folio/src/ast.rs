@@ -1,18 +1,25 @@
 //! Abstract Syntax Tree
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Document {
+    /// Text between `---` lines at the very top (YAML by convention); not rendered
+    pub front_matter: Option<String>,
+    /// Markdown before the first section, kept verbatim
+    pub preamble: Vec<String>,
     pub sections: Vec<Section>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Section {
     pub name: String,
-    pub attributes: HashMap<String, String>,
+    pub attributes: Attributes,
+    /// Heading line as written; None for the implicit section of a headerless document
+    pub heading: Option<String>,
     pub table: Table,
+    /// Everything after the heading in source order, for round-trip rendering
+    pub body: Vec<Block>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -24,6 +31,64 @@ pub struct Table {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Row {
     pub cells: Vec<Cell>,
+    /// All cell texts of the source row, including extra columns
+    pub raw_cells: Vec<String>,
+}
+
+/// A piece of a section body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Block {
+    /// Markdown outside tables (prose, blank lines, code), verbatim
+    Text(String),
+    /// Table header row; `result_column` is None when the table has no result column
+    Header { cells: Vec<String>, result_column: Option<usize> },
+    /// Table separator row with the user's alignment markers
+    Separator(Vec<String>),
+    /// Data row, as an index into `Section::table.rows`
+    Row(usize),
+}
+
+/// `@key:value` attributes in source order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Set a value; an existing key keeps its position
+    pub fn insert(&mut self, key: String, value: String) {
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.0.push((key, value)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let pos = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(pos).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +96,8 @@ pub struct Cell {
     pub name: String,
     pub formula: Option<Expr>,
     pub raw_text: String,
+    /// Per-cell annotations after the formula, e.g. `x / y @format:percent`
+    pub attributes: Attributes,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,16 +110,35 @@ pub enum Expr {
     FunctionCall(String, Vec<Expr>),
     /// List literal: [a, b, c]
     List(Vec<Expr>),
+    /// Record literal: {name: expr, ...}; `{a, b}` is shorthand for `{a: a, b: b}`
+    Record(Vec<(String, Expr)>),
     /// Field access on expression result: expr.field.subfield
     FieldAccess(Box<Expr>, Vec<String>),
+    /// Indexing and slicing: x[i], x[a:b], m[i, j], m[:, j]
+    Index(Box<Expr>, Vec<IndexArg>),
+}
+
+/// One comma-separated position inside `[...]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum IndexArg {
+    /// Single index (negative counts from the end), index list, or Bool mask
+    At(Expr),
+    /// Half-open range `a:b`; a missing bound means the start or end
+    Slice(Option<Expr>, Option<Expr>),
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum BinOp {
     Add, Sub, Mul, Div, Pow,
+    // Element-wise operators (.* and ./), same as Mul/Div for scalars
+    ElemMul, ElemDiv,
     // Comparison operators
     Lt, Gt, Le, Ge, Eq, Ne,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum UnaryOp { Neg }
+pub enum UnaryOp {
+    Neg,
+    /// Postfix `'` or `^T`
+    Transpose,
+}
@@ -0,0 +1,270 @@
+//! Compare two evaluations
+//!
+//! Lists the cells whose results moved between two [`EvalResult`]s. A changed
+//! cell is blamed on its own formula when its source was edited; otherwise the
+//! change is traced back through the dependency graph to the edited cells and
+//! external inputs it came from.
+
+use crate::eval::{CellSource, EvalResult};
+use crate::render::{table_line, CellFormat, Renderer};
+use folio_core::{Number, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Cells added, removed or changed between two evaluations
+#[derive(Debug, Clone, Default)]
+pub struct EvalDiff {
+    /// New document order, then removed cells in old document order
+    pub changes: Vec<CellChange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CellChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+    /// `new - old` when both are numbers
+    pub delta: Option<Number>,
+    /// `delta / |old|` when both are numbers and `old` is not zero
+    pub relative: Option<Number>,
+    pub cause: Cause,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cause {
+    /// The cell itself was added, removed or edited
+    Formula,
+    /// Recomputed; names the edited cells and changed inputs it depends on.
+    /// Empty when nothing upstream changed (e.g. `now()` or `random()`)
+    Upstream(Vec<String>),
+}
+
+impl EvalDiff {
+    pub fn between(old: &EvalResult, new: &EvalResult) -> Self {
+        Tracer::new(old, new).diff()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CellChange> {
+        self.changes.iter().find(|c| c.name == name)
+    }
+
+    /// `| cell | change | old | new | delta | relative | cause |` table
+    pub fn to_markdown(&self) -> String {
+        if self.changes.is_empty() {
+            return "No changes.\n".to_string();
+        }
+        let show = |v: &Option<Value>| v.as_ref().map(display).unwrap_or_default();
+
+        let mut out = String::from("| cell | change | old | new | delta | relative | cause |\n");
+        out.push_str("|------|--------|----:|----:|------:|---------:|-------|\n");
+        for change in &self.changes {
+            let kind = match change.kind {
+                ChangeKind::Added => "added",
+                ChangeKind::Removed => "removed",
+                ChangeKind::Changed => "changed",
+            };
+            let delta = change.delta.as_ref()
+                .map(|d| signed(&trim_zeros(d.as_decimal(10)), d))
+                .unwrap_or_default();
+            let relative = change.relative.as_ref()
+                .map(|r| {
+                    let percent = r.mul(&Number::from_i64(100));
+                    signed(&format!("{}%", percent.as_decimal(2)), r)
+                })
+                .unwrap_or_default();
+            let cause = match &change.cause {
+                Cause::Formula => "formula".to_string(),
+                Cause::Upstream(names) if names.is_empty() => "recomputed".to_string(),
+                Cause::Upstream(names) => format!("upstream: {}", names.join(", ")),
+            };
+            out.push_str(&table_line(&[
+                change.name.clone(), kind.to_string(), show(&change.old), show(&change.new), delta, relative, cause,
+            ]));
+        }
+        out
+    }
+}
+
+/// Numbers without trailing zeros; anything else as the default renderer shows it
+fn display(value: &Value) -> String {
+    match value {
+        Value::Number(n) => trim_zeros(n.as_decimal(10)),
+        other => Renderer::new().render_value(other, &CellFormat::default()),
+    }
+}
+
+fn trim_zeros(text: String) -> String {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+fn signed(text: &str, n: &Number) -> String {
+    if n.is_negative() || n.is_zero() {
+        text.to_string()
+    } else {
+        format!("+{}", text)
+    }
+}
+
+/// Walks the new document's dependency graph back to root changes
+struct Tracer<'a> {
+    old: &'a EvalResult,
+    new: &'a EvalResult,
+    old_cells: HashMap<&'a str, &'a CellSource>,
+    new_cells: HashMap<&'a str, &'a CellSource>,
+    /// Cells whose value, presence or source differs
+    moved: HashSet<&'a str>,
+    /// External variables that differ
+    inputs: HashSet<&'a str>,
+}
+
+impl<'a> Tracer<'a> {
+    fn new(old: &'a EvalResult, new: &'a EvalResult) -> Self {
+        let old_cells: HashMap<&str, &CellSource> = old.cells.iter().map(|c| (c.name.as_str(), c)).collect();
+        let new_cells: HashMap<&str, &CellSource> = new.cells.iter().map(|c| (c.name.as_str(), c)).collect();
+
+        let mut moved = HashSet::new();
+        for name in old_cells.keys().chain(new_cells.keys()) {
+            match (old.values.get(*name), new.values.get(*name)) {
+                (Some(a), Some(b)) if same_value(a, b) => {}
+                (None, None) => {}
+                _ => {
+                    moved.insert(*name);
+                }
+            }
+        }
+
+        let inputs = old.inputs.keys().chain(new.inputs.keys())
+            .filter(|name| match (old.inputs.get(*name), new.inputs.get(*name)) {
+                (Some(a), Some(b)) => !same_value(a, b),
+                _ => true,
+            })
+            .map(|name| name.as_str())
+            .collect();
+
+        Self { old, new, old_cells, new_cells, moved, inputs }
+    }
+
+    fn diff(&self) -> EvalDiff {
+        let mut changes = Vec::new();
+        for cell in &self.new.cells {
+            let name = cell.name.as_str();
+            if !self.moved.contains(name) {
+                continue;
+            }
+            let old_value = self.old_cells.get(name).and_then(|_| self.old.values.get(name)).cloned();
+            let new_value = self.new.values.get(name).cloned();
+            let kind = if self.old_cells.contains_key(name) { ChangeKind::Changed } else { ChangeKind::Added };
+            let cause = if self.edited(name) {
+                Cause::Formula
+            } else {
+                Cause::Upstream(self.roots(name))
+            };
+            changes.push(change(name, kind, old_value, new_value, cause));
+        }
+        for cell in &self.old.cells {
+            let name = cell.name.as_str();
+            if !self.new_cells.contains_key(name) {
+                changes.push(change(name, ChangeKind::Removed, self.old.values.get(name).cloned(), None, Cause::Formula));
+            }
+        }
+        EvalDiff { changes }
+    }
+
+    /// Added, removed, or source edited
+    fn edited(&self, name: &str) -> bool {
+        match (self.old_cells.get(name), self.new_cells.get(name)) {
+            (Some(a), Some(b)) => a.source != b.source,
+            _ => true,
+        }
+    }
+
+    /// Edited cells and changed inputs that `name` was recomputed from
+    fn roots(&self, name: &str) -> Vec<String> {
+        let mut roots = Vec::new();
+        let mut visited = HashSet::new();
+        self.collect_roots(name, &mut visited, &mut roots);
+        roots.sort();
+        roots.dedup();
+        roots
+    }
+
+    fn collect_roots(&self, name: &str, visited: &mut HashSet<String>, roots: &mut Vec<String>) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        let references = self.new_cells.get(name).map(|c| c.references.as_slice()).unwrap_or_default();
+        for reference in references {
+            let is_cell = self.new_cells.contains_key(reference.as_str()) || self.old_cells.contains_key(reference.as_str());
+            if is_cell {
+                if !self.moved.contains(reference.as_str()) {
+                    continue;
+                }
+                let before = roots.len();
+                if !self.edited(reference) {
+                    self.collect_roots(reference, visited, roots);
+                }
+                // An edited cell, or a recomputed one with no cause of its own
+                if roots.len() == before {
+                    roots.push(reference.clone());
+                }
+            } else if self.inputs.contains(reference.as_str()) {
+                roots.push(reference.clone());
+            }
+        }
+    }
+}
+
+fn change(name: &str, kind: ChangeKind, old: Option<Value>, new: Option<Value>, cause: Cause) -> CellChange {
+    let (delta, relative) = match (&old, &new) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => {
+            let delta = b.sub(a);
+            let relative = if a.is_zero() { None } else { delta.checked_div(&a.abs()).ok() };
+            (Some(delta), relative)
+        }
+        _ => (None, None),
+    };
+    CellChange { name: name.to_string(), kind, old, new, delta, relative, cause }
+}
+
+/// Exact comparison for numbers, structural for lists, objects and extension values
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y,
+        (Value::List(x), Value::List(y)) => x.len() == y.len() && x.iter().zip(y).all(|(a, b)| same_value(a, b)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| same_value(v, w)))
+        }
+        (Value::Custom(x), Value::Custom(y)) => x.type_name() == y.type_name() && x.to_json() == y.to_json(),
+        (Value::Error(x), Value::Error(y)) => x.code == y.code && x.message == y.message,
+        _ => std::mem::discriminant(a) == std::mem::discriminant(b) && a.to_string() == b.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_value() {
+        let n = |s: &str| Value::Number(Number::from_str(s).unwrap());
+        assert!(same_value(&n("0.1"), &n("0.10")));
+        assert!(!same_value(&n("0.1"), &n("0.1000000000001")));
+        assert!(same_value(&Value::List(vec![n("1"), Value::Text("a".into())]), &Value::List(vec![n("1"), Value::Text("a".into())])));
+        assert!(!same_value(&Value::Text("1".into()), &n("1")));
+    }
+}
@@ -0,0 +1,152 @@
+//! Editing parsed documents
+//!
+//! Lets a caller keep a [`Document`] around and change it cell by cell instead
+//! of rewriting the markdown. Edits go through the same cell parser as the
+//! source, and [`Document::to_source`] writes the result back in canonical form.
+
+use crate::ast::{Attributes, Block, Cell, Document, Row, Section};
+use crate::{formatter, parser};
+use folio_core::FolioError;
+
+const DEFAULT_COLUMNS: [&str; 3] = ["name", "formula", "result"];
+
+impl Document {
+    pub fn parse(source: &str) -> Result<Self, FolioError> {
+        parser::parse(source)
+    }
+
+    /// Canonical markdown, as [`format_document`](crate::format_document) writes it
+    pub fn to_source(&self) -> String {
+        formatter::format_ast(self)
+    }
+
+    /// First cell with this name
+    pub fn cell(&self, name: &str) -> Option<&Cell> {
+        self.sections.iter()
+            .flat_map(|s| &s.table.rows)
+            .flat_map(|r| &r.cells)
+            .find(|c| c.name == name)
+    }
+
+    /// Replace a cell's formula column, or append a row to the last table.
+    /// Returns whether the cell is new.
+    ///
+    /// `formula` is written as in the table: `=a * 2`, `100`,
+    /// `rate / 12 @format:percent`.
+    pub fn set_cell(&mut self, name: &str, formula: &str) -> Result<bool, FolioError> {
+        let name = name.trim();
+        let formula = formula.trim();
+        if name.is_empty() || name.contains(['|', '\n', '\r']) {
+            return Err(FolioError::parse_error(format!("Invalid cell name '{}'", name)));
+        }
+        if formula.contains(['|', '\n', '\r']) {
+            return Err(FolioError::parse_error("A formula can't contain '|' or line breaks"));
+        }
+        let cell = parser::parse_cell(name, formula)?;
+
+        for section in &mut self.sections {
+            for row in &mut section.table.rows {
+                if row.cells.first().is_some_and(|c| c.name == name) {
+                    match row.raw_cells.get_mut(1) {
+                        Some(slot) => *slot = formula.to_string(),
+                        None => row.raw_cells.push(formula.to_string()),
+                    }
+                    row.cells[0] = cell;
+                    return Ok(false);
+                }
+            }
+        }
+
+        let section = self.table_section();
+        let columns = section.body.iter()
+            .rev()
+            .find_map(|b| match b {
+                Block::Header { cells, .. } => Some(cells.len()),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_COLUMNS.len());
+        let mut raw_cells = vec![name.to_string(), formula.to_string()];
+        raw_cells.resize(columns.max(2), String::new());
+
+        // Right after the last table line, so the row joins that table
+        let at = section.body.iter()
+            .rposition(|b| !matches!(b, Block::Text(_)))
+            .map_or(section.body.len(), |i| i + 1);
+        section.body.insert(at, Block::Row(section.table.rows.len()));
+        section.table.rows.push(Row { cells: vec![cell], raw_cells });
+        Ok(true)
+    }
+
+    /// Last section with a table, giving one a table if none has
+    fn table_section(&mut self) -> &mut Section {
+        let index = match self.sections.iter().rposition(|s| s.body.iter().any(|b| matches!(b, Block::Header { .. }))) {
+            Some(index) => index,
+            None => {
+                if self.sections.is_empty() {
+                    self.sections.push(Section {
+                        name: "Default".to_string(),
+                        attributes: Attributes::new(),
+                        heading: None,
+                        table: Default::default(),
+                        body: Vec::new(),
+                    });
+                }
+                let section = self.sections.last_mut().unwrap();
+                let cells: Vec<String> = DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect();
+                section.table.columns = cells.clone();
+                section.body.push(Block::Header { cells, result_column: Some(2) });
+                section.body.push(Block::Separator(Vec::new()));
+                self.sections.len() - 1
+            }
+        };
+        &mut self.sections[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Folio;
+    use std::collections::HashMap;
+
+    const DOC: &str = "## Loan @precision:20\n\n| name | formula | result |\n|------|---------|--------|\n| principal | 1000 | |\n| interest | principal * 0.05 | |\n\nNotes.\n";
+
+    #[test]
+    fn test_set_existing_cell() {
+        let mut doc = Document::parse(DOC).unwrap();
+        assert!(!doc.set_cell("principal", "2000").unwrap());
+        assert!(doc.cell("principal").unwrap().formula.is_none());
+        assert_eq!(doc.cell("principal").unwrap().raw_text, "2000");
+
+        let values = Folio::default().eval_document(&doc, &HashMap::new()).values;
+        assert_eq!(values["interest"].as_number().unwrap().to_i64(), Some(100));
+        assert!(doc.to_source().contains("| principal | 2000"));
+    }
+
+    #[test]
+    fn test_add_cell() {
+        let mut doc = Document::parse(DOC).unwrap();
+        assert!(doc.set_cell("total", "=principal + interest @format:currency").unwrap());
+        assert_eq!(doc.cell("total").unwrap().attributes.get("format").map(String::as_str), Some("currency"));
+
+        // Appended to the table, before the prose, and stable through a round trip
+        let source = doc.to_source();
+        assert!(source.find("| total").unwrap() < source.find("Notes.").unwrap());
+        assert_eq!(Document::parse(&source).unwrap().to_source(), source);
+
+        let mut empty = Document::parse("").unwrap();
+        assert!(empty.set_cell("x", "6 * 7").unwrap());
+        let values = Folio::default().eval_document(&empty, &HashMap::new()).values;
+        assert_eq!(values["x"].as_number().unwrap().to_i64(), Some(42));
+    }
+
+    #[test]
+    fn test_set_cell_rejects_bad_input() {
+        let mut doc = Document::parse(DOC).unwrap();
+        assert!(doc.set_cell("a|b", "1").is_err());
+        assert!(doc.set_cell("x", "1 | 2").is_err());
+        assert!(doc.set_cell("x", "=").is_ok());
+        assert!(doc.set_cell("y", "={a: 1, a: 2}").is_err());
+        assert!(doc.cell("y").is_none());
+    }
+}
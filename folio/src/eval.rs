@@ -2,9 +2,11 @@
 //!
 //! Evaluates document expressions in dependency order.
 
-use crate::ast::{Document, Expr, BinOp, UnaryOp};
-use folio_plugin::EvalContext;
-use folio_core::{Value, FolioError, Number};
+use crate::ast::{Document, Expr, BinOp, UnaryOp, IndexArg};
+use crate::index::{index_value, Selector};
+use crate::render::{CellFormat, Renderer};
+use folio_plugin::{EvalContext, Typeset};
+use folio_core::{Value, FolioError, Number, ExtOp};
 use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Result of document evaluation
@@ -18,6 +20,20 @@ pub struct EvalResult {
     pub errors: Vec<FolioError>,
     /// Warnings (non-fatal)
     pub warnings: Vec<FolioError>,
+    /// Cells in document order, for comparing evaluations (see [`EvalDiff`](crate::EvalDiff))
+    pub cells: Vec<CellSource>,
+    /// External variables the document was evaluated with
+    pub inputs: HashMap<String, Value>,
+}
+
+/// A cell as written: canonical source and the names it references
+#[derive(Debug, Clone)]
+pub struct CellSource {
+    pub name: String,
+    /// Formula printed canonically, or the literal text
+    pub source: String,
+    /// Cells and external variables the formula refers to, sorted
+    pub references: Vec<String>,
 }
 
 impl EvalResult {
@@ -28,6 +44,8 @@ impl EvalResult {
             values: HashMap::new(),
             errors: vec![error],
             warnings: vec![],
+            cells: vec![],
+            inputs: HashMap::new(),
         }
     }
 }
@@ -41,12 +59,18 @@ impl Evaluator {
     }
     
     /// Evaluate document, return values by cell name
+    ///
+    /// Stops early when `ctx.cancel` is set and reports each cell to
+    /// `ctx.progress`.
     pub fn eval(&self, doc: &Document, ctx: &mut EvalContext) -> HashMap<String, Value> {
         let mut values = HashMap::new();
 
         // Collect all cells and their formulas
         let mut cells: HashMap<String, (Option<&Expr>, &str, u32)> = HashMap::new();
         let mut section_precisions: HashMap<String, u32> = HashMap::new();
+        // Display formats, only needed to show substituted values in the trace
+        let mut formats: HashMap<String, CellFormat> = HashMap::new();
+        let renderer = Renderer::new();
 
         for section in &doc.sections {
             let section_precision = section.attributes
@@ -61,24 +85,16 @@ impl Evaluator {
                         (cell.formula.as_ref(), &cell.raw_text, section_precision)
                     );
                     section_precisions.insert(cell.name.clone(), section_precision);
+                    if ctx.tracing {
+                        formats.insert(cell.name.clone(), renderer.cell_format(&section.attributes, &cell.attributes));
+                    }
                 }
             }
         }
 
         // Build dependency graph
-        let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
-        for (name, (formula, _, _)) in &cells {
-            let deps = if let Some(expr) = formula {
-                self.extract_dependencies(expr)
-            } else {
-                HashSet::new()
-            };
-            // Filter to only existing cells
-            let filtered_deps: Vec<String> = deps.into_iter()
-                .filter(|d| cells.contains_key(d))
-                .collect();
-            dependencies.insert(name.clone(), filtered_deps);
-        }
+        let dependencies = self.dependencies(doc);
+        let total = cells.len();
 
         // Detect cycles and compute topological order
         match self.topological_sort(&dependencies) {
@@ -86,6 +102,11 @@ impl Evaluator {
                 // Evaluate in topological order
                 for cell_name in order {
                     if let Some((formula, raw_text, precision)) = cells.get(&cell_name) {
+                        if ctx.is_cancelled() {
+                            values.insert(cell_name.clone(), Value::Error(FolioError::cancelled()));
+                            ctx.report_progress(&cell_name, values.len(), total);
+                            continue;
+                        }
                         ctx.precision = *precision;
 
                         // Check if this variable was already set externally
@@ -94,6 +115,7 @@ impl Evaluator {
                         if !existing.is_error() && formula.is_none() {
                             // External variable exists and cell is a literal - use external value
                             values.insert(cell_name.clone(), existing);
+                            ctx.report_progress(&cell_name, values.len(), total);
                             continue;
                         }
 
@@ -102,11 +124,15 @@ impl Evaluator {
                                 let deps = dependencies.get(&cell_name).cloned().unwrap_or_default();
                                 let result = self.eval_expr(expr, ctx);
                                 if ctx.tracing {
-                                    ctx.record_trace(
+                                    let format = formats.get(&cell_name).cloned().unwrap_or_default();
+                                    let lookup = |name: &str| deps.iter().any(|d| d == name).then(|| ctx.get_var(name));
+                                    let work = renderer.show_work(expr, &lookup, &result, &format);
+                                    ctx.record_trace_typeset(
                                         cell_name.clone(),
                                         raw_text.to_string(),
                                         result.clone(),
                                         deps,
+                                        Typeset { latex: expr.to_latex(), mathml: expr.to_mathml(), work },
                                     );
                                 }
                                 result
@@ -115,6 +141,7 @@ impl Evaluator {
                         };
                         ctx.set_var(cell_name.clone(), value.clone());
                         values.insert(cell_name.clone(), value);
+                        ctx.report_progress(&cell_name, values.len(), total);
                     }
                 }
             }
@@ -135,11 +162,17 @@ impl Evaluator {
                     for row in &section.table.rows {
                         for cell in &row.cells {
                             if !values.contains_key(&cell.name) {
+                                if ctx.is_cancelled() {
+                                    values.insert(cell.name.clone(), Value::Error(FolioError::cancelled()));
+                                    ctx.report_progress(&cell.name, values.len(), total);
+                                    continue;
+                                }
                                 // Check if this variable was already set externally
                                 let existing = ctx.get_var(&cell.name);
                                 if !existing.is_error() && cell.formula.is_none() {
                                     // External variable exists and cell is a literal - use external value
                                     values.insert(cell.name.clone(), existing);
+                                    ctx.report_progress(&cell.name, values.len(), total);
                                     continue;
                                 }
 
@@ -149,6 +182,7 @@ impl Evaluator {
                                 };
                                 ctx.set_var(cell.name.clone(), value.clone());
                                 values.insert(cell.name.clone(), value);
+                                ctx.report_progress(&cell.name, values.len(), total);
                             }
                         }
                     }
@@ -159,6 +193,54 @@ impl Evaluator {
         values
     }
 
+    /// Dependency graph: each cell mapped to the cells its formula reads, sorted by name
+    ///
+    /// References to names that are not cells (external variables, constants)
+    /// are left out.
+    pub fn dependencies(&self, doc: &Document) -> HashMap<String, Vec<String>> {
+        let cells: HashSet<&str> = doc.sections.iter()
+            .flat_map(|s| s.table.rows.iter())
+            .flat_map(|r| r.cells.iter())
+            .map(|c| c.name.as_str())
+            .collect();
+
+        let mut dependencies = HashMap::new();
+        for section in &doc.sections {
+            for row in &section.table.rows {
+                for cell in &row.cells {
+                    let mut deps: Vec<String> = match &cell.formula {
+                        Some(expr) => self.extract_dependencies(expr).into_iter()
+                            .filter(|d| cells.contains(d.as_str()))
+                            .collect(),
+                        None => Vec::new(),
+                    };
+                    deps.sort();
+                    dependencies.insert(cell.name.clone(), deps);
+                }
+            }
+        }
+        dependencies
+    }
+
+    /// Every cell with its canonical source, in document order
+    pub fn cell_sources(&self, doc: &Document) -> Vec<CellSource> {
+        doc.sections.iter()
+            .flat_map(|s| s.table.rows.iter())
+            .flat_map(|r| r.cells.iter())
+            .map(|cell| {
+                let (source, references) = match &cell.formula {
+                    Some(expr) => {
+                        let mut references: Vec<String> = self.extract_dependencies(expr).into_iter().collect();
+                        references.sort();
+                        (expr.to_string(), references)
+                    }
+                    None => (cell.raw_text.clone(), Vec::new()),
+                };
+                CellSource { name: cell.name.clone(), source, references }
+            })
+            .collect()
+    }
+
     /// Extract variable dependencies from an expression
     fn extract_dependencies(&self, expr: &Expr) -> HashSet<String> {
         let mut deps = HashSet::new();
@@ -197,6 +279,24 @@ impl Evaluator {
                 // Collect dependencies from the base expression
                 self.collect_deps(base_expr, deps);
             }
+            Expr::Record(fields) => {
+                for (_, value) in fields {
+                    self.collect_deps(value, deps);
+                }
+            }
+            Expr::Index(base_expr, args) => {
+                self.collect_deps(base_expr, deps);
+                for arg in args {
+                    match arg {
+                        IndexArg::At(e) => self.collect_deps(e, deps),
+                        IndexArg::Slice(start, end) => {
+                            for e in start.iter().chain(end.iter()) {
+                                self.collect_deps(e, deps);
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
 
@@ -278,7 +378,7 @@ impl Evaluator {
             Expr::BinaryOp(left, op, right) => {
                 let l = self.eval_expr(left, ctx);
                 let r = self.eval_expr(right, ctx);
-                self.eval_binary_op(l, *op, r, ctx.precision)
+                self.eval_binary_op(l, *op, r, ctx)
             }
 
             Expr::UnaryOp(op, inner) => {
@@ -299,6 +399,9 @@ impl Evaluator {
                     }
                 }
 
+                if ctx.is_cancelled() {
+                    return Value::Error(FolioError::cancelled());
+                }
                 ctx.registry.call_function(name, &evaluated_args, ctx)
             }
 
@@ -318,6 +421,17 @@ impl Evaluator {
                 Value::List(evaluated)
             }
 
+            Expr::Record(fields) => {
+                let mut map = HashMap::with_capacity(fields.len());
+                for (key, expr) in fields {
+                    match self.eval_expr(expr, ctx) {
+                        Value::Error(e) => return Value::Error(e.with_note(format!("in record field '{}'", key))),
+                        value => { map.insert(key.clone(), value); }
+                    }
+                }
+                Value::Object(map)
+            }
+
             Expr::FieldAccess(base_expr, fields) => {
                 let base_value = self.eval_expr(base_expr, ctx);
 
@@ -335,11 +449,21 @@ impl Evaluator {
                                 current = value.clone();
                             } else {
                                 return Value::Error(
-                                    FolioError::new("FIELD_NOT_FOUND", format!("Field '{}' not found in object", field))
+                                    FolioError::new(folio_core::codes::UNDEFINED_FIELD, format!("Field '{}' not found in object", field))
                                         .with_suggestion(&format!("Available fields: {}", map.keys().cloned().collect::<Vec<_>>().join(", ")))
                                 );
                             }
                         }
+                        Value::Custom(ext) => {
+                            if let Some(value) = ext.field(field) {
+                                current = value;
+                            } else {
+                                return Value::Error(
+                                    FolioError::new(folio_core::codes::UNDEFINED_FIELD, format!("Field '{}' not found in {}", field, ext.type_name()))
+                                        .with_suggestion(format!("Available fields: {}", ext.field_names().join(", ")))
+                                );
+                            }
+                        }
                         _ => {
                             return Value::Error(
                                 FolioError::new("NOT_OBJECT", format!("Cannot access field '{}' on non-object value", field))
@@ -350,6 +474,27 @@ impl Evaluator {
                 }
                 current
             }
+
+            Expr::Index(base_expr, args) => {
+                let base_value = self.eval_expr(base_expr, ctx);
+                if let Value::Error(e) = base_value {
+                    return Value::Error(e.with_note("in indexed value"));
+                }
+
+                let mut selectors = Vec::with_capacity(args.len());
+                for arg in args {
+                    let selector = match arg {
+                        IndexArg::At(e) => Selector::At(self.eval_expr(e, ctx)),
+                        IndexArg::Slice(start, end) => Selector::Slice(
+                            start.as_ref().map(|e| self.eval_expr(e, ctx)),
+                            end.as_ref().map(|e| self.eval_expr(e, ctx)),
+                        ),
+                    };
+                    selectors.push(selector);
+                }
+
+                index_value(&base_value, &selectors)
+            }
         }
     }
     
@@ -370,7 +515,8 @@ impl Evaluator {
         }
     }
     
-    fn eval_binary_op(&self, left: Value, op: BinOp, right: Value, precision: u32) -> Value {
+    fn eval_binary_op(&self, left: Value, op: BinOp, right: Value, ctx: &EvalContext) -> Value {
+        let precision = ctx.precision;
         // Propagate errors
         if let Value::Error(e) = &left {
             return Value::Error(e.clone().with_note("from left operand"));
@@ -379,6 +525,23 @@ impl Evaluator {
             return Value::Error(e.clone().with_note("from right operand"));
         }
 
+        // Plugin-defined values (matrices among them): left operand first, then right
+        if left.is_custom() || right.is_custom() {
+            return self.eval_ext_binary_op(&left, op, &right);
+        }
+
+        // Lists broadcast element-wise: [1, 2] * 3, prices * quantities
+        if matches!(left, Value::List(_)) || matches!(right, Value::List(_)) {
+            return self.eval_list_binary_op(left, op, right, ctx);
+        }
+
+        // Element-wise operators on scalars are plain * and /
+        let op = match op {
+            BinOp::ElemMul => BinOp::Mul,
+            BinOp::ElemDiv => BinOp::Div,
+            other => other,
+        };
+
         // Handle DateTime/Duration arithmetic
         match (&left, &right, op) {
             // DateTime + Duration -> DateTime
@@ -506,8 +669,8 @@ impl Evaluator {
         match op {
             BinOp::Add => Value::Number(l.add(r)),
             BinOp::Sub => Value::Number(l.sub(r)),
-            BinOp::Mul => Value::Number(l.mul(r)),
-            BinOp::Div => {
+            BinOp::Mul | BinOp::ElemMul => Value::Number(l.mul(r)),
+            BinOp::Div | BinOp::ElemDiv => {
                 match l.checked_div(r) {
                     Ok(n) => Value::Number(n),
                     Err(e) => Value::Error(e.into()),
@@ -554,13 +717,85 @@ impl Evaluator {
         }
     }
     
+    /// Broadcast a binary operator over lists (NumPy-style)
+    ///
+    /// A scalar pairs with every element, equal-length lists pair up by
+    /// position, and a single-element list stretches to the other length.
+    /// Nested lists recurse, so comparisons yield Bool lists usable as masks.
+    fn eval_list_binary_op(&self, left: Value, op: BinOp, right: Value, ctx: &EvalContext) -> Value {
+        let pairs: Vec<(Value, Value)> = match (left, right) {
+            (Value::List(l), Value::List(r)) => {
+                if l.len() == r.len() {
+                    l.into_iter().zip(r).collect()
+                } else if l.len() == 1 {
+                    r.into_iter().map(|b| (l[0].clone(), b)).collect()
+                } else if r.len() == 1 {
+                    l.into_iter().map(|a| (a, r[0].clone())).collect()
+                } else {
+                    return Value::Error(FolioError::shape_mismatch(format!(
+                        "lists of length {} and {} cannot be combined with {}",
+                        l.len(), r.len(), ext_op(op).symbol()
+                    )).with_note("Lists must have equal length, or one of them a single element"));
+                }
+            }
+            (Value::List(l), scalar) => l.into_iter().map(|a| (a, scalar.clone())).collect(),
+            (scalar, Value::List(r)) => r.into_iter().map(|b| (scalar.clone(), b)).collect(),
+            _ => unreachable!("eval_list_binary_op requires a List operand"),
+        };
+
+        let mut results = Vec::with_capacity(pairs.len());
+        for (i, (a, b)) in pairs.into_iter().enumerate() {
+            match self.eval_binary_op(a, op, b, ctx) {
+                Value::Error(e) => return Value::Error(e.with_note(format!("at list index {}", i))),
+                v => results.push(v),
+            }
+        }
+        Value::List(results)
+    }
+
+    /// Dispatch a binary operator to extension value hooks
+    fn eval_ext_binary_op(&self, left: &Value, op: BinOp, right: &Value) -> Value {
+        let ext_op = ext_op(op);
+
+        if let Value::Custom(ext) = left {
+            if let Some(result) = ext.binary_op(ext_op, right) {
+                return result;
+            }
+        }
+        if let Value::Custom(ext) = right {
+            if let Some(result) = ext.binary_op_rhs(ext_op, left) {
+                return result;
+            }
+        }
+
+        Value::Error(FolioError::type_error(
+            "operands supporting this operator",
+            &format!("{} {} {}", left.type_name(), ext_op.symbol(), right.type_name()),
+        ))
+    }
+
     fn eval_unary_op(&self, op: UnaryOp, value: Value) -> Value {
         if let Value::Error(e) = &value {
             return Value::Error(e.clone());
         }
 
         match op {
+            UnaryOp::Transpose => match &value {
+                Value::Custom(ext) => ext.transpose().unwrap_or_else(|| Value::Error(
+                    FolioError::type_error("Matrix", ext.type_name())
+                        .with_note(format!("{} does not support transpose", ext.type_name()))
+                )),
+                other => Value::Error(FolioError::type_error("Matrix", other.type_name())
+                    .with_note("' and ^T transpose matrices")),
+            },
             UnaryOp::Neg => {
+                // Handle plugin-defined values
+                if let Value::Custom(ext) = &value {
+                    return ext.neg().unwrap_or_else(|| Value::Error(
+                        FolioError::type_error("Number or Duration", ext.type_name())
+                            .with_note(format!("{} does not support negation", ext.type_name()))
+                    ));
+                }
                 // Handle Duration negation
                 if let Some(d) = value.as_duration() {
                     return Value::Duration(d.neg());
@@ -588,3 +823,22 @@ impl Default for Evaluator {
         Self::new()
     }
 }
+
+/// Map an AST operator to the operator enum shared with plugins
+fn ext_op(op: BinOp) -> ExtOp {
+    match op {
+        BinOp::Add => ExtOp::Add,
+        BinOp::Sub => ExtOp::Sub,
+        BinOp::Mul => ExtOp::Mul,
+        BinOp::Div => ExtOp::Div,
+        BinOp::Pow => ExtOp::Pow,
+        BinOp::ElemMul => ExtOp::ElemMul,
+        BinOp::ElemDiv => ExtOp::ElemDiv,
+        BinOp::Lt => ExtOp::Lt,
+        BinOp::Gt => ExtOp::Gt,
+        BinOp::Le => ExtOp::Le,
+        BinOp::Ge => ExtOp::Ge,
+        BinOp::Eq => ExtOp::Eq,
+        BinOp::Ne => ExtOp::Ne,
+    }
+}
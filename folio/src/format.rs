@@ -0,0 +1,360 @@
+//! Number format patterns and locales
+//!
+//! Supports the common subset of Excel-style patterns:
+//! `#,##0.00`, `0.0%`, `$#,##0`, `#,##0.00 "€"`, `#,##0.00;(#,##0.00)`, `#,##0,`
+//! plus named formats (`percent`, `integer`, `accounting`, `currency`).
+//! Digits come from `Number::as_decimal`; the pattern only decides how many
+//! places to ask for and how to decorate the result.
+
+use folio_core::Number;
+
+/// Decimal and grouping separators plus currency convention for a locale
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberLocale {
+    pub decimal: char,
+    pub group: char,
+    pub currency: &'static str,
+    /// Currency symbol goes after the number ("1.234,56 €")
+    pub currency_after: bool,
+}
+
+impl Default for NumberLocale {
+    fn default() -> Self {
+        Self { decimal: '.', group: ',', currency: "$", currency_after: false }
+    }
+}
+
+impl NumberLocale {
+    /// Look up a locale tag such as `de-DE` or `fr`. Unknown tags return None.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().trim_matches('"').replace('_', "-");
+        let lower = tag.to_ascii_lowercase();
+        let lang = lower.split('-').next().unwrap_or("");
+        let locale = |decimal, group, currency, currency_after| Some(Self { decimal, group, currency, currency_after });
+
+        match lower.as_str() {
+            "en-gb" => return locale('.', ',', "£", false),
+            "en-in" | "hi-in" => return locale('.', ',', "₹", false),
+            "de-ch" | "fr-ch" | "it-ch" => return locale('.', '\'', "CHF", false),
+            "pt-br" => return locale(',', '.', "R$", false),
+            _ => {}
+        }
+        match lang {
+            "en" => locale('.', ',', "$", false),
+            "ja" => locale('.', ',', "¥", false),
+            "zh" => locale('.', ',', "¥", false),
+            "de" | "it" | "es" | "nl" | "pt" => locale(',', '.', "€", true),
+            "fr" => locale(',', '\u{202F}', "€", true),
+            "fi" | "sk" => locale(',', '\u{A0}', "€", true),
+            "sv" => locale(',', '\u{A0}', "kr", true),
+            "nb" | "no" | "da" => locale(',', '.', "kr", true),
+            "pl" => locale(',', '\u{A0}', "zł", true),
+            "cs" => locale(',', '\u{A0}', "Kč", true),
+            "ru" => locale(',', '\u{A0}', "₽", true),
+            _ => None,
+        }
+    }
+
+    /// Replace the '.' of a plain decimal string with the locale separator
+    pub fn localize(&self, plain: &str) -> String {
+        if self.decimal == '.' {
+            plain.to_string()
+        } else {
+            plain.replace('.', &self.decimal.to_string())
+        }
+    }
+}
+
+/// Parsed format pattern with up to three sections: positive;negative;zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberPattern {
+    positive: Section,
+    negative: Option<Section>,
+    zero: Option<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    prefix: String,
+    suffix: String,
+    min_int: usize,
+    min_frac: usize,
+    max_frac: usize,
+    grouping: bool,
+    /// Divide by 1000 per trailing comma (`#,##0,` shows thousands)
+    scale_thousands: u32,
+    percent: bool,
+    /// Placeholder for the locale currency symbol
+    currency: bool,
+    /// False for text-only sections such as `"n/a"`
+    has_digits: bool,
+}
+
+impl NumberPattern {
+    /// Parse a pattern or named format. Returns None if there are no digit placeholders.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        let spec = spec.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(spec);
+        let spec = match spec.to_ascii_lowercase().as_str() {
+            "percent" => "0.00%",
+            "integer" => "#,##0",
+            "number" => "#,##0.00",
+            "accounting" => "#,##0.00;(#,##0.00)",
+            "currency" => "¤#,##0.00",
+            _ => spec,
+        };
+
+        let mut sections = split_sections(spec).into_iter().map(|s| parse_section(&s));
+        let positive = sections.next().filter(|s| s.has_digits)?;
+        let negative = sections.next();
+        let zero = sections.next();
+        Some(Self { positive, negative, zero })
+    }
+
+    /// Format a number with this pattern in the given locale
+    pub fn format(&self, n: &Number, locale: &NumberLocale) -> String {
+        if n.is_zero() {
+            if let Some(zero) = &self.zero {
+                return zero.render(n, locale);
+            }
+        }
+        if n.is_negative() {
+            let abs = n.abs();
+            return match &self.negative {
+                Some(negative) => negative.render(&abs, locale),
+                None => {
+                    let body = self.positive.render(&abs, locale);
+                    // Don't show "-0.00" when rounding reaches zero
+                    if body.chars().any(|c| c.is_ascii_digit() && c != '0') {
+                        format!("-{}", body)
+                    } else {
+                        body
+                    }
+                }
+            };
+        }
+        self.positive.render(n, locale)
+    }
+}
+
+/// Split on ';' outside quotes
+fn split_sections(spec: &str) -> Vec<String> {
+    let mut sections = vec![String::new()];
+    let mut in_quote = false;
+    let mut escaped = false;
+    for c in spec.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quote = !in_quote;
+        } else if c == ';' && !in_quote {
+            sections.push(String::new());
+            continue;
+        }
+        sections.last_mut().expect("at least one section").push(c);
+    }
+    sections
+}
+
+fn parse_section(spec: &str) -> Section {
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    let mut digits = String::new();
+    let mut percent = false;
+    let mut currency = false;
+
+    let mut chars = spec.chars().peekable();
+    let mut seen_digits = false;
+    while let Some(c) = chars.next() {
+        let in_digits = matches!(c, '0' | '#')
+            || (seen_digits && suffix.is_empty() && matches!(c, ',' | '.') )
+            || (!seen_digits && c == '.' && matches!(chars.peek(), Some('0' | '#')));
+        if in_digits {
+            seen_digits = true;
+            digits.push(c);
+            continue;
+        }
+        let literal = match c {
+            '"' => {
+                let mut quoted = String::new();
+                for q in chars.by_ref() {
+                    if q == '"' {
+                        break;
+                    }
+                    quoted.push(q);
+                }
+                quoted
+            }
+            '\\' => chars.next().map(String::from).unwrap_or_default(),
+            '_' => {
+                chars.next();
+                " ".to_string()
+            }
+            '%' => {
+                percent = true;
+                "%".to_string()
+            }
+            '¤' => {
+                currency = true;
+                "¤".to_string()
+            }
+            other => other.to_string(),
+        };
+        if seen_digits {
+            suffix.push_str(&literal);
+        } else {
+            prefix.push_str(&literal);
+        }
+    }
+
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((i, f)) => (i.to_string(), f.replace(',', "")),
+        None => (digits.clone(), String::new()),
+    };
+    let trimmed = int_part.trim_end_matches(',');
+    let scale_thousands = (int_part.len() - trimmed.len()) as u32;
+
+    Section {
+        prefix,
+        suffix,
+        min_int: trimmed.chars().filter(|&c| c == '0').count(),
+        min_frac: frac_part.chars().filter(|&c| c == '0').count(),
+        max_frac: frac_part.chars().filter(|&c| c == '0' || c == '#').count(),
+        grouping: trimmed.contains(','),
+        scale_thousands,
+        percent,
+        currency,
+        has_digits: seen_digits,
+    }
+}
+
+impl Section {
+    fn render(&self, n: &Number, locale: &NumberLocale) -> String {
+        if !self.has_digits {
+            return self.prefix.replace('¤', locale.currency);
+        }
+        let mut value = n.clone();
+        if self.percent {
+            value = value.mul(&Number::from_i64(100));
+        }
+        for _ in 0..self.scale_thousands {
+            value = value.checked_div(&Number::from_i64(1000)).unwrap_or(value);
+        }
+
+        let plain = fixed(&value, self.max_frac as u32);
+        let (int_digits, frac_digits) = match plain.split_once('.') {
+            Some((i, f)) => (i.to_string(), f.to_string()),
+            None => (plain, String::new()),
+        };
+
+        // Drop optional (#) fraction digits that are zero
+        let mut frac = frac_digits;
+        while frac.len() > self.min_frac && frac.ends_with('0') {
+            frac.pop();
+        }
+
+        let mut int = int_digits.trim_start_matches('0').to_string();
+        while int.len() < self.min_int {
+            int.insert(0, '0');
+        }
+        if self.grouping {
+            int = group_digits(&int, locale.group);
+        }
+
+        let mut body = int;
+        if !frac.is_empty() {
+            body.push(locale.decimal);
+            body.push_str(&frac);
+        }
+
+        let decorate = |text: &str| {
+            if !self.currency {
+                return text.to_string();
+            }
+            // A leading ¤ follows the locale convention; elsewhere it stays put
+            text.replace('¤', locale.currency)
+        };
+        if self.currency && self.prefix.trim() == "¤" && self.suffix.is_empty() && locale.currency_after {
+            return format!("{}\u{A0}{}", body, locale.currency);
+        }
+        format!("{}{}{}", decorate(&self.prefix), body, decorate(&self.suffix))
+    }
+}
+
+/// Fixed-point digits without sign, via `Number::to_fixed`
+fn fixed(n: &Number, places: u32) -> String {
+    n.abs().to_fixed(places)
+}
+
+fn group_digits(int: &str, sep: char) -> String {
+    let len = int.chars().count();
+    let mut out = String::with_capacity(len + len / 3);
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (len - i).is_multiple_of(3) {
+            out.push(sep);
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(pattern: &str, value: &str, locale: &str) -> String {
+        let pattern = NumberPattern::parse(pattern).expect("valid pattern");
+        let locale = NumberLocale::from_tag(locale).expect("known locale");
+        pattern.format(&Number::from_str(value).unwrap(), &locale)
+    }
+
+    #[test]
+    fn test_grouping_and_decimals() {
+        assert_eq!(fmt("#,##0.00", "1234567.891", "en-US"), "1,234,567.89");
+        assert_eq!(fmt("#,##0.00", "1234567.891", "de-DE"), "1.234.567,89");
+        assert_eq!(fmt("0.0", "0.26", "en-US"), "0.3");
+        assert_eq!(fmt("#.##", "0.5", "en-US"), ".5");
+        assert_eq!(fmt("000", "7", "en-US"), "007");
+        assert_eq!(fmt("#,##0,", "1234567", "en-US"), "1,235");
+    }
+
+    #[test]
+    fn test_rounding_keeps_every_digit() {
+        // 23 significant digits, past what an f64 holds
+        assert_eq!(fmt("#,##0.00", "12345678901234567890.125", "en-US"), "12,345,678,901,234,567,890.13");
+        assert_eq!(fmt("0.000000000000000000", "0.1234567890123456789", "en-US"), "0.123456789012345679");
+        assert_eq!(fmt("0.00", "0.0000001", "en-US"), "0.00");
+        assert_eq!(fmt("0", "2.5", "en-US"), "3");
+    }
+
+    #[test]
+    fn test_percent_and_named() {
+        assert_eq!(fmt("percent", "0.1234", "en-US"), "12.34%");
+        assert_eq!(fmt("0%", "0.5", "en-US"), "50%");
+        assert_eq!(fmt("integer", "9876.6", "en-US"), "9,877");
+    }
+
+    #[test]
+    fn test_negative_sections() {
+        assert_eq!(fmt("accounting", "-1234.5", "en-US"), "(1,234.50)");
+        assert_eq!(fmt("#,##0.00", "-1234.5", "en-US"), "-1,234.50");
+        assert_eq!(fmt("0.00", "-0.001", "en-US"), "0.00");
+        assert_eq!(fmt("0.00;-0.00;\"nil\"", "0", "en-US"), "nil");
+    }
+
+    #[test]
+    fn test_currency() {
+        assert_eq!(fmt("$#,##0.00", "-5", "en-US"), "-$5.00");
+        assert_eq!(fmt("currency", "1234.5", "en-US"), "$1,234.50");
+        assert_eq!(fmt("currency", "1234.5", "de-DE"), "1.234,50\u{A0}€");
+        assert_eq!(fmt("#,##0.00 \"EUR\"", "1234.5", "fr-FR"), "1\u{202F}234,50 EUR");
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert!(NumberPattern::parse("abc").is_none());
+        assert!(NumberLocale::from_tag("xx-YY").is_none());
+    }
+}
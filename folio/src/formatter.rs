@@ -0,0 +1,396 @@
+//! Canonical `.fmd` formatter
+//!
+//! Parses a document and writes it back in canonical form: section headings
+//! are `##`, headings and cell annotations list their attributes sorted by key,
+//! formulas are printed from the AST with single spaces around operators and
+//! only the parentheses the parser needs, and carry a leading `=` only when they
+//! would otherwise read as a literal. Table columns are padded to a common
+//! width. Prose is kept as written. Formatting a formatted document returns it
+//! unchanged, and documents that differ only in these respects come out equal.
+
+use crate::ast::{Attributes, BinOp, Block, Cell, Document, Expr, IndexArg, Row, UnaryOp};
+use crate::parser;
+use folio_core::FolioError;
+use std::fmt;
+
+/// Format a document into canonical markdown
+pub fn format_document(source: &str) -> Result<String, FolioError> {
+    let doc = parser::parse(source)?;
+    Ok(format_ast(&doc))
+}
+
+pub(crate) fn format_ast(doc: &Document) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    if let Some(front_matter) = &doc.front_matter {
+        lines.push("---".to_string());
+        lines.extend(front_matter.lines().map(|l| l.trim_end().to_string()));
+        lines.push("---".to_string());
+    }
+    lines.extend(doc.preamble.iter().map(|l| l.trim_end().to_string()));
+
+    for section in &doc.sections {
+        if section.heading.is_some() {
+            let mut line = format!("## {}", section.name);
+            line.push_str(&attributes(&section.attributes));
+            lines.push(line);
+        }
+
+        let mut table: Vec<TableLine> = Vec::new();
+        for block in &section.body {
+            match block {
+                Block::Text(text) => {
+                    flush_table(&mut lines, &mut table);
+                    lines.push(text.trim_end().to_string());
+                }
+                Block::Header { cells, .. } => {
+                    flush_table(&mut lines, &mut table);
+                    table.push(TableLine::Cells(cells.clone()));
+                }
+                Block::Separator(markers) => table.push(TableLine::Separator(markers.clone())),
+                Block::Row(index) => table.push(TableLine::Cells(row_cells(&section.table.rows[*index]))),
+            }
+        }
+        flush_table(&mut lines, &mut table);
+    }
+
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    let start = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+    let mut out = lines[start..].join("\n");
+    out.push('\n');
+    out
+}
+
+enum TableLine {
+    Cells(Vec<String>),
+    Separator(Vec<String>),
+}
+
+/// Source cells of a data row with the formula cell in canonical form
+fn row_cells(row: &Row) -> Vec<String> {
+    let mut cells = row.raw_cells.clone();
+    if let (Some(cell), Some(slot)) = (row.cells.first(), cells.get_mut(1)) {
+        *slot = formula_cell(cell, slot);
+    }
+    cells
+}
+
+fn formula_cell(cell: &Cell, raw: &str) -> String {
+    let mut text = match &cell.formula {
+        // `=` only where the formula would otherwise read as a literal
+        Some(expr) => {
+            let source = expr.to_string();
+            if !parser::looks_like_expression(&source) {
+                format!("={}", source)
+            } else {
+                source
+            }
+        }
+        None if raw.starts_with('=') => format!("={}", cell.raw_text),
+        None => cell.raw_text.clone(),
+    };
+    text.push_str(&attributes(&cell.attributes));
+    text.trim().to_string()
+}
+
+/// ` @key:value` pairs sorted by key
+fn attributes(attrs: &Attributes) -> String {
+    let mut pairs: Vec<(&String, &String)> = attrs.iter().collect();
+    pairs.sort();
+    pairs.iter().map(|(k, v)| format!(" @{}:{}", k, v)).collect()
+}
+
+/// Write a table with every column padded to its widest cell
+fn flush_table(lines: &mut Vec<String>, table: &mut Vec<TableLine>) {
+    if table.is_empty() {
+        return;
+    }
+    let columns = table.iter()
+        .map(|line| match line {
+            TableLine::Cells(cells) | TableLine::Separator(cells) => cells.len(),
+        })
+        .max()
+        .unwrap_or(0);
+    let mut widths = vec![3; columns];
+    for line in table.iter() {
+        if let TableLine::Cells(cells) = line {
+            for (i, cell) in cells.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+    }
+
+    // A header needs a separator row to be a table
+    if matches!(table.first(), Some(TableLine::Cells(_))) && !matches!(table.get(1), Some(TableLine::Separator(_))) {
+        table.insert(1, TableLine::Separator(Vec::new()));
+    }
+
+    for line in table.drain(..) {
+        let cells: Vec<String> = match line {
+            TableLine::Cells(cells) => (0..columns)
+                .map(|i| {
+                    let cell = cells.get(i).map(String::as_str).unwrap_or("");
+                    format!("{}{}", cell, " ".repeat(widths[i] - cell.chars().count()))
+                })
+                .collect(),
+            TableLine::Separator(markers) => (0..columns)
+                .map(|i| {
+                    let marker = markers.get(i).map(String::as_str).unwrap_or("");
+                    let left = marker.starts_with(':');
+                    let right = marker.len() > 1 && marker.ends_with(':');
+                    let dashes = widths[i] - left as usize - right as usize;
+                    format!("{}{}{}", if left { ":" } else { "" }, "-".repeat(dashes), if right { ":" } else { "" })
+                })
+                .collect(),
+        };
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+}
+
+// ========== Expressions ==========
+
+/// Canonical source text; parsing it gives back the same expression
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&source(self, true))
+    }
+}
+
+/// Binding strength as the parser sees it, loosest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Comparison,
+    Additive,
+    Multiplicative,
+    Power,
+    Atom,
+}
+
+fn op_level(op: BinOp) -> Level {
+    match op {
+        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge | BinOp::Eq | BinOp::Ne => Level::Comparison,
+        BinOp::Add | BinOp::Sub => Level::Additive,
+        BinOp::Mul | BinOp::Div | BinOp::ElemMul | BinOp::ElemDiv => Level::Multiplicative,
+        BinOp::Pow => Level::Power,
+    }
+}
+
+fn level(expr: &Expr) -> Level {
+    match expr {
+        Expr::BinaryOp(_, op, _) => op_level(*op),
+        Expr::UnaryOp(UnaryOp::Transpose, _) => Level::Power,
+        Expr::UnaryOp(UnaryOp::Neg, _) => Level::Additive,
+        _ => Level::Atom,
+    }
+}
+
+fn is_negative(expr: &Expr) -> bool {
+    matches!(expr, Expr::Number(n) if n.starts_with('-'))
+}
+
+/// `leading` is true when nothing precedes the expression at its nesting
+/// level; anywhere else a `-3` would be read as subtraction
+fn source(expr: &Expr, leading: bool) -> String {
+    match expr {
+        Expr::Number(n) if is_negative(expr) && !leading => format!("({})", n),
+        Expr::Number(n) => n.clone(),
+        Expr::StringLiteral(s) if s.contains('"') => format!("'{}'", s),
+        Expr::StringLiteral(s) => format!("\"{}\"", s),
+        Expr::Variable(parts) => parts.join("."),
+        Expr::BinaryOp(left, op, right) => {
+            let left = operand(left, *op, true, leading);
+            let right = operand(right, *op, false, false);
+            match op {
+                BinOp::Pow => format!("{}^{}", left, right),
+                _ => format!("{} {} {}", left, op_symbol(*op), right),
+            }
+        }
+        // Never produced by the parser, which reads `-x` as a name
+        Expr::UnaryOp(UnaryOp::Neg, inner) => format!("-{}", postfix_base(inner)),
+        Expr::UnaryOp(UnaryOp::Transpose, inner) => format!("{}'", postfix_base(inner)),
+        Expr::FunctionCall(name, args) => format!("{}({})", name, join(args)),
+        Expr::List(items) => format!("[{}]", join(items)),
+        Expr::Record(fields) => {
+            let fields: Vec<String> = fields.iter()
+                .map(|(key, value)| match value {
+                    // {a, b} shorthand
+                    Expr::Variable(parts) if parts.join(".") == *key => key.clone(),
+                    _ => format!("{}: {}", record_key(key), source(value, true)),
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Expr::FieldAccess(base, fields) => format!("{}.{}", postfix_base(base), fields.join(".")),
+        Expr::Index(base, args) => {
+            let args: Vec<String> = args.iter()
+                .map(|arg| match arg {
+                    IndexArg::At(e) => source(e, true),
+                    IndexArg::Slice(start, end) => format!(
+                        "{}:{}",
+                        start.as_ref().map(|e| source(e, true)).unwrap_or_default(),
+                        end.as_ref().map(|e| source(e, true)).unwrap_or_default()
+                    ),
+                })
+                .collect();
+            format!("{}[{}]", postfix_base(base), args.join(", "))
+        }
+    }
+}
+
+/// Operand of a binary operator, parenthesized where the parser would
+/// otherwise group it differently
+fn operand(child: &Expr, op: BinOp, left: bool, leading: bool) -> String {
+    let parent = op_level(op);
+    let child_level = level(child);
+    let wrap = match (left, parent) {
+        // Both sides of a comparison are parsed as sums
+        (_, Level::Comparison) => child_level == Level::Comparison,
+        // The base of a power is a primary
+        (true, Level::Power) => child_level < Level::Atom || is_negative(child),
+        // Powers are right-associative; `x^T` would be a transpose
+        (false, Level::Power) => {
+            child_level < Level::Power || matches!(child, Expr::Variable(parts) if parts.len() == 1 && parts[0] == "T")
+        }
+        // Left-associative: a - (b - c) and a * (b * c) keep their parentheses
+        (true, _) => child_level < parent,
+        (false, _) => child_level <= parent,
+    };
+    if wrap {
+        format!("({})", source(child, true))
+    } else {
+        source(child, leading && left)
+    }
+}
+
+fn postfix_base(expr: &Expr) -> String {
+    if level(expr) < Level::Atom || is_negative(expr) {
+        format!("({})", source(expr, true))
+    } else {
+        source(expr, true)
+    }
+}
+
+fn join(items: &[Expr]) -> String {
+    items.iter().map(|e| source(e, true)).collect::<Vec<_>>().join(", ")
+}
+
+fn record_key(key: &str) -> String {
+    if key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+        key.to_string()
+    } else {
+        format!("\"{}\"", key)
+    }
+}
+
+fn op_symbol(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::ElemMul => ".*",
+        BinOp::ElemDiv => "./",
+        BinOp::Pow => "^",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Eq => "==",
+        BinOp::Ne => "!=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(src: &str) -> String {
+        let expr = parser::parse_expr(src).unwrap();
+        let printed = expr.to_string();
+        let reparsed = parser::parse_expr(&printed).unwrap();
+        assert_eq!(format!("{:?}", expr), format!("{:?}", reparsed), "{} -> {}", src, printed);
+        printed
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        assert_eq!(canonical("(a+b)*c"), "(a + b) * c");
+        assert_eq!(canonical("((a*b))+c"), "a * b + c");
+        assert_eq!(canonical("a-(b-c)"), "a - (b - c)");
+        assert_eq!(canonical("a*(b*c)"), "a * (b * c)");
+        assert_eq!(canonical("(a-b)-c"), "a - b - c");
+        assert_eq!(canonical("2^(3^x)"), "2^3^x");
+        assert_eq!(canonical("(2^3)^x"), "(2^3)^x");
+        assert_eq!(canonical("(a < b) == (c > d)"), "(a < b) == (c > d)");
+        assert_eq!(canonical("m.*n./k"), "m .* n ./ k");
+    }
+
+    #[test]
+    fn test_negative_numbers_and_postfix() {
+        assert_eq!(canonical("-3*x"), "-3 * x");
+        assert_eq!(canonical("x+(-3)"), "x + (-3)");
+        assert_eq!(canonical("x*(-3)"), "x * (-3)");
+        assert_eq!(canonical("(-2)^2"), "(-2)^2");
+        assert_eq!(canonical("2^(-1)"), "2^(-1)");
+        assert_eq!(canonical("x^(T)"), "x^(T)");
+        assert_eq!(canonical("(a+b)^T"), "(a + b)'");
+        assert_eq!(canonical("(m')^2"), "(m')^2");
+        assert_eq!(canonical("m' * 'text'"), "m' * \"text\"");
+        assert_eq!(canonical("(a + b)[0]"), "(a + b)[0]");
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(canonical("sum( [1,2 , x] )"), "sum([1, 2, x])");
+        assert_eq!(canonical("{a:1,\"two words\":x, b}"), "{a: 1, \"two words\": x, b}");
+        assert_eq!(canonical("v[1:-1, :]"), "v[1:-1, :]");
+        assert_eq!(canonical("lookup('say \"hi\"')"), "lookup('say \"hi\"')");
+        assert_eq!(canonical("stats(x).mean"), "stats(x).mean");
+    }
+
+    #[test]
+    fn test_format_document() {
+        let src = "Intro   \n\n## Loan @precision:20 @format:#,##0.00\n\n| name | formula | result |\n|:-|--:|---|\n| rate | 0.05 | |\n| payment | = (principal*rate)/12 @sigfigs:3 @format:percent | old |\n| check | =(5) | |\n\n\n";
+        let formatted = format_document(src).unwrap();
+        assert_eq!(
+            formatted,
+            "Intro\n\n## Loan @format:#,##0.00 @precision:20\n\n\
+             | name    | formula                                          | result |\n\
+             | :------ | -----------------------------------------------: | ------ |\n\
+             | rate    | 0.05                                             |        |\n\
+             | payment | principal * rate / 12 @format:percent @sigfigs:3 | old    |\n\
+             | check   | =5                                               |        |\n"
+        );
+        assert_eq!(format_document(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_equivalent_documents_converge() {
+        let marked = "# Totals\n\n| name | formula | result |\n|---|---|---|\n| a | =1+2 | |\n| b | =5 | |\n";
+        let plain = "## Totals\n\n| name | formula | result |\n|---|---|---|\n| a | 1 + 2 | |\n| b | =5 | |\n";
+        let formatted = format_document(marked).unwrap();
+        assert_eq!(formatted, format_document(plain).unwrap());
+        // `=` stays where the formula would otherwise read as a literal
+        assert!(formatted.starts_with("## Totals\n"), "{}", formatted);
+        assert!(formatted.contains("| a    | 1 + 2   |"), "{}", formatted);
+        assert!(formatted.contains("| b    | =5      |"), "{}", formatted);
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let src = "---\nname: loan\narguments:\n  - name: rate\n---\n# Loan\n\n| name | formula | result |\n|---|---|---|\n| x | 1 | |\n";
+        let doc = parser::parse(src).unwrap();
+        assert_eq!(doc.front_matter.as_deref(), Some("name: loan\narguments:\n  - name: rate\n"));
+        let formatted = format_document(src).unwrap();
+        assert!(formatted.starts_with("---\nname: loan\narguments:\n  - name: rate\n---\n## Loan\n"), "{}", formatted);
+        assert_eq!(format_document(&formatted).unwrap(), formatted);
+
+        // Metadata, not output
+        let markdown = crate::Folio::default().eval(src, &Default::default()).markdown;
+        assert!(!markdown.contains("name: loan"), "{}", markdown);
+
+        assert_eq!(parser::split_front_matter("---\nunclosed\n"), (None, "---\nunclosed\n"));
+        assert_eq!(parser::split_front_matter("x\n---\n"), (None, "x\n---\n"));
+    }
+}
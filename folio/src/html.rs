@@ -0,0 +1,334 @@
+//! HTML renderer
+//!
+//! Writes a standalone HTML page. Result cells show the formula that produced
+//! them on hover; error cells show the message, suggestion and notes.
+
+use crate::ast::{Block, Cell, Document};
+use crate::render::{column_alignment, heading_parts, Align, CellFormat, DocumentRenderer, Renderer};
+use folio_core::{FolioError, Value};
+use std::collections::HashMap;
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }
+table.folio { border-collapse: collapse; margin: 1rem 0; }
+table.folio th, table.folio td { border: 1px solid #ccc; padding: 0.25rem 0.6rem; }
+table.folio th { background: #f4f4f4; }
+table.folio td.result { font-variant-numeric: tabular-nums; }
+table.folio td[title] { cursor: help; text-decoration: underline dotted #999; }
+table.folio td.error { color: #b00020; background: #fdecea; }
+pre { background: #f6f8fa; padding: 0.75rem; overflow-x: auto; }
+";
+
+/// Standalone HTML back-end
+pub struct HtmlRenderer {
+    title: Option<String>,
+}
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self { title: None }
+    }
+
+    /// Page title (default: the first section name)
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    fn page(&self, title: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape(title), STYLE, body
+        )
+    }
+
+    fn render_table_start(&self, out: &mut String, cells: &[String], result_col: usize, align: &[Align]) {
+        out.push_str("<table class=\"folio\">\n<thead>\n<tr>");
+        for (i, cell) in cells.iter().enumerate() {
+            out.push_str(&format!("<th{}>{}</th>", align_attr(align, i, result_col), escape(cell)));
+        }
+        out.push_str("</tr>\n</thead>\n<tbody>\n");
+    }
+}
+
+impl DocumentRenderer for HtmlRenderer {
+    fn render(
+        &self,
+        doc: &Document,
+        values: &HashMap<String, Value>,
+        external: &HashMap<String, Value>,
+    ) -> String {
+        let display = Renderer::new();
+        let mut body = String::new();
+
+        if !external.is_empty() {
+            body.push_str("<h2>External Variables</h2>\n<table class=\"folio\">\n<thead>\n<tr><th>name</th><th>value</th></tr>\n</thead>\n<tbody>\n");
+            let format = CellFormat::default();
+            let mut names: Vec<&String> = external.keys().collect();
+            names.sort();
+            for name in names {
+                body.push_str(&format!(
+                    "<tr><td>{}</td><td class=\"result\">{}</td></tr>\n",
+                    escape(name), escape(&display.render_value(&external[name], &format))
+                ));
+            }
+            body.push_str("</tbody>\n</table>\n");
+        }
+
+        body.push_str(&prose(&doc.preamble));
+
+        for section in &doc.sections {
+            if let Some(heading) = &section.heading {
+                let level = heading_parts(heading).map_or(2, |(level, _)| level);
+                body.push_str(&format!("<h{0}>{1}</h{0}>\n", level, escape(&section.name)));
+            }
+
+            let section_format = display.cell_format(&section.attributes, &Default::default());
+            let mut text: Vec<String> = Vec::new();
+            let mut header: Option<(Vec<String>, usize)> = None;
+            let mut align: Vec<Align> = Vec::new();
+            let mut in_table = false;
+
+            for block in &section.body {
+                if !matches!(block, Block::Text(_)) && !text.is_empty() {
+                    body.push_str(&prose(&std::mem::take(&mut text)));
+                }
+                if !matches!(block, Block::Row(_) | Block::Separator(_)) && in_table {
+                    body.push_str("</tbody>\n</table>\n");
+                    in_table = false;
+                }
+                match block {
+                    Block::Text(line) => text.push(line.clone()),
+                    Block::Header { cells, result_column } => {
+                        let mut cells = cells.clone();
+                        let result_col = result_column.unwrap_or_else(|| {
+                            cells.push("result".to_string());
+                            cells.len() - 1
+                        });
+                        header = Some((cells, result_col));
+                        align.clear();
+                    }
+                    Block::Separator(markers) => {
+                        align = markers.iter().map(|m| column_alignment(m)).collect();
+                        if let Some((cells, result_col)) = &header {
+                            self.render_table_start(&mut body, cells, *result_col, &align);
+                            in_table = true;
+                        }
+                    }
+                    Block::Row(index) => {
+                        let Some((cells, result_col)) = &header else { continue };
+                        if !in_table {
+                            self.render_table_start(&mut body, cells, *result_col, &align);
+                            in_table = true;
+                        }
+                        let row = &section.table.rows[*index];
+                        let mut raw = row.raw_cells.clone();
+                        while raw.len() <= *result_col {
+                            raw.push(String::new());
+                        }
+                        body.push_str("<tr>");
+                        for (i, text) in raw.iter().enumerate() {
+                            let attr = align_attr(&align, i, *result_col);
+                            match row.cells.first() {
+                                Some(cell) if i == *result_col => {
+                                    let format = if cell.attributes.is_empty() {
+                                        section_format.clone()
+                                    } else {
+                                        display.cell_format(&section.attributes, &cell.attributes)
+                                    };
+                                    body.push_str(&result_td(&display, cell, values.get(&cell.name), &format, &attr));
+                                }
+                                Some(cell) if i == 1 && cell.formula.is_some() => {
+                                    body.push_str(&format!("<td{}><code>{}</code></td>", attr, escape(text)));
+                                }
+                                _ => body.push_str(&format!("<td{}>{}</td>", attr, escape(text))),
+                            }
+                        }
+                        body.push_str("</tr>\n");
+                    }
+                }
+            }
+            if in_table {
+                body.push_str("</tbody>\n</table>\n");
+            }
+            body.push_str(&prose(&text));
+        }
+
+        let title = self.title.clone()
+            .or_else(|| doc.sections.first().map(|s| s.name.clone()))
+            .unwrap_or_else(|| "Folio".to_string());
+        self.page(&title, &body)
+    }
+
+    fn render_parse_error(&self, error: &FolioError) -> String {
+        let mut body = String::from("<h1>Parse Error</h1>\n");
+        body.push_str(&format!("<p class=\"error\"><strong>{}</strong>: {}</p>\n", escape(&error.code), escape(&error.message)));
+        if let Some(suggestion) = &error.suggestion {
+            body.push_str(&format!("<p>{}</p>\n", escape(suggestion)));
+        }
+        self.page("Parse Error", &body)
+    }
+}
+
+impl Default for HtmlRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result cell; the tooltip shows the formula, or the error details
+fn result_td(display: &Renderer, cell: &Cell, value: Option<&Value>, format: &CellFormat, align: &str) -> String {
+    let Some(value) = value else {
+        return format!("<td class=\"result\"{}></td>", align);
+    };
+    let text = display.render_value(value, format);
+    match value {
+        Value::Error(e) => {
+            let mut tip = format!("{}: {}", e.code, e.message);
+            if let Some(suggestion) = &e.suggestion {
+                tip.push_str(&format!("\nSuggestion: {}", suggestion));
+            }
+            if let Some(context) = &e.context {
+                for note in &context.notes {
+                    tip.push_str(&format!("\n{}", note));
+                }
+            }
+            if cell.formula.is_some() {
+                tip.push_str(&format!("\n= {}", cell.raw_text));
+            }
+            format!("<td class=\"result error\"{} title=\"{}\">{}</td>", align, escape(&tip), escape(&text))
+        }
+        _ if cell.formula.is_some() => format!(
+            "<td class=\"result\"{} title=\"= {}\">{}</td>",
+            align, escape(&cell.raw_text), escape(&text)
+        ),
+        _ => format!("<td class=\"result\"{}>{}</td>", align, escape(&text)),
+    }
+}
+
+fn align_attr(align: &[Align], col: usize, result_col: usize) -> String {
+    let align = align.get(col).copied().unwrap_or(Align::Default);
+    let align = match align {
+        Align::Default if col == result_col => Align::Right,
+        other => other,
+    };
+    match align {
+        Align::Default => String::new(),
+        Align::Left => " style=\"text-align: left\"".to_string(),
+        Align::Center => " style=\"text-align: center\"".to_string(),
+        Align::Right => " style=\"text-align: right\"".to_string(),
+    }
+}
+
+/// Markdown prose to HTML: headings, paragraphs, bullet lists and fenced code
+fn prose(lines: &[String]) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut list: Vec<String> = Vec::new();
+    let mut fence: Option<Vec<String>> = None;
+
+    fn flush(out: &mut String, paragraph: &mut Vec<String>, list: &mut Vec<String>) {
+        if !paragraph.is_empty() {
+            out.push_str(&format!("<p>{}</p>\n", inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+        if !list.is_empty() {
+            out.push_str("<ul>\n");
+            for item in list.drain(..) {
+                out.push_str(&format!("<li>{}</li>\n", inline(&item)));
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+
+    for line in lines {
+        let trimmed = line.trim();
+        if let Some(code) = fence.as_mut() {
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                out.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&code.join("\n"))));
+                fence = None;
+            } else {
+                code.push(line.clone());
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush(&mut out, &mut paragraph, &mut list);
+            fence = Some(Vec::new());
+        } else if trimmed.is_empty() {
+            flush(&mut out, &mut paragraph, &mut list);
+        } else if let Some((level, text)) = heading_parts(trimmed) {
+            flush(&mut out, &mut paragraph, &mut list);
+            out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            if !paragraph.is_empty() {
+                flush(&mut out, &mut paragraph, &mut Vec::new());
+            }
+            list.push(item.to_string());
+        } else {
+            if !list.is_empty() {
+                flush(&mut out, &mut Vec::new(), &mut list);
+            }
+            paragraph.push(trimmed.to_string());
+        }
+    }
+    if let Some(code) = fence {
+        out.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&code.join("\n"))));
+    }
+    flush(&mut out, &mut paragraph, &mut list);
+    out
+}
+
+/// Inline `code` and **bold**
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            out.push_str(&format!("<code>{}</code>", escape(part)));
+            continue;
+        }
+        for (j, piece) in part.split("**").enumerate() {
+            if j % 2 == 1 {
+                out.push_str(&format!("<strong>{}</strong>", escape(piece)));
+            } else {
+                out.push_str(&escape(piece));
+            }
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prose() {
+        let lines: Vec<String> = ["Intro with `code` and **bold**.", "", "- one", "- two", "### Notes"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            prose(&lines),
+            "<p>Intro with <code>code</code> and <strong>bold</strong>.</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<h3>Notes</h3>\n"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a < b & \"c\""), "a &lt; b &amp; &quot;c&quot;");
+    }
+}
@@ -0,0 +1,172 @@
+//! Indexing and slicing: x[i], x[a:b], m[i, j], m[:, j]
+//!
+//! Positions are 0-based; negative positions count from the end. Slices are
+//! half-open and clamp to the available range like Python. A List of Bool
+//! works as a mask and a List of Numbers picks several positions.
+
+use folio_core::{Value, FolioError};
+#[cfg(feature = "matrix")]
+use folio_matrix::{extract_matrix, is_matrix, Matrix, MatrixMode};
+
+/// An evaluated index argument
+#[derive(Debug, Clone)]
+pub(crate) enum Selector {
+    At(Value),
+    Slice(Option<Value>, Option<Value>),
+}
+
+/// Apply `target[selectors]`
+pub(crate) fn index_value(target: &Value, selectors: &[Selector]) -> Value {
+    match index_inner(target, selectors) {
+        Ok(v) => v,
+        Err(e) => Value::Error(e),
+    }
+}
+
+fn index_inner(target: &Value, selectors: &[Selector]) -> Result<Value, FolioError> {
+    if let Value::Error(e) = target {
+        return Err(e.clone());
+    }
+    let Some((first, rest)) = selectors.split_first() else {
+        return Ok(target.clone());
+    };
+
+    #[cfg(feature = "matrix")]
+    if is_matrix(target) {
+        return index_matrix(target, selectors);
+    }
+
+    match target {
+        Value::List(items) => {
+            let what = format!("list of length {}", items.len());
+            let (picked, single) = positions(first, items.len(), &what)?;
+            if single {
+                return index_inner(&items[picked[0]], rest);
+            }
+            let values: Result<Vec<Value>, FolioError> = picked.into_iter()
+                .map(|i| index_inner(&items[i], rest))
+                .collect();
+            Ok(Value::List(values?))
+        }
+        Value::Text(s) => {
+            if !rest.is_empty() {
+                return Err(FolioError::type_error("single index for Text", &format!("{} indices", selectors.len())));
+            }
+            let chars: Vec<char> = s.chars().collect();
+            let what = format!("text of length {}", chars.len());
+            let (picked, _) = positions(first, chars.len(), &what)?;
+            Ok(Value::Text(picked.into_iter().map(|i| chars[i]).collect()))
+        }
+        Value::Object(map) => match first {
+            Selector::At(Value::Text(key)) => match map.get(key) {
+                Some(v) => index_inner(v, rest),
+                None => Err(FolioError::new(folio_core::codes::UNDEFINED_FIELD, format!("Field '{}' not found in object", key))
+                    .with_suggestion(format!("Available fields: {}", sorted_keys(map)))),
+            },
+            _ => Err(FolioError::type_error("Text key for Object", "non-Text index")
+                .with_suggestion("Index objects by field name, e.g. obj[\"slope\"]")),
+        },
+        other => Err(FolioError::type_error("List, Text, Matrix or Object", other.type_name())
+            .with_note("only these values can be indexed")),
+    }
+}
+
+/// m[i, j] → Number, m[i] / m[i, :] → row List, m[:, j] → column List, ranges → Matrix
+#[cfg(feature = "matrix")]
+fn index_matrix(target: &Value, selectors: &[Selector]) -> Result<Value, FolioError> {
+    let m = extract_matrix(target, "index", "matrix")?;
+    let all = Selector::Slice(None, None);
+    let (row_sel, col_sel) = match selectors {
+        [r] => (r, &all),
+        [r, c] => (r, c),
+        _ => return Err(FolioError::type_error("at most 2 indices for Matrix", &format!("{} indices", selectors.len()))),
+    };
+
+    let (rows, single_row) = positions(row_sel, m.rows(), &format!("matrix with {} rows", m.rows()))?;
+    let (cols, single_col) = positions(col_sel, m.cols(), &format!("matrix with {} columns", m.cols()))?;
+    let cell = |r: usize, c: usize| Value::Number(m.get(r, c).expect("index resolved against matrix shape"));
+
+    Ok(match (single_row, single_col) {
+        (true, true) => cell(rows[0], cols[0]),
+        (true, false) => Value::List(cols.iter().map(|&c| cell(rows[0], c)).collect()),
+        (false, true) => Value::List(rows.iter().map(|&r| cell(r, cols[0])).collect()),
+        (false, false) => {
+            let data = rows.iter()
+                .map(|&r| cols.iter().map(|&c| m.get(r, c).expect("index resolved against matrix shape")).collect())
+                .collect();
+            Matrix::from_nested_list(data, MatrixMode::Auto)?.into()
+        }
+    })
+}
+
+/// Resolve a selector against a length. The flag is true for a single position.
+fn positions(selector: &Selector, len: usize, what: &str) -> Result<(Vec<usize>, bool), FolioError> {
+    match selector {
+        Selector::At(Value::List(items)) => {
+            if !items.is_empty() && items.iter().all(|v| matches!(v, Value::Bool(_))) {
+                if items.len() != len {
+                    return Err(FolioError::shape_mismatch(format!(
+                        "mask of length {} for {}", items.len(), what
+                    )));
+                }
+                let picked = items.iter().enumerate()
+                    .filter(|(_, v)| matches!(v, Value::Bool(true)))
+                    .map(|(i, _)| i)
+                    .collect();
+                return Ok((picked, false));
+            }
+            let picked: Result<Vec<usize>, FolioError> = items.iter()
+                .map(|v| position(v, len, what))
+                .collect();
+            Ok((picked?, false))
+        }
+        Selector::At(v) => Ok((vec![position(v, len, what)?], true)),
+        Selector::Slice(start, end) => {
+            let start = bound(start.as_ref(), len, 0)?;
+            let end = bound(end.as_ref(), len, len)?;
+            Ok(((start..end.max(start)).collect(), false))
+        }
+    }
+}
+
+/// A single index; negative values count from the end
+fn position(value: &Value, len: usize, what: &str) -> Result<usize, FolioError> {
+    let raw = integer(value)?;
+    let resolved = if raw < 0 { raw + len as i64 } else { raw };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(FolioError::domain_error(format!("Index {} out of bounds for {}", raw, what))
+            .with_suggestion(if len == 0 {
+                "The value is empty".to_string()
+            } else {
+                format!("Valid indices are 0..{} or -{}..-1", len - 1, len)
+            }));
+    }
+    Ok(resolved as usize)
+}
+
+/// A slice bound, clamped to 0..=len
+fn bound(value: Option<&Value>, len: usize, default: usize) -> Result<usize, FolioError> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    let raw = integer(value)?;
+    let resolved = if raw < 0 { raw + len as i64 } else { raw };
+    Ok(resolved.clamp(0, len as i64) as usize)
+}
+
+fn integer(value: &Value) -> Result<i64, FolioError> {
+    match value {
+        Value::Number(n) if n.is_integer() => n.to_i64()
+            .ok_or_else(|| FolioError::domain_error(format!("Index {} is too large", n))),
+        Value::Number(n) => Err(FolioError::domain_error(format!("Index must be an integer, got {}", n))),
+        Value::Error(e) => Err(e.clone()),
+        other => Err(FolioError::type_error("Number", other.type_name())
+            .with_note("in index")),
+    }
+}
+
+fn sorted_keys(map: &std::collections::HashMap<String, Value>) -> String {
+    let mut keys: Vec<&str> = map.keys().map(|k| k.as_str()).collect();
+    keys.sort();
+    keys.join(", ")
+}
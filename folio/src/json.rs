@@ -0,0 +1,355 @@
+//! JSON renderer
+//!
+//! Emits an evaluated document as structured JSON for programmatic clients.
+//! The layout is versioned by the `schema` field; fields may be added within
+//! a version but never renamed or removed.
+//!
+//! ```text
+//! {
+//!   "schema": "folio.eval/v1",
+//!   "sections": [{
+//!     "name": "Totals",
+//!     "attributes": { "precision": "20" },
+//!     "cells": [{
+//!       "name": "c",
+//!       "formula": "a + b",          // null for literal cells
+//!       "source": "a + b",           // cell text as written
+//!       "attributes": {},
+//!       "depends_on": ["a", "b"],
+//!       "value": { "type": "number", "value": "42", "precision": 20, "digits": 2 },
+//!       "display": "42.0000000000"   // as in the markdown output
+//!     }]
+//!   }],
+//!   "external": { "rate": { "type": "number", ... } },
+//!   "dependencies": [{ "from": "c", "to": "a" }, { "from": "c", "to": "b" }],
+//!   "errors": [{ "code": "DIV_ZERO", "message": "...", "context": { "cell": "x", "formula": "1/0" } }]
+//! }
+//! ```
+//!
+//! Typed values carry a `type` tag: `number` (exact decimal string plus the
+//! working precision and significant digits), `text`, `bool`, `null`,
+//! `datetime`, `duration`, `list` (`items`), `object` (`fields`), `matrix`
+//! (`rows`, `cols`, `data`), `error` (`error`), or the name of a custom type.
+
+use crate::ast::Document;
+use crate::eval::Evaluator;
+use crate::render::{matrix_rows, DocumentRenderer, Renderer};
+use folio_core::{FolioError, Number, Value};
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+
+/// Identifier of the JSON layout produced by [`JsonRenderer`]
+pub const JSON_SCHEMA_VERSION: &str = "folio.eval/v1";
+
+/// Renders an evaluated document as `folio.eval/v1` JSON
+pub struct JsonRenderer {
+    default_precision: u32,
+}
+
+impl JsonRenderer {
+    pub fn new() -> Self {
+        Self { default_precision: 50 }
+    }
+
+    /// Precision reported for sections without `@precision`
+    pub fn with_precision(mut self, precision: u32) -> Self {
+        self.default_precision = precision;
+        self
+    }
+
+    /// Render document with computed values and its dependency graph
+    pub fn render(
+        &self,
+        doc: &Document,
+        values: &HashMap<String, Value>,
+        external: &HashMap<String, Value>,
+        dependencies: &HashMap<String, Vec<String>>,
+    ) -> JsonValue {
+        let display = Renderer::new();
+        let mut errors = Vec::new();
+        let mut sections = Vec::new();
+
+        for section in &doc.sections {
+            let precision = section.attributes.get("precision")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(self.default_precision);
+
+            let mut cells = Vec::new();
+            for row in &section.table.rows {
+                for cell in &row.cells {
+                    let format = display.cell_format(&section.attributes, &cell.attributes);
+                    let depends_on = dependencies.get(&cell.name).cloned().unwrap_or_default();
+                    let mut entry = json!({
+                        "name": cell.name,
+                        "formula": cell.formula.as_ref().map(|_| cell.raw_text.as_str()),
+                        "source": cell.raw_text,
+                        "attributes": attributes_json(cell.attributes.iter()),
+                        "depends_on": depends_on,
+                    });
+                    if let Some(value) = values.get(&cell.name) {
+                        entry["value"] = typed_value(value, precision);
+                        entry["display"] = JsonValue::String(display.render_value(value, &format));
+                        if let Value::Error(e) = value {
+                            let e = with_cell_context(e, &cell.name, &cell.raw_text);
+                            entry["error"] = error_json(&e);
+                            errors.push(error_json(&e));
+                        }
+                    }
+                    cells.push(entry);
+                }
+            }
+
+            sections.push(json!({
+                "name": section.name,
+                "attributes": attributes_json(section.attributes.iter()),
+                "cells": cells,
+            }));
+        }
+
+        let mut names: Vec<&String> = dependencies.keys().collect();
+        names.sort();
+        let edges: Vec<JsonValue> = names.into_iter()
+            .flat_map(|from| dependencies[from].iter().map(move |to| json!({ "from": from, "to": to })))
+            .collect();
+
+        let external: Map<String, JsonValue> = external.iter()
+            .map(|(k, v)| (k.clone(), typed_value(v, self.default_precision)))
+            .collect();
+
+        json!({
+            "schema": JSON_SCHEMA_VERSION,
+            "sections": sections,
+            "external": external,
+            "dependencies": edges,
+            "errors": errors,
+        })
+    }
+
+    /// Output for a document that failed to parse
+    pub fn render_parse_error(&self, error: &FolioError) -> JsonValue {
+        json!({
+            "schema": JSON_SCHEMA_VERSION,
+            "sections": [],
+            "external": {},
+            "dependencies": [],
+            "errors": [error_json(error)],
+        })
+    }
+
+    /// JSON Schema describing the output of [`JsonRenderer::render`]
+    pub fn schema() -> JsonValue {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$id": JSON_SCHEMA_VERSION,
+            "type": "object",
+            "required": ["schema", "sections", "external", "dependencies", "errors"],
+            "properties": {
+                "schema": { "const": JSON_SCHEMA_VERSION },
+                "sections": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["name", "attributes", "cells"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "attributes": { "$ref": "#/$defs/attributes" },
+                            "cells": { "type": "array", "items": { "$ref": "#/$defs/cell" } }
+                        }
+                    }
+                },
+                "external": { "type": "object", "additionalProperties": { "$ref": "#/$defs/value" } },
+                "dependencies": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["from", "to"],
+                        "properties": { "from": { "type": "string" }, "to": { "type": "string" } }
+                    }
+                },
+                "errors": { "type": "array", "items": { "$ref": "#/$defs/error" } }
+            },
+            "$defs": {
+                "attributes": { "type": "object", "additionalProperties": { "type": "string" } },
+                "cell": {
+                    "type": "object",
+                    "required": ["name", "formula", "source", "attributes", "depends_on"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "formula": { "type": ["string", "null"] },
+                        "source": { "type": "string" },
+                        "attributes": { "$ref": "#/$defs/attributes" },
+                        "depends_on": { "type": "array", "items": { "type": "string" } },
+                        "value": { "$ref": "#/$defs/value" },
+                        "display": { "type": "string" },
+                        "error": { "$ref": "#/$defs/error" }
+                    }
+                },
+                "value": {
+                    "type": "object",
+                    "required": ["type"],
+                    "properties": {
+                        "type": { "type": "string" },
+                        "value": {},
+                        "precision": { "type": "integer" },
+                        "digits": { "type": "integer" },
+                        "items": { "type": "array", "items": { "$ref": "#/$defs/value" } },
+                        "fields": { "type": "object", "additionalProperties": { "$ref": "#/$defs/value" } },
+                        "rows": { "type": "integer" },
+                        "cols": { "type": "integer" },
+                        "data": { "type": "array", "items": { "type": "array", "items": { "$ref": "#/$defs/value" } } },
+                        "error": { "$ref": "#/$defs/error" }
+                    }
+                },
+                "error": {
+                    "type": "object",
+                    "required": ["code", "message"],
+                    "properties": {
+                        "code": { "type": "string" },
+                        "message": { "type": "string" },
+                        "suggestion": { "type": "string" },
+                        "severity": { "type": "string" },
+                        "context": {
+                            "type": "object",
+                            "properties": {
+                                "cell": { "type": "string" },
+                                "formula": { "type": "string" },
+                                "line": { "type": "integer" },
+                                "column": { "type": "integer" },
+                                "notes": { "type": "array", "items": { "type": "string" } }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl DocumentRenderer for JsonRenderer {
+    fn render(
+        &self,
+        doc: &Document,
+        values: &HashMap<String, Value>,
+        external: &HashMap<String, Value>,
+    ) -> String {
+        let dependencies = Evaluator::new().dependencies(doc);
+        let json = JsonRenderer::render(self, doc, values, external, &dependencies);
+        serde_json::to_string_pretty(&json).unwrap_or_default()
+    }
+
+    fn render_parse_error(&self, error: &FolioError) -> String {
+        serde_json::to_string_pretty(&JsonRenderer::render_parse_error(self, error)).unwrap_or_default()
+    }
+}
+
+impl Default for JsonRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tagged JSON for a value; numbers keep every digit as a string
+pub(crate) fn typed_value(value: &Value, precision: u32) -> JsonValue {
+    match value {
+        Value::Number(n) => number_json(n, precision),
+        Value::Text(s) => json!({ "type": "text", "value": s }),
+        Value::Bool(b) => json!({ "type": "bool", "value": b }),
+        Value::Null => json!({ "type": "null" }),
+        Value::DateTime(dt) => json!({ "type": "datetime", "value": dt.to_string(), "nanos": dt.as_nanos().to_string() }),
+        Value::Duration(d) => json!({ "type": "duration", "value": d.to_string(), "nanos": d.as_nanos().to_string() }),
+        Value::List(items) => json!({
+            "type": "list",
+            "items": items.iter().map(|v| typed_value(v, precision)).collect::<Vec<_>>(),
+        }),
+        Value::Object(map) => {
+            let fields: Map<String, JsonValue> = map.iter()
+                .map(|(k, v)| (k.clone(), typed_value(v, precision)))
+                .collect();
+            json!({ "type": "object", "fields": fields })
+        }
+        Value::Custom(ext) => match matrix_rows(value) {
+            Some(rows) => json!({
+                "type": "matrix",
+                "rows": rows.len(),
+                "cols": rows.first().map_or(0, |r| r.len()),
+                "data": rows.iter()
+                    .map(|r| r.iter().map(|v| typed_value(v, precision)).collect::<Vec<_>>())
+                    .collect::<Vec<_>>(),
+            }),
+            None => json!({ "type": ext.type_name(), "value": ext.to_json() }),
+        },
+        Value::Error(e) => json!({ "type": "error", "error": error_json(e) }),
+    }
+}
+
+fn number_json(n: &Number, precision: u32) -> JsonValue {
+    let exact = n.as_exact();
+    json!({
+        "type": "number",
+        "value": exact,
+        "precision": precision,
+        "digits": significant_digits(&exact),
+    })
+}
+
+/// Count significant digits of a plain decimal string ("0.0120" → 3, "1200" → 2)
+fn significant_digits(text: &str) -> usize {
+    let mantissa = text.split(['e', 'E']).next().unwrap_or(text);
+    let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+    let digits = digits.trim_start_matches('0');
+    let digits = if mantissa.contains('.') {
+        digits
+    } else {
+        digits.trim_end_matches('0')
+    };
+    digits.len().max(1)
+}
+
+fn error_json(error: &FolioError) -> JsonValue {
+    serde_json::to_value(error).unwrap_or_else(|_| json!({ "code": error.code, "message": error.message }))
+}
+
+/// Fill in the cell and formula unless the error already names its origin
+fn with_cell_context(error: &FolioError, cell: &str, formula: &str) -> FolioError {
+    let has_cell = error.context.as_ref().is_some_and(|c| c.cell.is_some());
+    if has_cell {
+        error.clone()
+    } else {
+        error.clone().in_cell(cell).with_formula(formula)
+    }
+}
+
+fn attributes_json<'a>(attrs: impl Iterator<Item = (&'a String, &'a String)>) -> JsonValue {
+    let map: Map<String, JsonValue> = attrs
+        .map(|(k, v)| (k.clone(), JsonValue::String(v.clone())))
+        .collect();
+    JsonValue::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_significant_digits() {
+        assert_eq!(significant_digits("42"), 2);
+        assert_eq!(significant_digits("1200"), 2);
+        assert_eq!(significant_digits("0.0120"), 3);
+        assert_eq!(significant_digits("-3.14159"), 6);
+        assert_eq!(significant_digits("0"), 1);
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let n = Number::from_str("1/3").unwrap();
+        let v = typed_value(&Value::Number(n), 50);
+        assert_eq!(v["type"], "number");
+        assert_eq!(v["precision"], 50);
+        assert!(v["value"].as_str().unwrap().starts_with("0.3333333333333333333"));
+
+        let list = Value::List(vec![Value::Bool(true), Value::Text("x".into())]);
+        let v = typed_value(&list, 50);
+        assert_eq!(v["items"][0], json!({ "type": "bool", "value": true }));
+        assert_eq!(v["items"][1], json!({ "type": "text", "value": "x" }));
+    }
+}
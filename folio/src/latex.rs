@@ -0,0 +1,282 @@
+//! LaTeX renderer
+//!
+//! Writes sections as starred headings and tables as `tabular` environments,
+//! with formulas typeset in math mode. The output is a fragment for `\input`
+//! unless `standalone` is set.
+
+use crate::ast::{Block, Document};
+use crate::render::{column_alignment, heading_parts, Align, CellFormat, DocumentRenderer, Renderer};
+use folio_core::{FolioError, Value};
+use std::collections::HashMap;
+
+/// LaTeX back-end
+pub struct LatexRenderer {
+    standalone: bool,
+}
+
+impl LatexRenderer {
+    pub fn new() -> Self {
+        Self { standalone: false }
+    }
+
+    /// Wrap the output in `\documentclass{article}` … `\end{document}`
+    pub fn standalone(mut self, standalone: bool) -> Self {
+        self.standalone = standalone;
+        self
+    }
+
+    fn wrap(&self, body: String) -> String {
+        if !self.standalone {
+            return body;
+        }
+        format!(
+            "\\documentclass{{article}}\n\\usepackage[utf8]{{inputenc}}\n\\usepackage{{amsmath}}\n\\begin{{document}}\n\n{}\\end{{document}}\n",
+            body
+        )
+    }
+}
+
+impl DocumentRenderer for LatexRenderer {
+    fn render(
+        &self,
+        doc: &Document,
+        values: &HashMap<String, Value>,
+        external: &HashMap<String, Value>,
+    ) -> String {
+        let display = Renderer::new();
+        let mut out = String::new();
+
+        if !external.is_empty() {
+            out.push_str("\\section*{External Variables}\n\n\\begin{tabular}{lr}\n\\hline\nname & value \\\\\n\\hline\n");
+            let format = CellFormat::default();
+            let mut names: Vec<&String> = external.keys().collect();
+            names.sort();
+            for name in names {
+                out.push_str(&format!(
+                    "{} & {} \\\\\n",
+                    escape(name), escape(&display.render_value(&external[name], &format))
+                ));
+            }
+            out.push_str("\\hline\n\\end{tabular}\n\n");
+        }
+
+        out.push_str(&prose(&doc.preamble));
+
+        for section in &doc.sections {
+            if let Some(heading) = &section.heading {
+                let level = heading_parts(heading).map_or(2, |(level, _)| level);
+                out.push_str(&format!("{}{{{}}}\n\n", sectioning(level), escape(&section.name)));
+            }
+
+            let section_format = display.cell_format(&section.attributes, &Default::default());
+            let mut text: Vec<String> = Vec::new();
+            let mut header: Option<(Vec<String>, usize)> = None;
+            let mut in_table = false;
+
+            for block in &section.body {
+                if !matches!(block, Block::Text(_)) && !text.is_empty() {
+                    out.push_str(&prose(&std::mem::take(&mut text)));
+                }
+                if !matches!(block, Block::Row(_) | Block::Separator(_)) && in_table {
+                    out.push_str("\\hline\n\\end{tabular}\n\n");
+                    in_table = false;
+                }
+                match block {
+                    Block::Text(line) => text.push(line.clone()),
+                    Block::Header { cells, result_column } => {
+                        let mut cells = cells.clone();
+                        let result_col = result_column.unwrap_or_else(|| {
+                            cells.push("result".to_string());
+                            cells.len() - 1
+                        });
+                        header = Some((cells, result_col));
+                    }
+                    Block::Separator(markers) => {
+                        if let Some((cells, result_col)) = &header {
+                            let align: Vec<Align> = markers.iter().map(|m| column_alignment(m)).collect();
+                            out.push_str(&table_start(cells, *result_col, &align));
+                            in_table = true;
+                        }
+                    }
+                    Block::Row(index) => {
+                        let Some((cells, result_col)) = &header else { continue };
+                        if !in_table {
+                            out.push_str(&table_start(cells, *result_col, &[]));
+                            in_table = true;
+                        }
+                        let row = &section.table.rows[*index];
+                        let mut line: Vec<String> = row.raw_cells.iter().map(|c| escape(c)).collect();
+                        while line.len() < cells.len() {
+                            line.push(String::new());
+                        }
+                        if let Some(cell) = row.cells.first() {
+                            if let (Some(expr), Some(slot)) = (&cell.formula, line.get_mut(1)) {
+                                *slot = format!("${}$", expr.to_latex());
+                            }
+                            if let Some(value) = values.get(&cell.name) {
+                                let format = if cell.attributes.is_empty() {
+                                    section_format.clone()
+                                } else {
+                                    display.cell_format(&section.attributes, &cell.attributes)
+                                };
+                                line[*result_col] = match value {
+                                    Value::Error(e) => format!("\\textbf{{\\#ERROR: {}}}", escape(&e.code)),
+                                    other => escape(&display.render_value(other, &format)),
+                                };
+                            }
+                        }
+                        out.push_str(&line.join(" & "));
+                        out.push_str(" \\\\\n");
+                    }
+                }
+            }
+            if in_table {
+                out.push_str("\\hline\n\\end{tabular}\n\n");
+            }
+            out.push_str(&prose(&text));
+        }
+
+        self.wrap(out)
+    }
+
+    fn render_parse_error(&self, error: &FolioError) -> String {
+        let mut out = format!(
+            "\\section*{{Parse Error}}\n\n\\textbf{{{}}}: {}\n\n",
+            escape(&error.code), escape(&error.message)
+        );
+        if let Some(suggestion) = &error.suggestion {
+            out.push_str(&format!("{}\n\n", escape(suggestion)));
+        }
+        self.wrap(out)
+    }
+}
+
+impl Default for LatexRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sectioning(level: usize) -> &'static str {
+    match level {
+        1 => "\\section*",
+        2 => "\\subsection*",
+        _ => "\\subsubsection*",
+    }
+}
+
+fn table_start(cells: &[String], result_col: usize, align: &[Align]) -> String {
+    let spec: String = (0..cells.len())
+        .map(|i| match align.get(i).copied().unwrap_or(Align::Default) {
+            Align::Center => 'c',
+            Align::Right => 'r',
+            Align::Left => 'l',
+            Align::Default if i == result_col => 'r',
+            Align::Default => 'l',
+        })
+        .collect();
+    let header: Vec<String> = cells.iter().map(|c| escape(c)).collect();
+    format!("\\begin{{tabular}}{{{}}}\n\\hline\n{} \\\\\n\\hline\n", spec, header.join(" & "))
+}
+
+/// Markdown prose to LaTeX: headings, paragraphs, bullet lists and fenced code
+fn prose(lines: &[String]) -> String {
+    let mut out = String::new();
+    let mut in_list = false;
+    let mut fence = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            if in_list {
+                out.push_str("\\end{itemize}\n");
+                in_list = false;
+            }
+            out.push_str(if fence { "\\end{verbatim}\n\n" } else { "\\begin{verbatim}\n" });
+            fence = !fence;
+            continue;
+        }
+        if fence {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        let item = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* "));
+        if in_list && item.is_none() {
+            out.push_str("\\end{itemize}\n\n");
+            in_list = false;
+        }
+        if let Some(item) = item {
+            if !in_list {
+                out.push_str("\\begin{itemize}\n");
+                in_list = true;
+            }
+            out.push_str(&format!("\\item {}\n", inline(item)));
+        } else if let Some((level, text)) = heading_parts(trimmed) {
+            out.push_str(&format!("{}{{{}}}\n\n", sectioning(level), inline(text)));
+        } else if trimmed.is_empty() {
+            if !out.is_empty() && !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+        } else {
+            out.push_str(&inline(trimmed));
+            out.push('\n');
+        }
+    }
+    if in_list {
+        out.push_str("\\end{itemize}\n");
+    }
+    if fence {
+        out.push_str("\\end{verbatim}\n");
+    }
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    out
+}
+
+/// Inline `code` and **bold**
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            out.push_str(&format!("\\texttt{{{}}}", escape(part)));
+            continue;
+        }
+        for (j, piece) in part.split("**").enumerate() {
+            if j % 2 == 1 {
+                out.push_str(&format!("\\textbf{{{}}}", escape(piece)));
+            } else {
+                out.push_str(&escape(piece));
+            }
+        }
+    }
+    out
+}
+
+/// Escape LaTeX special characters in text mode
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("50% of $x & y_1"), "50\\% of \\$x \\& y\\_1");
+    }
+}
@@ -3,11 +3,27 @@
 mod parser;
 mod ast;
 mod eval;
+mod index;
 mod render;
-
-pub use ast::{Document, Section, Table, Row, Cell, Expr};
-pub use eval::{Evaluator, EvalResult};
-pub use render::Renderer;
+mod json;
+mod html;
+mod latex;
+mod format;
+mod math;
+mod formatter;
+mod diff;
+mod edit;
+
+pub use ast::{Document, Section, Table, Row, Cell, Expr, Block, Attributes};
+pub use eval::{Evaluator, EvalResult, CellSource};
+pub use diff::{EvalDiff, CellChange, ChangeKind, Cause};
+pub use render::{Renderer, DocumentRenderer};
+pub use json::{JsonRenderer, JSON_SCHEMA_VERSION};
+pub use html::HtmlRenderer;
+pub use latex::LatexRenderer;
+pub use formatter::format_document;
+pub use parser::split_front_matter;
+pub use folio_plugin::{CancelToken, Progress, ProgressCallback};
 
 use folio_plugin::{PluginRegistry, EvalContext};
 use folio_core::Value;
@@ -15,9 +31,14 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Main Folio engine
+///
+/// Cloning is cheap: the plugin registry is shared.
+#[derive(Clone)]
 pub struct Folio {
     registry: Arc<PluginRegistry>,
     default_precision: u32,
+    cancel: CancelToken,
+    progress: Option<ProgressCallback>,
 }
 
 impl Folio {
@@ -25,17 +46,28 @@ impl Folio {
         Self {
             registry: Arc::new(registry),
             default_precision: 50,
+            cancel: CancelToken::new(),
+            progress: None,
         }
     }
     
+    /// The standard library plus the domain libraries enabled as crate features
     pub fn with_standard_library() -> Self {
         let registry = folio_std::standard_registry();
+        #[cfg(feature = "stats")]
         let registry = folio_stats::load_stats_library(registry);
+        #[cfg(feature = "text")]
         let registry = folio_text::load_text_library(registry);
+        #[cfg(feature = "finance")]
         let registry = folio_finance::load_finance_library(registry);
+        #[cfg(feature = "units")]
         let registry = folio_units::load_units_library(registry);
+        #[cfg(feature = "matrix")]
         let registry = folio_matrix::load_matrix_library(registry);
+        #[cfg(feature = "kitchen")]
         let registry = folio_kitchen::load_kitchen_library(registry);
+        #[cfg(feature = "chart")]
+        let registry = folio_chart::load_chart_library(registry);
         Self::new(registry)
     }
     
@@ -43,22 +75,32 @@ impl Folio {
         self.default_precision = precision;
         self
     }
+
+    /// Stop evaluations once `token` is cancelled; remaining cells become `CANCELLED` errors
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Call `callback` after each cell of every evaluation
+    pub fn with_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
     
     pub fn eval(&self, template: &str, variables: &HashMap<String, Value>) -> EvalResult {
-        let doc = match parser::parse(template) {
-            Ok(d) => d,
-            Err(e) => return EvalResult::parse_error(e),
-        };
-        
-        let mut ctx = EvalContext::new(self.registry.clone())
-            .with_precision(self.default_precision)
-            .with_variables(variables.clone());
-        
-        let evaluator = Evaluator::new();
-        let values = evaluator.eval(&doc, &mut ctx);
-        
+        match parser::parse(template) {
+            Ok(doc) => self.eval_document(&doc, variables),
+            Err(e) => EvalResult::parse_error(e),
+        }
+    }
+
+    /// Evaluate an already parsed (possibly edited) document
+    pub fn eval_document(&self, doc: &Document, variables: &HashMap<String, Value>) -> EvalResult {
+        let (values, ctx) = self.evaluate(doc, variables);
+
         let renderer = Renderer::new();
-        let markdown = renderer.render(&doc, &values, variables);
+        let markdown = renderer.render(doc, &values, variables);
         
         EvalResult {
             markdown,
@@ -67,8 +109,57 @@ impl Folio {
                 .filter_map(|s| if let Value::Error(e) = &s.result { Some(e.clone()) } else { None })
                 .collect(),
             warnings: vec![],
+            cells: Evaluator::new().cell_sources(doc),
+            inputs: variables.clone(),
         }
     }
+
+    /// Evaluate two versions of a document and compare their results
+    pub fn diff(&self, old_template: &str, new_template: &str, variables: &HashMap<String, Value>) -> EvalDiff {
+        EvalDiff::between(&self.eval(old_template, variables), &self.eval(new_template, variables))
+    }
+
+    /// Evaluate and render as `folio.eval/v1` JSON (see [`JsonRenderer`])
+    pub fn eval_json(&self, template: &str, variables: &HashMap<String, Value>) -> serde_json::Value {
+        let renderer = JsonRenderer::new().with_precision(self.default_precision);
+        let doc = match parser::parse(template) {
+            Ok(d) => d,
+            Err(e) => return renderer.render_parse_error(&e),
+        };
+        let (values, _) = self.evaluate(&doc, variables);
+        let dependencies = Evaluator::new().dependencies(&doc);
+        renderer.render(&doc, &values, variables, &dependencies)
+    }
+
+    /// Render an evaluation of `doc` as `folio.eval/v1` JSON without evaluating again
+    pub fn render_json(&self, doc: &Document, result: &EvalResult) -> serde_json::Value {
+        let dependencies = Evaluator::new().dependencies(doc);
+        JsonRenderer::new()
+            .with_precision(self.default_precision)
+            .render(doc, &result.values, &result.inputs, &dependencies)
+    }
+
+    /// Evaluate and render with any back-end, e.g. [`HtmlRenderer`] or [`LatexRenderer`]
+    pub fn eval_with(&self, template: &str, variables: &HashMap<String, Value>, renderer: &dyn DocumentRenderer) -> String {
+        let doc = match parser::parse(template) {
+            Ok(d) => d,
+            Err(e) => return renderer.render_parse_error(&e),
+        };
+        let (values, _) = self.evaluate(&doc, variables);
+        renderer.render(&doc, &values, variables)
+    }
+
+    fn evaluate(&self, doc: &Document, variables: &HashMap<String, Value>) -> (HashMap<String, Value>, EvalContext) {
+        let mut ctx = EvalContext::new(self.registry.clone())
+            .with_precision(self.default_precision)
+            .with_variables(variables.clone())
+            .with_cancel(self.cancel.clone());
+        ctx.progress = self.progress.clone();
+
+        let evaluator = Evaluator::new();
+        let values = evaluator.eval(doc, &mut ctx);
+        (values, ctx)
+    }
     
     pub fn help(&self, name: Option<&str>) -> Value {
         self.registry.help(name)
@@ -81,6 +172,11 @@ impl Folio {
     pub fn list_constants(&self) -> Value {
         self.registry.list_constants()
     }
+
+    /// Function, command and constant names for `prefix`, see [`PluginRegistry::complete`]
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        self.registry.complete(prefix)
+    }
 }
 
 impl Default for Folio {
@@ -436,6 +532,157 @@ mod tests {
             "Speed of light display: {}", rendered);
     }
 
+    #[test]
+    fn test_number_format_patterns() {
+        let folio = test_folio();
+        let doc = r##"
+## Report @format:"#,##0.00" @locale:de-DE
+
+| name | formula | result |
+|------|---------|--------|
+| revenue | 1234567.891 | |
+| margin | 0.1234 @format:percent | |
+| units | 42 @decimals:0 | |
+| loss | -1500 @format:accounting @locale:en-US | |
+
+## Plain @decimals:2
+
+| name | formula | result |
+|------|---------|--------|
+| ratio | 1 / 3 | |
+"##;
+        let result = folio.eval(doc, &HashMap::new());
+        assert!(result.errors.is_empty(), "errors: {:?}", result.errors);
+
+        assert!(result.markdown.contains("| revenue | 1234567.891 | 1.234.567,89 |"), "got:\n{}", result.markdown);
+        assert!(result.markdown.contains("| margin | 0.1234 @format:percent | 12,34% |"), "got:\n{}", result.markdown);
+        assert!(result.markdown.contains("| units | 42 @decimals:0 | 42 |"), "got:\n{}", result.markdown);
+        assert!(result.markdown.contains("| loss | -1500 @format:accounting @locale:en-US | (1,500.00) |"), "got:\n{}", result.markdown);
+        assert!(result.markdown.contains("| ratio | 1 / 3 | 0.33 |"), "got:\n{}", result.markdown);
+
+        // Annotations don't change the computed value
+        assert_eq!(result.values.get("margin").unwrap().as_number().unwrap().to_f64(), Some(0.1234));
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let folio = test_folio();
+        let doc = r#"# Quarterly Model
+
+Intro paragraph with **markdown**.
+
+## Inputs @sigfigs:3 @precision:40 @locale:en-US
+
+| Name | Formula | Unit | Result | Notes |
+|:-----|--------:|------|:------:|-------|
+| price | 12.5 | EUR | | list price |
+| qty | 4 | pcs | | |
+
+Some prose between tables.
+
+```
+| not | a | table |
+```
+
+## Totals
+
+| name | formula |
+|------|---------|
+| total | price * qty |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+        assert!(result.errors.is_empty(), "errors: {:?}", result.errors);
+
+        let expected = r#"# Quarterly Model
+
+Intro paragraph with **markdown**.
+
+## Inputs @sigfigs:3 @precision:40 @locale:en-US
+
+| Name | Formula | Unit | Result | Notes |
+| :----- | --------: | ------ | :------: | ------- |
+| price | 12.5 | EUR | 12.5 | list price |
+| qty | 4 | pcs | 4.00 | |
+
+Some prose between tables.
+
+```
+| not | a | table |
+```
+
+## Totals
+
+| name | formula | result |
+| ------ | --------- | -------- |
+| total | price * qty | 50.0000000000 |
+"#;
+        assert_eq!(result.markdown, expected);
+
+        // Rendering is deterministic
+        assert_eq!(folio.eval(doc, &HashMap::new()).markdown, result.markdown);
+    }
+
+    #[test]
+    fn test_render_keeps_value_column() {
+        let folio = test_folio();
+
+        // `value` holds formulas like `formula` does; results get their own column
+        let doc = "| name | value |\n|---|---|\n| a | 5 | \n| b | a * 2 |\n";
+        let md = folio.eval(doc, &HashMap::new()).markdown;
+        assert!(md.contains("| name | value | result |\n"), "{}", md);
+        assert!(md.contains("| b | a * 2 | 10.0000000000 |\n"), "{}", md);
+
+        let doc = "| name | value | result |\n|---|---|---|\n| a | 5 | |\n| b | a * 2 | |\n";
+        let md = folio.eval(doc, &HashMap::new()).markdown;
+        assert!(md.contains("| b | a * 2 | 10.0000000000 |\n"), "{}", md);
+
+        // A `result` header at or before the formula column is just a name
+        let doc = "| result | formula |\n|---|---|\n| a | 5 |\n";
+        let md = folio.eval(doc, &HashMap::new()).markdown;
+        assert!(md.contains("| result | formula | result |\n| --- | --- | -------- |\n| a | 5 | 5.0000000000 |\n"), "{}", md);
+    }
+
+    #[test]
+    #[cfg(feature = "matrix")]
+    fn test_render_structured_values() {
+        let folio = test_folio();
+        let doc = r#"
+## Data @decimals:0 @listMax:4 @expand:M,stats
+
+| name | formula | result |
+|------|---------|--------|
+| short | [1, 2, 3] | |
+| long | [1, 2, 3, 4, 5, 6] | |
+| M | matrix([[1, 2], [3, 4]]) | |
+| stats | {n: 2, label: "ab"} | |
+
+Trailing prose.
+
+## Latex @decimals:0 @matrix:latex @expand:all
+
+| name | formula | result |
+|------|---------|--------|
+| I | identity(2) | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+        assert!(result.errors.is_empty(), "errors: {:?}", result.errors);
+        let md = &result.markdown;
+
+        assert!(md.contains("| short | [1, 2, 3] | [1, 2, 3] |"), "got:\n{}", md);
+        assert!(md.contains("| long | [1, 2, 3, 4, 5, 6] | [1, 2, 3, 4, …] (6 items) |"), "got:\n{}", md);
+        assert!(md.contains("| M | matrix([[1, 2], [3, 4]]) | [[1, 2], [3, 4]] |"), "got:\n{}", md);
+
+        let matrix_table = "\n**M**\n\n| | 0 | 1 |\n| --- | ---: | ---: |\n| **0** | 1 | 2 |\n| **1** | 3 | 4 |\n";
+        assert!(md.contains(matrix_table), "got:\n{}", md);
+        let object_table = "\n**stats**\n\n| field | value |\n|-------|-------|\n| label | ab |\n| n | 2 |\n";
+        assert!(md.contains(object_table), "got:\n{}", md);
+
+        // Expansions come after the table and before the prose that follows it
+        assert!(md.find("**stats**").unwrap() < md.find("Trailing prose.").unwrap());
+
+        assert!(md.contains("$$\n\\begin{pmatrix}\n1 & 0 \\\\\n0 & 1\n\\end{pmatrix}\n$$\n"), "got:\n{}", md);
+    }
+
     #[test]
     fn test_physics_constants() {
         // Test that physics constants (m_e, m_mu, etc.) now work correctly
@@ -687,6 +934,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "stats")]
     fn test_list_literals() {
         let folio = test_folio();
         let doc = r#"
@@ -728,6 +976,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "stats")]
     fn test_long_list_literals() {
         let folio = test_folio();
         let doc = r#"
@@ -767,6 +1016,186 @@ mod tests {
     }
 
     #[test]
+    fn test_list_broadcasting() {
+        let folio = test_folio();
+        let doc = r#"
+## Broadcasting @precision:10
+
+| name | formula | result |
+|------|---------|--------|
+| prices | [10, 20, 30] | |
+| quantities | [1, 2, 3] | |
+| doubled | prices * 2 | |
+| offset | 100 - prices | |
+| totals | prices * quantities | |
+| stretched | [5] + quantities | |
+| nested | [[1, 2], [3, 4]] * 10 | |
+| expensive | prices > 15 | |
+| picked | filter(prices, prices > 15) | |
+| mismatch | prices + [1, 2] | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+
+        let numbers = |name: &str| -> Vec<i64> {
+            let list = result.values.get(name).unwrap().as_list()
+                .unwrap_or_else(|| panic!("{} should be a list, got {:?}", name, result.values.get(name)));
+            list.iter().map(|v| v.as_number().unwrap().to_i64().unwrap()).collect()
+        };
+
+        assert_eq!(numbers("doubled"), vec![20, 40, 60]);
+        assert_eq!(numbers("offset"), vec![90, 80, 70]);
+        assert_eq!(numbers("totals"), vec![10, 40, 90]);
+        assert_eq!(numbers("stretched"), vec![6, 7, 8]);
+        assert_eq!(numbers("picked"), vec![20, 30]);
+
+        let nested = result.values.get("nested").unwrap().as_list().unwrap();
+        assert_eq!(nested[1].as_list().unwrap()[0].as_number().unwrap().to_i64(), Some(30));
+
+        let mask: Vec<bool> = result.values.get("expensive").unwrap().as_list().unwrap()
+            .iter().map(|v| v.as_bool().unwrap()).collect();
+        assert_eq!(mask, vec![false, true, true]);
+
+        match result.values.get("mismatch").unwrap() {
+            Value::Error(e) => {
+                assert_eq!(e.code, "SHAPE_MISMATCH");
+                assert!(e.message.contains("length 3 and 2"), "got: {}", e.message);
+            }
+            other => panic!("expected shape mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "matrix")]
+    fn test_indexing_and_slicing() {
+        let folio = test_folio();
+        let doc = r#"
+## Indexing @precision:10
+
+| name | formula | result |
+|------|---------|--------|
+| xs | [10, 20, 30, 40] | |
+| first | xs[0] | |
+| last | xs[-1] | |
+| middle | xs[1:3] | |
+| tail_part | xs[2:] | |
+| head_part | xs[:-3] | |
+| masked | xs[xs > 15] | |
+| picked | xs[[0, 2]] | |
+| word | "folio" | |
+| letter | word[1] | |
+| stem | word[0:3] | |
+| nested | [[1, 2], [3, 4]] | |
+| inner | nested[1, 0] | |
+| inner_col | nested[:, 1] | |
+| M | matrix([[1, 2, 3], [4, 5, 6]]) | |
+| m12 | M[1, 2] | |
+| row0 | M[0] | |
+| col1 | M[:, 1] | |
+| sub | M[:, 1:] | |
+| call_index | head(xs, 2)[1] | |
+| oob | xs[4] | |
+| oob_neg | xs[-5] | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+
+        let num = |name: &str| -> i64 {
+            let v = result.values.get(name).unwrap();
+            v.as_number().unwrap_or_else(|| panic!("{} should be a number, got {:?}", name, v)).to_i64().unwrap()
+        };
+        let nums = |name: &str| -> Vec<i64> {
+            let v = result.values.get(name).unwrap();
+            v.as_list().unwrap_or_else(|| panic!("{} should be a list, got {:?}", name, v))
+                .iter().map(|x| x.as_number().unwrap().to_i64().unwrap()).collect()
+        };
+        let text = |name: &str| -> String {
+            result.values.get(name).unwrap().as_text().unwrap().to_string()
+        };
+
+        assert_eq!(num("first"), 10);
+        assert_eq!(num("last"), 40);
+        assert_eq!(nums("middle"), vec![20, 30]);
+        assert_eq!(nums("tail_part"), vec![30, 40]);
+        assert_eq!(nums("head_part"), vec![10]);
+        assert_eq!(nums("masked"), vec![20, 30, 40]);
+        assert_eq!(nums("picked"), vec![10, 30]);
+        assert_eq!(text("letter"), "o");
+        assert_eq!(text("stem"), "fol");
+        assert_eq!(num("inner"), 3);
+        assert_eq!(nums("inner_col"), vec![2, 4]);
+        assert_eq!(num("m12"), 6);
+        assert_eq!(nums("row0"), vec![1, 2, 3]);
+        assert_eq!(nums("col1"), vec![2, 5]);
+        assert_eq!(num("call_index"), 20);
+
+        let sub = folio_matrix::extract_matrix(result.values.get("sub").unwrap(), "test", "sub").unwrap();
+        assert_eq!((sub.rows(), sub.cols()), (2, 2));
+        assert_eq!(sub.get(1, 1).unwrap().to_i64(), Some(6));
+
+        for name in ["oob", "oob_neg"] {
+            match result.values.get(name).unwrap() {
+                Value::Error(e) => assert!(e.message.contains("list of length 4"), "got: {}", e.message),
+                other => panic!("{} should be an out-of-bounds error, got {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_record_literals() {
+        let folio = test_folio();
+        let doc = r#"
+## Records @precision:10
+
+| name | formula | result |
+|------|---------|--------|
+| price | 20 | |
+| qty | 3 | |
+| order | {price, qty, total: price * qty, tags: ["a", "b"]} | |
+| total | order.total | |
+| by_key | order["qty"] | |
+| nested | {inner: {x: 1 + 1}}.inner | |
+| updated | merge(order, {qty: 5}) | |
+| ks | keys({b: 2, a: 1}) | |
+| vs | values({b: 2, a: 1}) | |
+| es | entries({b: 2, a: 1}) | |
+| empty | {} | |
+| broken | {ok: 1, bad: 1 / 0} | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+
+        let order = result.values.get("order").unwrap().as_object()
+            .unwrap_or_else(|| panic!("order should be an object, got {:?}", result.values.get("order")));
+        assert_eq!(order.len(), 4);
+        assert_eq!(order["price"].as_number().unwrap().to_i64(), Some(20));
+        assert_eq!(result.values.get("total").unwrap().as_number().unwrap().to_i64(), Some(60));
+        assert_eq!(result.values.get("by_key").unwrap().as_number().unwrap().to_i64(), Some(3));
+
+        let updated = result.values.get("updated").unwrap().as_object().unwrap();
+        assert_eq!(updated["qty"].as_number().unwrap().to_i64(), Some(5));
+        assert_eq!(updated["price"].as_number().unwrap().to_i64(), Some(20));
+
+        let ks: Vec<&str> = result.values.get("ks").unwrap().as_list().unwrap()
+            .iter().map(|v| v.as_text().unwrap()).collect();
+        assert_eq!(ks, vec!["a", "b"]);
+        let vs: Vec<i64> = result.values.get("vs").unwrap().as_list().unwrap()
+            .iter().map(|v| v.as_number().unwrap().to_i64().unwrap()).collect();
+        assert_eq!(vs, vec![1, 2]);
+        let es = result.values.get("es").unwrap().as_list().unwrap();
+        assert_eq!(es[1].as_list().unwrap()[0].as_text(), Some("b"));
+
+        assert!(result.values.get("empty").unwrap().as_object().unwrap().is_empty());
+
+        match result.values.get("broken").unwrap() {
+            Value::Error(e) => {
+                assert_eq!(e.code, "DIV_ZERO");
+                let notes = &e.context.as_ref().unwrap().notes;
+                assert!(notes.iter().any(|n| n.contains("record field 'bad'")), "notes: {:?}", notes);
+            }
+            other => panic!("expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
     fn test_comparison_operators() {
         let folio = test_folio();
         let doc = r#"
@@ -840,6 +1269,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "matrix")]
     fn test_matrix_operations() {
         let folio = test_folio();
         let doc = r#"
@@ -905,6 +1335,97 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "matrix")]
+    fn test_matrix_operators() {
+        let folio = test_folio();
+        let doc = r#"
+## Matrix Operators @precision:10
+
+| name | formula | result |
+|------|---------|--------|
+| A | matrix([[1, 2], [3, 4]]) | |
+| B | matrix([[5, 6], [7, 8]]) | |
+| C | matrix([[1, 2, 3], [4, 5, 6]]) | |
+| product | A * B | |
+| sum | A + B | |
+| scaled | 2 * A | |
+| halved | A / 2 | |
+| hadamard | A .* B | |
+| transposed | C^T | |
+| squared | A^2 | |
+| mv | A * [1, 1] | |
+| bad | C * C | |
+| scalar_elem | 6 ./ 3 | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+
+        let entry = |name: &str, row: usize, col: usize| -> f64 {
+            let m = folio_matrix::extract_matrix(result.values.get(name).unwrap(), "test", name)
+                .unwrap_or_else(|e| panic!("{} should be a matrix: {:?}", name, e));
+            m.get(row, col).unwrap().to_f64().unwrap()
+        };
+
+        assert_eq!(entry("product", 0, 0), 19.0);
+        assert_eq!(entry("product", 1, 1), 50.0);
+        assert_eq!(entry("sum", 1, 0), 10.0);
+        assert_eq!(entry("scaled", 1, 1), 8.0);
+        assert_eq!(entry("halved", 0, 1), 1.0);
+        assert_eq!(entry("hadamard", 1, 0), 21.0);
+        assert_eq!(entry("transposed", 2, 1), 6.0);
+        assert_eq!(entry("squared", 0, 0), 7.0);
+
+        let mv = result.values.get("mv").unwrap().as_list().expect("M * v should be a list");
+        let mv: Vec<f64> = mv.iter().map(|x| x.as_number().unwrap().to_f64().unwrap()).collect();
+        assert_eq!(mv, vec![3.0, 7.0]);
+
+        match result.values.get("bad").unwrap() {
+            Value::Error(e) => {
+                assert_eq!(e.code, "SHAPE_MISMATCH");
+                assert!(e.message.contains("2×3 * 2×3"), "got: {}", e.message);
+            }
+            other => panic!("expected shape mismatch, got {:?}", other),
+        }
+
+        let scalar = result.values.get("scalar_elem").unwrap();
+        assert_eq!(scalar.as_number().unwrap().to_i64(), Some(2));
+    }
+
+    #[test]
+    #[cfg(feature = "matrix")]
+    fn test_transpose_postfix() {
+        let folio = test_folio();
+        let doc = r#"
+## Growth @precision:10
+
+| name | formula | result |
+|------|---------|--------|
+| r | 0.05 | |
+| T | 10 | |
+| grown | (1 + r)^(T) | |
+| C | matrix([[1, 2, 3], [4, 5, 6]]) | |
+| quoted | C' | |
+| hatted | C^T | |
+| product | (C * C')[1, 0] | |
+| label | 'C' | |
+| number | (1 + r)' | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+        let grown = result.values["grown"].as_number().unwrap().to_f64().unwrap();
+        assert!((grown - 1.628894627).abs() < 1e-9, "{:?}", result.values["grown"]);
+
+        // A cell named T doesn't turn ^T into a power
+        for name in ["quoted", "hatted"] {
+            let m = folio_matrix::extract_matrix(&result.values[name], "test", name)
+                .unwrap_or_else(|e| panic!("{} should be a matrix: {:?}", name, e));
+            assert_eq!((m.rows(), m.cols()), (3, 2), "{}", name);
+        }
+        assert_eq!(result.values["product"].as_number().unwrap().to_i64(), Some(32));
+        assert_eq!(result.values["label"].as_text(), Some("C"));
+        assert!(matches!(&result.values["number"], Value::Error(e) if e.code == "TYPE_ERROR"), "{:?}", result.values["number"]);
+    }
+
+    #[test]
+    #[cfg(feature = "matrix")]
     fn test_matrix_decompositions() {
         let folio = test_folio();
         let doc = r#"
@@ -966,6 +1487,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "finance")]
     fn test_finance_functions() {
         let folio = test_folio();
         let doc = r#"
@@ -1035,6 +1557,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "units")]
     fn test_unit_conversions() {
         let folio = test_folio();
         let doc = r#"
@@ -1102,6 +1625,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "matrix")]
     fn test_matrix_example_file() {
         // Test that the matrix_test.fmd example file evaluates without errors
         let content = include_str!("../../examples/matrix_test.fmd");
@@ -1175,6 +1699,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "text")]
     fn test_quoted_string_literals() {
         // Test that quoted string literals are properly handled when stored as cell values
         let folio = test_folio();
@@ -1229,4 +1754,426 @@ mod tests {
         assert!(!ends_world.is_error(), "ends_with should work, got: {:?}", ends_world);
         assert_eq!(ends_world.as_bool(), Some(true), "should end with 'World'");
     }
+
+    #[derive(Debug)]
+    struct Cents(i64);
+
+    impl folio_core::FolioExtValue for Cents {
+        fn type_name(&self) -> &'static str { "Cents" }
+        fn display(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}¢", self.0)
+        }
+        fn to_json(&self) -> serde_json::Value { serde_json::json!(self.0) }
+        fn field(&self, name: &str) -> Option<Value> {
+            (name == "amount").then(|| Value::from(self.0))
+        }
+        fn field_names(&self) -> Vec<String> { vec!["amount".to_string()] }
+        fn binary_op(&self, op: folio_core::ExtOp, rhs: &Value) -> Option<Value> {
+            match (op, rhs) {
+                (folio_core::ExtOp::Add, Value::Custom(_)) => {
+                    rhs.as_custom::<Cents>().map(|c| Value::custom(Cents(self.0 + c.0)))
+                }
+                (folio_core::ExtOp::Mul, Value::Number(n)) => {
+                    n.to_i64().map(|k| Value::custom(Cents(self.0 * k)))
+                }
+                _ => None,
+            }
+        }
+        fn binary_op_rhs(&self, op: folio_core::ExtOp, lhs: &Value) -> Option<Value> {
+            // Multiplication by a scalar commutes
+            self.binary_op(op, lhs)
+        }
+        fn neg(&self) -> Option<Value> { Some(Value::custom(Cents(-self.0))) }
+        fn as_any(&self) -> &dyn std::any::Any { self }
+    }
+
+    struct CentsFn;
+
+    impl folio_plugin::FunctionPlugin for CentsFn {
+        fn meta(&self) -> folio_plugin::FunctionMeta {
+            folio_plugin::FunctionMeta {
+                name: "cents", description: "Test extension value", usage: "cents(n)",
+                args: &[], returns: "Cents", examples: &[],
+                category: "test", source: None, related: &[],
+            }
+        }
+
+        fn call(&self, args: &[Value], _ctx: &EvalContext) -> Value {
+            match args.first().and_then(|v| v.as_number()).and_then(|n| n.to_i64()) {
+                Some(n) => Value::custom(Cents(n)),
+                None => Value::Error(folio_core::FolioError::arg_type("cents", "n", "Number", "other")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_extension_values() {
+        let folio = Folio::new(folio_std::standard_registry().with_function(CentsFn));
+        let doc = r#"
+## Ext Test
+| name | formula | result |
+|------|---------|--------|
+| a | cents(150) | |
+| b | cents(50) | |
+| total | a + b | |
+| doubled | 2 * a | |
+| amount | total.amount | |
+| kind | typeof(a) | |
+| bad | a - b | |
+| missing | a.nope | |
+| called | cents(1).nope | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+
+        let total = result.values.get("total").unwrap();
+        assert_eq!(total.as_custom::<Cents>().map(|c| c.0), Some(200), "got: {:?}", total);
+        let doubled = result.values.get("doubled").unwrap();
+        assert_eq!(doubled.as_custom::<Cents>().map(|c| c.0), Some(300), "got: {:?}", doubled);
+        let amount = result.values.get("amount").unwrap();
+        assert_eq!(amount.as_number().unwrap().to_i64(), Some(200));
+        assert_eq!(result.values.get("kind").unwrap().as_text(), Some("Cents"));
+
+        // Operators the type does not overload are TYPE_ERRORs
+        let bad = result.values.get("bad").unwrap();
+        assert!(matches!(bad, Value::Error(e) if e.code == "TYPE_ERROR"), "got: {:?}", bad);
+        let missing = result.values.get("missing").unwrap();
+        assert!(matches!(missing, Value::Error(e) if e.code == "UNDEFINED_FIELD"), "got: {:?}", missing);
+        let called = result.values.get("called").unwrap();
+        assert!(matches!(called, Value::Error(e) if e.code == "UNDEFINED_FIELD"), "got: {:?}", called);
+
+        // Renderer uses the extension's display
+        assert!(result.markdown.contains("| 200¢ |"), "{}", result.markdown);
+    }
+
+    #[test]
+    fn test_eval_json() {
+        let folio = test_folio();
+        let doc = r#"
+## Totals @precision:20
+
+| name | formula | result |
+|------|---------|--------|
+| a | 1 | |
+| b | 3 | |
+| c | a / b @decimals:2 | |
+| d | c / 0 | |
+| v | [a, b] | |
+"#;
+        let json = folio.eval_json(doc, &vars! { rate: 2 });
+        assert_eq!(json["schema"], JSON_SCHEMA_VERSION);
+
+        let section = &json["sections"][0];
+        assert_eq!(section["name"], "Totals");
+        assert_eq!(section["attributes"]["precision"], "20");
+
+        let c = &section["cells"][2];
+        assert_eq!(c["name"], "c");
+        assert_eq!(c["formula"], "a / b");
+        assert_eq!(c["depends_on"], serde_json::json!(["a", "b"]));
+        assert_eq!(c["attributes"]["decimals"], "2");
+        assert_eq!(c["display"], "0.33");
+        assert_eq!(c["value"]["type"], "number");
+        assert_eq!(c["value"]["precision"], 20);
+        assert!(c["value"]["value"].as_str().unwrap().starts_with("0.33333333333333"), "{}", c);
+
+        // Literal cells have no formula
+        assert!(section["cells"][0]["formula"].is_null());
+        assert_eq!(section["cells"][4]["value"]["items"][1]["value"], "3");
+
+        // Errors carry the cell they came from
+        let d = &section["cells"][3];
+        assert_eq!(d["value"]["type"], "error");
+        assert_eq!(d["error"]["code"], "DIV_ZERO");
+        assert_eq!(d["error"]["context"]["cell"], "d");
+        assert_eq!(json["errors"][0]["context"]["formula"], "c / 0");
+
+        let edges = json["dependencies"].as_array().unwrap();
+        assert!(edges.contains(&serde_json::json!({ "from": "d", "to": "c" })));
+        assert_eq!(edges.len(), 5);
+        assert_eq!(json["external"]["rate"]["value"], "2");
+
+        // Rendering an existing evaluation gives the same output
+        let parsed = Document::parse(doc).unwrap();
+        let result = folio.eval_document(&parsed, &vars! { rate: 2 });
+        assert_eq!(folio.render_json(&parsed, &result), json);
+
+        let broken = folio.eval_json("| a | b |\n|---|---|\n| x | (1 + |\n", &HashMap::new());
+        assert_eq!(broken["schema"], JSON_SCHEMA_VERSION);
+        assert_eq!(broken["errors"][0]["code"], "PARSE_ERROR");
+        assert!(broken["errors"][0]["message"].as_str().unwrap().contains("Unclosed '('"), "{}", broken);
+    }
+
+    #[test]
+    fn test_html_and_latex_renderers() {
+        let folio = test_folio();
+        let doc = r#"
+# Loan @sigfigs:4
+
+Monthly payment for a **fixed** rate.
+
+| name | formula | result |
+|------|---------|-------:|
+| r | 0.005 | |
+| n | 360 | |
+| growth | (1 + r) ^ n | |
+| bad | r / 0 | |
+"#;
+        let html = folio.eval_with(doc, &HashMap::new(), &HtmlRenderer::new());
+        assert!(html.starts_with("<!DOCTYPE html>"), "{}", html);
+        assert!(html.contains("<title>Loan</title>"), "{}", html);
+        assert!(html.contains("<h1>Loan</h1>"), "{}", html);
+        assert!(html.contains("<p>Monthly payment for a <strong>fixed</strong> rate.</p>"), "{}", html);
+        assert!(html.contains("<td class=\"result\" style=\"text-align: right\" title=\"= (1 + r) ^ n\">6.023</td>"), "{}", html);
+        assert!(html.contains("<td class=\"result error\" style=\"text-align: right\" title=\"DIV_ZERO: "), "{}", html);
+
+        let latex = folio.eval_with(doc, &HashMap::new(), &LatexRenderer::new());
+        assert!(latex.starts_with("\\section*{Loan}\n\nMonthly payment for a \\textbf{fixed} rate.\n"), "{}", latex);
+        assert!(latex.contains("\\begin{tabular}{llr}\n\\hline\nname & formula & result \\\\\n\\hline\n"), "{}", latex);
+        assert!(latex.contains("growth & ${\\left(1 + r\\right)}^{n}$ & 6.023 \\\\"), "{}", latex);
+        assert!(latex.contains("bad & $\\frac{r}{0}$ & \\textbf{\\#ERROR: DIV\\_ZERO} \\\\"), "{}", latex);
+        assert!(latex.contains("\\hline\n\\end{tabular}"), "{}", latex);
+
+        let standalone = folio.eval_with(doc, &HashMap::new(), &LatexRenderer::new().standalone(true));
+        assert!(standalone.starts_with("\\documentclass{article}"));
+        assert!(standalone.ends_with("\\end{document}\n"));
+
+        let broken = folio.eval_with("| a | b |\n|---|---|\n| x | ={a: 1, a: 2} |\n", &HashMap::new(), &HtmlRenderer::new());
+        assert!(broken.contains("<h1>Parse Error</h1>"), "{}", broken);
+    }
+
+    #[test]
+    fn test_typeset_formulas() {
+        let folio = test_folio();
+        let doc = r#"
+## Loan @formula:latex
+
+| name | formula | result |
+|------|---------|--------|
+| P | 300000 | |
+| r | 0.005 | |
+| n | 360 | |
+| payment | P * r * (1 + r) ^ n / ((1 + r) ^ n - 1) | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+        assert!(result.markdown.contains("| name | formula | result | math |"), "{}", result.markdown);
+        assert!(result.markdown.contains(
+            "| payment | P * r * (1 + r) ^ n / ((1 + r) ^ n - 1) | 1798.6515754583 | $\\frac{P \\cdot r \\cdot {\\left(1 + r\\right)}^{n}}{{\\left(1 + r\\right)}^{n} - 1}$ |"
+        ), "{}", result.markdown);
+        // Literal cells leave the column empty
+        assert!(result.markdown.contains("| P | 300000 | 300000.0000000000 | |"), "{}", result.markdown);
+
+        let mathml = folio.eval(&doc.replace("@formula:latex", "@formula:mathml"), &HashMap::new());
+        assert!(mathml.markdown.contains("<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mfrac>"), "{}", mathml.markdown);
+
+        // EXPLAIN reports the typeset formula from the trace
+        let parsed = parser::parse(doc).unwrap();
+        let registry = folio.registry.clone();
+        let mut ctx = EvalContext::new(registry.clone()).with_tracing(true);
+        Evaluator::new().eval(&parsed, &mut ctx);
+        let explain = registry.get_command("EXPLAIN").unwrap();
+        let explained = explain.execute(&[Value::Text("payment".to_string())], &mut ctx);
+        assert_eq!(explained.get("formula").as_text(), Some("P * r * (1 + r) ^ n / ((1 + r) ^ n - 1)"));
+        assert!(explained.get("latex").as_text().unwrap().starts_with("\\frac{P \\cdot r"), "{:?}", explained);
+        assert!(explained.get("mathml").as_text().unwrap().starts_with("<math"), "{:?}", explained);
+    }
+
+    #[test]
+    fn test_show_work() {
+        let folio = test_folio();
+        let doc = r#"
+## Loan @decimals:2 @showWork:true
+
+| name | formula | result |
+|------|---------|--------|
+| P | 300000 | |
+| r | 0.0054 | |
+| n | 360 | |
+| payment | P * r * (1 + r) ^ n / ((1 + r) ^ n - 1) | |
+| area | pi * (0 - n) ^ 2 | |
+| rm | 0.065 / 12 | |
+| interest | P * rm | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+        assert!(result.markdown.contains("| name | formula | result | work |"), "{}", result.markdown);
+        // Computed inputs keep their digits rather than the section's two decimals
+        assert!(result.markdown.contains("| interest | P * rm | 1625.00 | 300000 × 0.00541666666666667 = 1625.00 |"), "{}", result.markdown);
+        assert!(result.markdown.contains(
+            "| payment | P * r * (1 + r) ^ n / ((1 + r) ^ n - 1) | 1892.26 | 300000 × 0.0054 × (1 + 0.0054)^360 / ((1 + 0.0054)^360 − 1) = 1892.26 |"
+        ), "{}", result.markdown);
+        // Constants stay symbolic
+        assert!(result.markdown.contains("| area | pi * (0 - n) ^ 2 | 407150.41 | pi × (0 − 360)^2 = 407150.41 |"), "{}", result.markdown);
+        assert!(result.markdown.contains("| P | 300000 | 300000.00 | |"), "{}", result.markdown);
+
+        // EXPLAIN shows the same line from the trace
+        let parsed = parser::parse(&doc.replace("@decimals:2", "@format:#,##0.####")).unwrap();
+        let mut ctx = EvalContext::new(folio.registry.clone()).with_tracing(true);
+        Evaluator::new().eval(&parsed, &mut ctx);
+        let explained = folio.registry.get_command("EXPLAIN").unwrap()
+            .execute(&[Value::Text("payment".to_string())], &mut ctx);
+        assert_eq!(
+            explained.get("work").as_text(),
+            Some("payment = 300000 × 0.0054 × (1 + 0.0054)^360 / ((1 + 0.0054)^360 − 1) = 1,892.2599")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "matrix")]
+    fn test_format_document_keeps_results() {
+        let folio = test_folio();
+        let doc = r#"
+# Loan @sigfigs:6 @precision:30
+
+| name | formula | result |
+|---|---|---|
+| P | 300000 | |
+| r | 0.0054 | |
+| n | 360 | |
+| payment | P*r*(1+r)^n/(((1+r)^n)-1) | |
+| total | =payment*n @format:#,##0.00 | |
+| m | [[1,2],[3,4]] | |
+| mt | (matrix(m)^T)[0] | |
+| big | total>(P*2) | |
+"#;
+        let formatted = format_document(doc).unwrap();
+        assert!(formatted.starts_with("## Loan @precision:30 @sigfigs:6
+"), "{}", formatted);
+        assert!(formatted.contains("| payment | P * r * (1 + r)^n / ((1 + r)^n - 1) |"), "{}", formatted);
+        assert!(formatted.contains("| total   | payment * n @format:#,##0.00        |"), "{}", formatted);
+        assert!(formatted.contains("| mt      | (matrix(m)')[0]                     |"), "{}", formatted);
+        assert_eq!(format_document(&formatted).unwrap(), formatted);
+
+        let before = folio.eval(doc, &HashMap::new());
+        let after = folio.eval(&formatted, &HashMap::new());
+        assert!(after.errors.is_empty(), "{:?}", after.errors);
+        for (name, value) in &before.values {
+            assert_eq!(format!("{}", value), format!("{}", after.values[name]), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let folio = test_folio();
+        let old = r#"
+## Loan
+
+| name | formula | result |
+|------|---------|--------|
+| principal | 1000 | |
+| rate | 0.05 | |
+| interest | principal * rate | |
+| total | principal + interest | |
+| fee | 10 | |
+| label | "loan" | |
+"#;
+        let new = old
+            .replace("| rate | 0.05 |", "| rate | 0.06 |")
+            .replace("| fee | 10 | |\n", "")
+            .replace("| label | \"loan\" | |", "| label | \"loan\" | |\n| net | total * (1 - tax) | |");
+        let diff = folio.diff(old, &new, &vars! { tax: 0 });
+
+        let rate = diff.get("rate").unwrap();
+        assert_eq!(rate.kind, ChangeKind::Changed);
+        assert_eq!(rate.cause, Cause::Formula);
+        let num = |s: &str| Some(folio_core::Number::from_str(s).unwrap());
+        assert_eq!(rate.delta, num("0.01"));
+        assert_eq!(rate.relative, num("0.2"));
+
+        // total moved only because interest did, which moved because of rate
+        let total = diff.get("total").unwrap();
+        assert_eq!(total.cause, Cause::Upstream(vec!["rate".to_string()]));
+        assert_eq!(total.delta, num("10"));
+
+        assert_eq!(diff.get("fee").unwrap().kind, ChangeKind::Removed);
+        assert_eq!(diff.get("net").unwrap().kind, ChangeKind::Added);
+        assert!(diff.get("principal").is_none());
+        assert!(diff.get("label").is_none());
+
+        let table = diff.to_markdown();
+        assert!(table.starts_with("| cell | change | old | new | delta | relative | cause |\n"), "{}", table);
+        assert!(table.contains("| rate | changed | 0.05 | 0.06 | +0.01 | +20.00% | formula |"), "{}", table);
+        assert!(table.contains("| interest | changed | 50 | 60 | +10 | +20.00% | upstream: rate |"), "{}", table);
+        assert!(table.contains("| fee | removed | 10 | | | | formula |"), "{}", table);
+
+        // Same document, different inputs
+        let a = folio.eval(&new, &vars! { tax: 0 });
+        let b = folio.eval(&new, &vars! { tax: 1 });
+        let diff = EvalDiff::between(&a, &b);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].cause, Cause::Upstream(vec!["tax".to_string()]));
+        assert!(diff.changes[0].relative.is_some());
+        assert!(EvalDiff::between(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_progress() {
+        let doc = r#"
+## Test
+| name | formula | result |
+|------|---------|--------|
+| a | 10 | |
+| b | a * 2 | |
+| c | b + a | |
+"#;
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let folio = test_folio().with_progress(move |p| sink.lock().unwrap().push((p.cell.clone(), p.done, p.total)));
+        folio.eval(doc, &HashMap::new());
+
+        let expected: Vec<(String, usize, usize)> = vec![("a".into(), 1, 3), ("b".into(), 2, 3), ("c".into(), 3, 3)];
+        assert_eq!(*seen.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let doc = r#"
+## Test
+| name | formula | result |
+|------|---------|--------|
+| a | 10 | |
+| b | sqrt(a) | |
+"#;
+        let token = CancelToken::new();
+        let folio = test_folio().with_cancel(token.clone());
+        assert!(folio.eval(doc, &HashMap::new()).values["b"].as_number().is_some());
+
+        token.cancel();
+        let result = folio.eval(doc, &HashMap::new());
+        match &result.values["b"] {
+            Value::Error(e) => assert_eq!(e.code, folio_core::codes::CANCELLED),
+            other => panic!("expected CANCELLED, got {:?}", other),
+        }
+    }
+
+    #[test]
+    // The histogram and Q-Q charts bin and rank with the stats library
+    #[cfg(all(feature = "chart", feature = "stats"))]
+    fn test_charts() {
+        let folio = test_folio();
+        let doc = r#"
+## Data
+
+| name | formula | result |
+|------|---------|--------|
+| data | [2.1, 3.4, 1.9, 5.6, 4.2, 3.3, 2.8, 4.9] | |
+| trend | sparkline(data) | |
+| hist | histogram_chart(data, 4) | |
+| hist_text | histogram_chart(histogram(data, 4), "text") | |
+| qq | qq_chart(data) | |
+| fit | scatter_chart([1, 2, 3, 4, 5, 6, 7, 8], data) | |
+"#;
+        let result = folio.eval(doc, &HashMap::new());
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(result.markdown.contains("| trend | sparkline(data) | ▁▄▁█▅▄▃▇ |"), "{}", result.markdown);
+        // Counts 3, 2, 1, 2
+        assert!(result.markdown.contains("| hist_text | histogram_chart(histogram(data, 4), \"text\") | █▆▃▆ |"), "{}", result.markdown);
+        for name in ["hist", "qq", "fit"] {
+            let line = result.markdown.lines().find(|l| l.starts_with(&format!("| {} |", name))).unwrap();
+            assert!(line.contains("<svg") && line.ends_with("</svg> |"), "{}", line);
+        }
+        assert!(result.markdown.contains("class=\"folio-chart qq\""));
+        assert!(result.markdown.contains("stroke-dasharray"));
+
+        // Same input, same bytes
+        assert_eq!(folio.eval(doc, &HashMap::new()).markdown, result.markdown);
+    }
 }
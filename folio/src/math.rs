@@ -0,0 +1,579 @@
+//! Typeset formulas as LaTeX or MathML
+//!
+//! Parentheses are only written where precedence needs them, division
+//! becomes a fraction, powers and indices become super- and subscripts,
+//! well-known functions use their math operators (`\sqrt`, `\ln`, `|x|`) and
+//! Greek names (`pi`, `phi`, `alpha`) become letters.
+
+use crate::ast::{BinOp, Expr, IndexArg, UnaryOp};
+
+impl Expr {
+    /// LaTeX math-mode source, without the surrounding `$`
+    pub fn to_latex(&self) -> String {
+        latex(self)
+    }
+
+    /// MathML presentation markup wrapped in an inline `<math>` element
+    pub fn to_mathml(&self) -> String {
+        format!("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>", mathml(self))
+    }
+}
+
+/// Binding strength, loosest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    Comparison,
+    Additive,
+    Multiplicative,
+    Prefix,
+    Power,
+    Atom,
+}
+
+fn op_prec(op: BinOp) -> Prec {
+    match op {
+        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge | BinOp::Eq | BinOp::Ne => Prec::Comparison,
+        BinOp::Add | BinOp::Sub => Prec::Additive,
+        BinOp::Mul | BinOp::Div | BinOp::ElemMul | BinOp::ElemDiv => Prec::Multiplicative,
+        BinOp::Pow => Prec::Power,
+    }
+}
+
+/// How tightly an expression holds together once typeset. A fraction is
+/// visually grouped, so it only needs parentheses as the base of a power.
+fn prec(expr: &Expr) -> Prec {
+    match expr {
+        Expr::BinaryOp(_, op, _) => op_prec(*op),
+        Expr::UnaryOp(UnaryOp::Neg, _) => Prec::Prefix,
+        Expr::Number(n) if n.starts_with('-') || n.contains(['e', 'E', '/']) => Prec::Prefix,
+        _ => Prec::Atom,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// With `fractions`, division is drawn as a stacked fraction and groups its
+/// operands by itself; otherwise it is an ordinary infix operator
+fn needs_parens(child: &Expr, op: BinOp, side: Side, fractions: bool) -> bool {
+    if fractions && matches!(op, BinOp::Div) {
+        return false;
+    }
+    let parent = op_prec(op);
+    let child_prec = prec(child);
+    if fractions && matches!(child, Expr::BinaryOp(_, BinOp::Div, _)) && parent != Prec::Power {
+        return false;
+    }
+    match side {
+        // Power is right-associative: (a^b)^c keeps its parentheses
+        Side::Left if parent == Prec::Power => child_prec <= Prec::Power,
+        Side::Left => child_prec < parent,
+        // a - (b + c), a ./ (b .* c); a + (-b) reads better than a + -b
+        Side::Right if parent == Prec::Power => !fractions && child_prec < Prec::Atom,
+        Side::Right => {
+            child_prec < parent
+                || (child_prec == parent && matches!(op, BinOp::Sub | BinOp::Div | BinOp::ElemDiv))
+                || child_prec == Prec::Prefix
+                || (parent == Prec::Comparison && child_prec == Prec::Comparison)
+        }
+    }
+}
+
+// ========== LaTeX ==========
+
+fn latex(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(n) => latex_number(n),
+        Expr::StringLiteral(s) => format!("\\text{{``{}''}}", latex_text(s)),
+        Expr::Variable(parts) => parts.iter().map(|p| latex_identifier(p)).collect::<Vec<_>>().join("."),
+        Expr::BinaryOp(left, op, right) => {
+            let side = |e: &Expr, s: Side| {
+                if needs_parens(e, *op, s, true) {
+                    format!("\\left({}\\right)", latex(e))
+                } else {
+                    latex(e)
+                }
+            };
+            match op {
+                BinOp::Div => format!("\\frac{{{}}}{{{}}}", latex(left), latex(right)),
+                BinOp::Pow => format!("{{{}}}^{{{}}}", side(left, Side::Left), latex(right)),
+                _ => format!("{} {} {}", side(left, Side::Left), latex_op(*op), side(right, Side::Right)),
+            }
+        }
+        Expr::UnaryOp(UnaryOp::Neg, inner) => {
+            if prec(inner) <= Prec::Prefix {
+                format!("-\\left({}\\right)", latex(inner))
+            } else {
+                format!("-{}", latex(inner))
+            }
+        }
+        Expr::UnaryOp(UnaryOp::Transpose, inner) => format!("{{{}}}^{{\\mathsf{{T}}}}", latex_postfix_base(inner)),
+        Expr::FunctionCall(name, args) => latex_function(name, args),
+        Expr::List(items) => format!("\\left[{}\\right]", latex_join(items)),
+        Expr::Record(fields) => format!(
+            "\\left\\{{{}\\right\\}}",
+            fields.iter()
+                .map(|(k, v)| format!("{}: {}", latex_identifier(k), latex(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::FieldAccess(base, fields) => format!(
+            "{}.{}",
+            latex_postfix_base(base),
+            fields.iter().map(|f| latex_identifier(f)).collect::<Vec<_>>().join(".")
+        ),
+        Expr::Index(base, args) => {
+            let args: Vec<String> = args.iter()
+                .map(|arg| match arg {
+                    IndexArg::At(e) => latex(e),
+                    IndexArg::Slice(start, end) => format!(
+                        "{}:{}",
+                        start.as_ref().map(latex).unwrap_or_default(),
+                        end.as_ref().map(latex).unwrap_or_default()
+                    ),
+                })
+                .collect();
+            format!("{{{}}}_{{{}}}", latex_postfix_base(base), args.join(", "))
+        }
+    }
+}
+
+fn latex_postfix_base(expr: &Expr) -> String {
+    if prec(expr) < Prec::Atom {
+        format!("\\left({}\\right)", latex(expr))
+    } else {
+        latex(expr)
+    }
+}
+
+fn latex_join(items: &[Expr]) -> String {
+    items.iter().map(latex).collect::<Vec<_>>().join(", ")
+}
+
+fn latex_op(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul | BinOp::Div => "\\cdot",
+        BinOp::ElemMul => "\\odot",
+        BinOp::ElemDiv => "\\oslash",
+        BinOp::Pow => "^",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "\\le",
+        BinOp::Ge => "\\ge",
+        BinOp::Eq => "=",
+        BinOp::Ne => "\\ne",
+    }
+}
+
+/// `1.5e-3` → `1.5 \times 10^{-3}`, `1/3` → `\frac{1}{3}`
+fn latex_number(n: &str) -> String {
+    if let Some((num, den)) = n.split_once('/') {
+        return format!("\\frac{{{}}}{{{}}}", num.trim(), den.trim());
+    }
+    match n.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => format!("{} \\times 10^{{{}}}", mantissa, exp.trim_start_matches('+')),
+        None => n.to_string(),
+    }
+}
+
+fn latex_function(name: &str, args: &[Expr]) -> String {
+    let lower = name.to_ascii_lowercase();
+    match (lower.as_str(), args) {
+        ("sqrt", [x]) => return format!("\\sqrt{{{}}}", latex(x)),
+        ("abs", [x]) => return format!("\\left\\lvert {} \\right\\rvert", latex(x)),
+        ("floor", [x]) => return format!("\\left\\lfloor {} \\right\\rfloor", latex(x)),
+        ("ceil", [x]) => return format!("\\left\\lceil {} \\right\\rceil", latex(x)),
+        ("exp", [x]) => return format!("e^{{{}}}", latex(x)),
+        _ => {}
+    }
+    let operator = match lower.as_str() {
+        "ln" | "log" | "sin" | "cos" | "tan" | "sinh" | "cosh" | "tanh" | "arcsin" | "arccos"
+        | "arctan" | "min" | "max" | "det" | "gcd" => format!("\\{}", lower),
+        "asin" | "acos" | "atan" => format!("\\arc{}", &lower[1..]),
+        _ => format!("\\operatorname{{{}}}", latex_text(name)),
+    };
+    format!("{}\\left({}\\right)", operator, latex_join(args))
+}
+
+/// Single letters stay italic, Greek names become letters, `x_1` gets a
+/// subscript and other names are set upright as one word
+fn latex_identifier(name: &str) -> String {
+    if let Some(greek) = greek(name) {
+        return greek.latex.to_string();
+    }
+    if let Some((base, sub)) = name.split_once('_') {
+        if !base.is_empty() && !sub.is_empty() && !sub.contains('_') {
+            return format!("{}_{{{}}}", latex_identifier(base), latex_identifier(sub));
+        }
+    }
+    if name.chars().count() == 1 {
+        name.to_string()
+    } else {
+        format!("\\mathrm{{{}}}", latex_text(name))
+    }
+}
+
+/// Escape text for `\text{}`/`\mathrm{}`; `|` would split a markdown table cell
+fn latex_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '|' => out.push_str("\\textbar{}"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+// ========== MathML ==========
+
+fn mathml(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(n) => mathml_number(n),
+        Expr::StringLiteral(s) => format!("<mtext>\"{}\"</mtext>", xml_escape(s)),
+        Expr::Variable(parts) => mathml_dotted(parts),
+        Expr::BinaryOp(left, op, right) => {
+            let side = |e: &Expr, s: Side| {
+                if needs_parens(e, *op, s, true) {
+                    mathml_parens(&mathml(e))
+                } else {
+                    mathml(e)
+                }
+            };
+            match op {
+                BinOp::Div => format!("<mfrac><mrow>{}</mrow><mrow>{}</mrow></mfrac>", mathml(left), mathml(right)),
+                BinOp::Pow => format!("<msup><mrow>{}</mrow><mrow>{}</mrow></msup>", side(left, Side::Left), mathml(right)),
+                _ => format!("<mrow>{}<mo>{}</mo>{}</mrow>", side(left, Side::Left), mathml_op(*op), side(right, Side::Right)),
+            }
+        }
+        Expr::UnaryOp(UnaryOp::Neg, inner) => {
+            let inner = if prec(inner) <= Prec::Prefix {
+                mathml_parens(&mathml(inner))
+            } else {
+                mathml(inner)
+            };
+            format!("<mrow><mo>\u{2212}</mo>{}</mrow>", inner)
+        }
+        Expr::UnaryOp(UnaryOp::Transpose, inner) => {
+            format!("<msup><mrow>{}</mrow><mi mathvariant=\"sans-serif\">T</mi></msup>", mathml_postfix_base(inner))
+        }
+        Expr::FunctionCall(name, args) => mathml_function(name, args),
+        Expr::List(items) => format!("<mrow><mo>[</mo>{}<mo>]</mo></mrow>", mathml_join(items)),
+        Expr::Record(fields) => format!(
+            "<mrow><mo>{{</mo>{}<mo>}}</mo></mrow>",
+            fields.iter()
+                .map(|(k, v)| format!("{}<mo>:</mo>{}", mathml_identifier(k), mathml(v)))
+                .collect::<Vec<_>>()
+                .join("<mo>,</mo>")
+        ),
+        Expr::FieldAccess(base, fields) => format!("<mrow>{}<mo>.</mo>{}</mrow>", mathml_postfix_base(base), mathml_dotted(fields)),
+        Expr::Index(base, args) => {
+            let args: Vec<String> = args.iter()
+                .map(|arg| match arg {
+                    IndexArg::At(e) => mathml(e),
+                    IndexArg::Slice(start, end) => format!(
+                        "{}<mo>:</mo>{}",
+                        start.as_ref().map(mathml).unwrap_or_default(),
+                        end.as_ref().map(mathml).unwrap_or_default()
+                    ),
+                })
+                .collect();
+            format!("<msub><mrow>{}</mrow><mrow>{}</mrow></msub>", mathml_postfix_base(base), args.join("<mo>,</mo>"))
+        }
+    }
+}
+
+fn mathml_postfix_base(expr: &Expr) -> String {
+    if prec(expr) < Prec::Atom {
+        mathml_parens(&mathml(expr))
+    } else {
+        mathml(expr)
+    }
+}
+
+fn mathml_parens(inner: &str) -> String {
+    format!("<mrow><mo>(</mo>{}<mo>)</mo></mrow>", inner)
+}
+
+fn mathml_join(items: &[Expr]) -> String {
+    items.iter().map(mathml).collect::<Vec<_>>().join("<mo>,</mo>")
+}
+
+fn mathml_dotted(parts: &[String]) -> String {
+    parts.iter().map(|p| mathml_identifier(p)).collect::<Vec<_>>().join("<mo>.</mo>")
+}
+
+fn mathml_op(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "\u{2212}",
+        BinOp::Mul | BinOp::Div => "\u{22C5}",
+        BinOp::ElemMul => "\u{2299}",
+        BinOp::ElemDiv => "\u{2298}",
+        BinOp::Pow => "^",
+        BinOp::Lt => "&lt;",
+        BinOp::Gt => "&gt;",
+        BinOp::Le => "\u{2264}",
+        BinOp::Ge => "\u{2265}",
+        BinOp::Eq => "=",
+        BinOp::Ne => "\u{2260}",
+    }
+}
+
+fn mathml_number(n: &str) -> String {
+    if let Some((num, den)) = n.split_once('/') {
+        return format!("<mfrac><mn>{}</mn><mn>{}</mn></mfrac>", num.trim(), den.trim());
+    }
+    let negative = n.starts_with('-');
+    let n = n.trim_start_matches('-');
+    let body = match n.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => format!(
+            "<mn>{}</mn><mo>\u{D7}</mo><msup><mn>10</mn><mn>{}</mn></msup>",
+            mantissa, exp.trim_start_matches('+').replace('-', "\u{2212}")
+        ),
+        None => format!("<mn>{}</mn>", n),
+    };
+    if negative {
+        format!("<mrow><mo>\u{2212}</mo>{}</mrow>", body)
+    } else {
+        body
+    }
+}
+
+fn mathml_function(name: &str, args: &[Expr]) -> String {
+    let lower = name.to_ascii_lowercase();
+    match (lower.as_str(), args) {
+        ("sqrt", [x]) => return format!("<msqrt>{}</msqrt>", mathml(x)),
+        ("abs", [x]) => return format!("<mrow><mo>&#124;</mo>{}<mo>&#124;</mo></mrow>", mathml(x)),
+        ("floor", [x]) => return format!("<mrow><mo>\u{230A}</mo>{}<mo>\u{230B}</mo></mrow>", mathml(x)),
+        ("ceil", [x]) => return format!("<mrow><mo>\u{2308}</mo>{}<mo>\u{2309}</mo></mrow>", mathml(x)),
+        ("exp", [x]) => return format!("<msup><mi>e</mi><mrow>{}</mrow></msup>", mathml(x)),
+        _ => {}
+    }
+    let name = match lower.as_str() {
+        "asin" | "acos" | "atan" => format!("arc{}", &lower[1..]),
+        _ => name.to_string(),
+    };
+    format!(
+        "<mrow><mi>{}</mi><mo>\u{2061}</mo><mrow><mo>(</mo>{}<mo>)</mo></mrow></mrow>",
+        xml_escape(&name),
+        mathml_join(args)
+    )
+}
+
+fn mathml_identifier(name: &str) -> String {
+    if let Some(greek) = greek(name) {
+        return format!("<mi>{}</mi>", greek.symbol);
+    }
+    if let Some((base, sub)) = name.split_once('_') {
+        if !base.is_empty() && !sub.is_empty() && !sub.contains('_') {
+            return format!("<msub>{}{}</msub>", mathml_identifier(base), mathml_identifier(sub));
+        }
+    }
+    format!("<mi>{}</mi>", xml_escape(name))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('|', "&#124;")
+}
+
+// ========== Plain text ==========
+
+/// Formula as Unicode text (`×`, `−`, `^`) with variables replaced where
+/// `subst` returns a value, e.g. `300000 × 0.005 × (1 + 0.005)^360`
+pub(crate) fn to_text(expr: &Expr, subst: &dyn Fn(&[String]) -> Option<String>) -> String {
+    let text = |e: &Expr| to_text(e, subst);
+    match expr {
+        Expr::Number(n) => n.replace('-', "\u{2212}"),
+        Expr::StringLiteral(s) => format!("\"{}\"", s),
+        Expr::Variable(parts) => match subst(parts) {
+            // Keep a substituted "-3" from reading as an operator
+            Some(value) if value.starts_with('-') || value.contains(' ') => format!("({})", value.replacen('-', "\u{2212}", 1)),
+            Some(value) => value,
+            None => parts.join("."),
+        },
+        Expr::BinaryOp(left, op, right) => {
+            let side = |e: &Expr, s: Side| {
+                if needs_parens(e, *op, s, false) {
+                    format!("({})", text(e))
+                } else {
+                    text(e)
+                }
+            };
+            match op {
+                BinOp::Pow => format!("{}^{}", side(left, Side::Left), side(right, Side::Right)),
+                _ => format!("{} {} {}", side(left, Side::Left), text_op(*op), side(right, Side::Right)),
+            }
+        }
+        Expr::UnaryOp(UnaryOp::Neg, inner) => {
+            if prec(inner) <= Prec::Prefix {
+                format!("\u{2212}({})", text(inner))
+            } else {
+                format!("\u{2212}{}", text(inner))
+            }
+        }
+        Expr::UnaryOp(UnaryOp::Transpose, inner) => format!("{}^T", text_postfix_base(inner, subst)),
+        Expr::FunctionCall(name, args) => format!("{}({})", name, text_join(args, subst)),
+        Expr::List(items) => format!("[{}]", text_join(items, subst)),
+        Expr::Record(fields) => format!(
+            "{{{}}}",
+            fields.iter().map(|(k, v)| format!("{}: {}", k, text(v))).collect::<Vec<_>>().join(", ")
+        ),
+        Expr::FieldAccess(base, fields) => format!("{}.{}", text_postfix_base(base, subst), fields.join(".")),
+        Expr::Index(base, args) => {
+            let args: Vec<String> = args.iter()
+                .map(|arg| match arg {
+                    IndexArg::At(e) => text(e),
+                    IndexArg::Slice(start, end) => format!(
+                        "{}:{}",
+                        start.as_ref().map(text).unwrap_or_default(),
+                        end.as_ref().map(text).unwrap_or_default()
+                    ),
+                })
+                .collect();
+            format!("{}[{}]", text_postfix_base(base, subst), args.join(", "))
+        }
+    }
+}
+
+fn text_postfix_base(expr: &Expr, subst: &dyn Fn(&[String]) -> Option<String>) -> String {
+    if prec(expr) < Prec::Atom {
+        format!("({})", to_text(expr, subst))
+    } else {
+        to_text(expr, subst)
+    }
+}
+
+fn text_join(items: &[Expr], subst: &dyn Fn(&[String]) -> Option<String>) -> String {
+    items.iter().map(|e| to_text(e, subst)).collect::<Vec<_>>().join(", ")
+}
+
+fn text_op(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "\u{2212}",
+        BinOp::Mul => "\u{D7}",
+        BinOp::Div => "/",
+        BinOp::ElemMul => "\u{2299}",
+        BinOp::ElemDiv => "\u{2298}",
+        BinOp::Pow => "^",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "\u{2264}",
+        BinOp::Ge => "\u{2265}",
+        BinOp::Eq => "=",
+        BinOp::Ne => "\u{2260}",
+    }
+}
+
+// ========== Greek letters ==========
+
+struct Greek {
+    latex: &'static str,
+    symbol: &'static str,
+}
+
+/// Greek letter for a spelled-out name or the letter itself; `phi` is the
+/// golden-ratio constant and uses the curly form
+fn greek(name: &str) -> Option<Greek> {
+    const LETTERS: [(&str, &str, &str); 40] = [
+        ("alpha", "\\alpha", "α"), ("beta", "\\beta", "β"), ("gamma", "\\gamma", "γ"),
+        ("delta", "\\delta", "δ"), ("epsilon", "\\epsilon", "ε"), ("zeta", "\\zeta", "ζ"),
+        ("eta", "\\eta", "η"), ("theta", "\\theta", "θ"), ("iota", "\\iota", "ι"),
+        ("kappa", "\\kappa", "κ"), ("lambda", "\\lambda", "λ"), ("mu", "\\mu", "μ"),
+        ("nu", "\\nu", "ν"), ("xi", "\\xi", "ξ"), ("pi", "\\pi", "π"),
+        ("rho", "\\rho", "ρ"), ("sigma", "\\sigma", "σ"), ("tau", "\\tau", "τ"),
+        ("upsilon", "\\upsilon", "υ"), ("phi", "\\varphi", "φ"), ("chi", "\\chi", "χ"),
+        ("psi", "\\psi", "ψ"), ("omega", "\\omega", "ω"),
+        ("Gamma", "\\Gamma", "Γ"), ("Delta", "\\Delta", "Δ"), ("Theta", "\\Theta", "Θ"),
+        ("Lambda", "\\Lambda", "Λ"), ("Xi", "\\Xi", "Ξ"), ("Pi", "\\Pi", "Π"),
+        ("Sigma", "\\Sigma", "Σ"), ("Upsilon", "\\Upsilon", "Υ"), ("Phi", "\\Phi", "Φ"),
+        ("Psi", "\\Psi", "Ψ"), ("Omega", "\\Omega", "Ω"),
+        ("varepsilon", "\\varepsilon", "ε"), ("vartheta", "\\vartheta", "ϑ"),
+        ("varphi", "\\varphi", "φ"), ("varrho", "\\varrho", "ϱ"), ("varsigma", "\\varsigma", "ς"),
+        ("ell", "\\ell", "ℓ"),
+    ];
+    LETTERS.iter()
+        .find(|(spelled, _, symbol)| *spelled == name || *symbol == name)
+        .map(|&(_, latex, symbol)| Greek { latex, symbol })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_expr;
+
+    fn latex(src: &str) -> String {
+        parse_expr(src).unwrap().to_latex()
+    }
+
+    fn mathml(src: &str) -> String {
+        parse_expr(src).unwrap().to_mathml()
+    }
+
+    #[test]
+    fn test_latex_precedence() {
+        assert_eq!(latex("a + b * c"), "a + b \\cdot c");
+        assert_eq!(latex("(a + b) * c"), "\\left(a + b\\right) \\cdot c");
+        assert_eq!(latex("a - (b - c)"), "a - \\left(b - c\\right)");
+        assert_eq!(latex("(a - b) - c"), "a - b - c");
+        assert_eq!(latex("(1 + r) ^ n"), "{\\left(1 + r\\right)}^{n}");
+        assert_eq!(latex("2 ^ 3 ^ 2"), "{2}^{{3}^{2}}");
+        assert_eq!(latex("(a / b) ^ 2"), "{\\left(\\frac{a}{b}\\right)}^{2}");
+    }
+
+    #[test]
+    fn test_latex_fractions_and_functions() {
+        assert_eq!(
+            latex("P * r * (1 + r) ^ n / ((1 + r) ^ n - 1)"),
+            "\\frac{P \\cdot r \\cdot {\\left(1 + r\\right)}^{n}}{{\\left(1 + r\\right)}^{n} - 1}"
+        );
+        assert_eq!(latex("sqrt(x) + abs(y)"), "\\sqrt{x} + \\left\\lvert y \\right\\rvert");
+        assert_eq!(latex("ln(x) * cbrt(y)"), "\\ln\\left(x\\right) \\cdot \\operatorname{cbrt}\\left(y\\right)");
+        assert_eq!(latex("1.5e3"), "1.5 \\times 10^{3}");
+    }
+
+    #[test]
+    fn test_latex_identifiers() {
+        assert_eq!(latex("2 * pi * r"), "2 \\cdot \\pi \\cdot r");
+        assert_eq!(latex("phi ^ 2"), "{\\varphi}^{2}");
+        assert_eq!(latex("x_1 + rate"), "x_{1} + \\mathrm{rate}");
+        assert_eq!(latex("v[i]"), "{v}_{i}");
+    }
+
+    #[test]
+    fn test_text_substitution() {
+        let expr = parse_expr("a / (b * c) - (x - y) ^ 2").unwrap();
+        let subst = |parts: &[String]| match parts[0].as_str() {
+            "a" => Some("6".to_string()),
+            "x" => Some("-1".to_string()),
+            _ => None,
+        };
+        assert_eq!(super::to_text(&expr, &subst), "6 / (b × c) − ((\u{2212}1) − y)^2");
+    }
+
+    #[test]
+    fn test_mathml() {
+        assert_eq!(
+            mathml("a / (b + 1)"),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mfrac><mrow><mi>a</mi></mrow><mrow><mrow><mi>b</mi><mo>+</mo><mn>1</mn></mrow></mrow></mfrac></math>"
+        );
+        assert!(mathml("sqrt(pi)").contains("<msqrt><mi>π</mi></msqrt>"));
+        assert!(mathml("(a - b) * c").contains("<mrow><mo>(</mo><mrow><mi>a</mi><mo>\u{2212}</mo><mi>b</mi></mrow><mo>)</mo></mrow><mo>\u{22C5}</mo><mi>c</mi>"));
+    }
+}
@@ -1,140 +1,189 @@
 //! Markdown table parser
 
-use crate::ast::{Document, Section, Table, Row, Cell, Expr, BinOp};
+use crate::ast::{Document, Section, Table, Row, Cell, Expr, BinOp, UnaryOp, IndexArg, Block, Attributes};
 use folio_core::FolioError;
-use std::collections::HashMap;
 
 /// Parse markdown document to AST
+///
+/// Besides the evaluable table, every section keeps its heading and body
+/// lines in source order so the renderer can reproduce the document.
 pub fn parse(input: &str) -> Result<Document, FolioError> {
+    let (front_matter, input) = split_front_matter(input);
+    let mut preamble: Vec<String> = Vec::new();
     let mut sections = Vec::new();
     let mut current_section: Option<Section> = None;
     let mut in_table = false;
-    let mut table_rows: Vec<Row> = Vec::new();
-    let mut columns: Vec<String> = Vec::new();
-    
-    for line in input.lines() {
-        let line = line.trim();
+    let mut expect_separator = false;
+    let mut in_fence = false;
 
-        // Section header - support both # and ## (# takes priority check first)
-        if line.starts_with("# ") && !line.starts_with("## ") {
-            // Single # header - treat as section
-            if let Some(mut sec) = current_section.take() {
-                sec.table.rows = std::mem::take(&mut table_rows);
-                sec.table.columns = std::mem::take(&mut columns);
-                sections.push(sec);
-            }
+    for raw_line in input.lines() {
+        let line = raw_line.trim();
 
-            let header = &line[2..]; // Skip "# "
-            let (name, attrs) = parse_section_header(header);
-            current_section = Some(Section {
-                name,
-                attributes: attrs,
-                table: Table::default(),
-            });
+        // Fenced code blocks are prose, even if they contain tables or headings
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.starts_with("```") || line.starts_with("~~~") {
+            push_text(&mut current_section, &mut preamble, raw_line);
             in_table = false;
             continue;
         }
 
-        // Double ## section header
-        if line.starts_with("## ") {
+        // Section header - support both # and ## (# takes priority check first)
+        let header = if line.starts_with("# ") && !line.starts_with("## ") {
+            Some(&line[2..]) // Skip "# "
+        } else if line.starts_with("## ") {
+            Some(&line[3..])
+        } else {
+            None
+        };
+        if let Some(header) = header {
             // Save previous section
-            if let Some(mut sec) = current_section.take() {
-                sec.table.rows = std::mem::take(&mut table_rows);
-                sec.table.columns = std::mem::take(&mut columns);
+            if let Some(sec) = current_section.take() {
                 sections.push(sec);
             }
-            
-            let header = &line[3..];
+
             let (name, attrs) = parse_section_header(header);
             current_section = Some(Section {
                 name,
                 attributes: attrs,
+                heading: Some(raw_line.to_string()),
                 table: Table::default(),
+                body: Vec::new(),
             });
             in_table = false;
             continue;
         }
-        
+
+        let is_table_line = line.starts_with('|') && line.ends_with('|') && line.len() > 1;
+
         // Table header
-        if line.starts_with('|') && line.ends_with('|') && !in_table {
-            columns = parse_table_row_cells(line);
+        if is_table_line && !in_table {
+            // Fallback: create default section if there's a table but no section header
+            let section = current_section.get_or_insert_with(|| Section {
+                name: "Default".to_string(),
+                attributes: Attributes::new(),
+                heading: None,
+                table: Table::default(),
+                body: std::mem::take(&mut preamble).into_iter().map(Block::Text).collect(),
+            });
+            let columns = parse_table_row_cells(line);
+            // Only a `result` column after the formula (always the second column) takes results
+            let result_column = columns.iter().skip(2)
+                .position(|c| c.eq_ignore_ascii_case("result"))
+                .map(|i| i + 2);
+            if section.table.columns.is_empty() {
+                section.table.columns = columns.clone();
+            }
+            section.body.push(Block::Header { cells: columns, result_column });
             in_table = true;
+            expect_separator = true;
             continue;
         }
-        
+
         // Table separator (only matches lines that contain only |, -, :, and whitespace)
-        if line.starts_with('|') && line.ends_with('|') && in_table && table_rows.is_empty() {
+        if is_table_line && expect_separator {
+            expect_separator = false;
             let is_separator = line.chars().all(|c| c == '|' || c == '-' || c == ':' || c.is_whitespace());
             if is_separator {
+                if let Some(section) = current_section.as_mut() {
+                    section.body.push(Block::Separator(parse_table_row_cells(line)));
+                }
                 continue;
             }
         }
-        
+
         // Table row
-        if line.starts_with('|') && line.ends_with('|') && in_table {
+        if is_table_line && in_table {
+            let section = current_section.as_mut().expect("table rows follow a header");
             let cells_text = parse_table_row_cells(line);
-            if cells_text.len() >= 2 {
-                let name = cells_text[0].trim().to_string();
-                let formula_text = cells_text[1].trim().to_string();
-
-                // Check for formula indicator (=) and strip it
-                let (is_formula, expr_text) = if formula_text.starts_with('=') {
-                    (true, formula_text[1..].trim().to_string())
-                } else {
-                    (false, formula_text.clone())
-                };
-
-                let formula = if expr_text.is_empty() {
-                    None
-                } else if is_formula {
-                    // Explicitly marked as formula with =
-                    Some(parse_expr(&expr_text)?)
-                } else {
-                    // Check if it looks like an expression (contains operators or function calls)
-                    // Otherwise treat as literal value
-                    if looks_like_expression(&expr_text) {
-                        Some(parse_expr(&expr_text)?)
-                    } else {
-                        None // Treat as literal
-                    }
-                };
-                
-                table_rows.push(Row {
-                    cells: vec![Cell {
-                        name: name.clone(),
-                        formula,
-                        raw_text: expr_text, // Store the expression text (without = prefix)
-                    }],
-                });
+            if cells_text.len() < 2 {
+                section.body.push(Block::Text(raw_line.to_string()));
+                continue;
             }
+
+            let cell = parse_cell(&cells_text[0], &cells_text[1])?;
+            section.body.push(Block::Row(section.table.rows.len()));
+            section.table.rows.push(Row {
+                cells: vec![cell],
+                raw_cells: cells_text,
+            });
             continue;
         }
 
-        // Empty line ends table
-        if line.is_empty() && in_table {
-            in_table = false;
-        }
+        // Empty line (or any prose) ends table
+        in_table = false;
+        expect_separator = false;
+        push_text(&mut current_section, &mut preamble, raw_line);
     }
 
     // Save last section
-    if let Some(mut sec) = current_section {
-        sec.table.rows = table_rows;
-        sec.table.columns = columns;
+    if let Some(sec) = current_section {
         sections.push(sec);
-    } else if !table_rows.is_empty() {
-        // Fallback: create default section if there's content but no section header
-        sections.push(Section {
-            name: "Default".to_string(),
-            attributes: HashMap::new(),
-            table: Table { rows: table_rows, columns },
-        });
     }
 
-    Ok(Document { sections })
+    Ok(Document { front_matter: front_matter.map(str::to_string), preamble, sections })
+}
+
+/// Split a leading `---` block from the document; the closing line is `---` or `...`
+pub fn split_front_matter(input: &str) -> (Option<&str>, &str) {
+    let Some(rest) = input.strip_prefix("---\n").or_else(|| input.strip_prefix("---\r\n")) else {
+        return (None, input);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, input)
+}
+
+/// A cell from its name and formula column text
+pub(crate) fn parse_cell(name: &str, text: &str) -> Result<Cell, FolioError> {
+    let (formula_text, cell_attrs) = split_cell_attributes(text.trim());
+
+    // Check for formula indicator (=) and strip it
+    let (is_formula, expr_text) = if formula_text.starts_with('=') {
+        (true, formula_text[1..].trim().to_string())
+    } else {
+        (false, formula_text.clone())
+    };
+
+    let formula = if expr_text.is_empty() {
+        None
+    } else if is_formula {
+        // Explicitly marked as formula with =
+        Some(parse_expr(&expr_text)?)
+    } else {
+        // Check if it looks like an expression (contains operators or function calls)
+        // Otherwise treat as literal value
+        if looks_like_expression(&expr_text) {
+            Some(parse_expr(&expr_text)?)
+        } else {
+            None // Treat as literal
+        }
+    };
+
+    Ok(Cell {
+        name: name.trim().to_string(),
+        formula,
+        raw_text: expr_text, // Store the expression text (without = prefix)
+        attributes: cell_attrs,
+    })
+}
+
+/// Keep a non-table line verbatim in the current section or the preamble
+fn push_text(section: &mut Option<Section>, preamble: &mut Vec<String>, line: &str) {
+    match section {
+        Some(sec) => sec.body.push(Block::Text(line.to_string())),
+        None => preamble.push(line.to_string()),
+    }
 }
 
 /// Check if text looks like an expression (vs a literal value)
-fn looks_like_expression(text: &str) -> bool {
+pub(crate) fn looks_like_expression(text: &str) -> bool {
     let text = text.trim();
 
     // First check if it's a valid number literal (including scientific notation)
@@ -147,6 +196,11 @@ fn looks_like_expression(text: &str) -> bool {
         return true;
     }
 
+    // Record literal
+    if text.starts_with('{') && text.ends_with('}') {
+        return true;
+    }
+
     // Contains operators (but not just a negative number)
     if text.contains('+') || text.contains('*') || text.contains('/') || text.contains('^') {
         return true;
@@ -208,8 +262,8 @@ fn is_number_literal(text: &str) -> bool {
     false
 }
 
-fn parse_section_header(header: &str) -> (String, HashMap<String, String>) {
-    let mut attrs = HashMap::new();
+fn parse_section_header(header: &str) -> (String, Attributes) {
+    let mut attrs = Attributes::new();
     let parts: Vec<&str> = header.split('@').collect();
     let name = parts[0].trim().to_string();
     
@@ -222,6 +276,27 @@ fn parse_section_header(header: &str) -> (String, HashMap<String, String>) {
     (name, attrs)
 }
 
+/// Split trailing `@key:value` annotations off a formula cell
+fn split_cell_attributes(text: &str) -> (String, Attributes) {
+    let marks = transpose_marks(text);
+    let mut in_double_quote = false;
+    let mut in_single_quote = false;
+    let mut prev = ' ';
+    for (byte_pos, c) in text.char_indices() {
+        match c {
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
+            '@' if !in_double_quote && !in_single_quote && prev.is_whitespace() => {
+                let (_, attrs) = parse_section_header(&text[byte_pos..]);
+                return (text[..byte_pos].trim().to_string(), attrs);
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    (text.to_string(), Attributes::new())
+}
+
 fn parse_table_row_cells(line: &str) -> Vec<String> {
     line.trim_matches('|')
         .split('|')
@@ -235,12 +310,66 @@ pub fn parse_expr(input: &str) -> Result<Expr, FolioError> {
     if input.is_empty() {
         return Err(FolioError::parse_error("Empty expression"));
     }
+    check_balanced(input)?;
 
     parse_comparison(input)
 }
 
+/// Brackets outside string literals must close in order; `(1 +` is not a name
+fn check_balanced(input: &str) -> Result<(), FolioError> {
+    let marks = transpose_marks(input);
+    let mut open: Vec<char> = Vec::new();
+    let mut in_double_quote = false;
+    let mut in_single_quote = false;
+    for (byte_pos, c) in input.char_indices() {
+        match c {
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
+            _ if in_double_quote || in_single_quote => {}
+            '(' | '[' | '{' => open.push(c),
+            ')' | ']' | '}' => {
+                let expected = match open.pop() {
+                    Some('(') => ')',
+                    Some('[') => ']',
+                    Some(_) => '}',
+                    None => return Err(FolioError::parse_error(format!("Unmatched '{}' in '{}'", c, input))),
+                };
+                if c != expected {
+                    return Err(FolioError::parse_error(format!("Expected '{}' but found '{}' in '{}'", expected, c, input)));
+                }
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(c) => Err(FolioError::parse_error(format!("Unclosed '{}' in '{}'", c, input))),
+        None => Ok(()),
+    }
+}
+
+/// Byte offsets of the `'` marks that transpose the operand just before
+/// them (`A'`, `f(x)'`, `m[0]'`). Any other `'` opens or closes a string.
+fn transpose_marks(input: &str) -> Vec<usize> {
+    let mut marks = Vec::new();
+    let mut in_double_quote = false;
+    let mut in_single_quote = false;
+    let mut prev = ' ';
+    for (byte_pos, c) in input.char_indices() {
+        match c {
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\'' if !in_double_quote && !in_single_quote
+                && (prev.is_alphanumeric() || matches!(prev, '_' | ')' | ']' | '\'')) => marks.push(byte_pos),
+            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            _ => {}
+        }
+        prev = c;
+    }
+    marks
+}
+
 /// Parse comparison operators (lowest precedence)
 fn parse_comparison(input: &str) -> Result<Expr, FolioError> {
+    let marks = transpose_marks(input);
     let mut paren_depth = 0;
     let mut bracket_depth = 0;
     let mut in_double_quote = false;
@@ -254,11 +383,11 @@ fn parse_comparison(input: &str) -> Result<Expr, FolioError> {
         let (byte_pos, c) = char_indices[i];
         match c {
             '"' if !in_single_quote => in_double_quote = !in_double_quote,
-            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
             '(' if !in_double_quote && !in_single_quote => paren_depth += 1,
             ')' if !in_double_quote && !in_single_quote => paren_depth -= 1,
-            '[' if !in_double_quote && !in_single_quote => bracket_depth += 1,
-            ']' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
+            '[' | '{' if !in_double_quote && !in_single_quote => bracket_depth += 1,
+            ']' | '}' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
             '<' | '>' | '=' | '!' if paren_depth == 0 && bracket_depth == 0 && !in_double_quote && !in_single_quote => {
                 // Check for two-character operators
                 let next_char = if i + 1 < char_indices.len() { Some(char_indices[i + 1].1) } else { None };
@@ -299,6 +428,7 @@ fn parse_comparison(input: &str) -> Result<Expr, FolioError> {
 }
 
 fn parse_additive(input: &str) -> Result<Expr, FolioError> {
+    let marks = transpose_marks(input);
     // Find + or - not inside parentheses, brackets, function calls, or quotes
     let mut paren_depth = 0;
     let mut bracket_depth = 0;
@@ -312,11 +442,11 @@ fn parse_additive(input: &str) -> Result<Expr, FolioError> {
         let (byte_pos, c) = char_indices[idx];
         match c {
             '"' if !in_single_quote => in_double_quote = !in_double_quote,
-            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
             ')' if !in_double_quote && !in_single_quote => paren_depth += 1,
             '(' if !in_double_quote && !in_single_quote => paren_depth -= 1,
-            ']' if !in_double_quote && !in_single_quote => bracket_depth += 1,
-            '[' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
+            ']' | '}' if !in_double_quote && !in_single_quote => bracket_depth += 1,
+            '[' | '{' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
             '+' | '-' if paren_depth == 0 && bracket_depth == 0 && idx > 0 && !in_double_quote && !in_single_quote => {
                 let left = input[..byte_pos].trim();
                 let right = input[byte_pos + c.len_utf8()..].trim();
@@ -337,6 +467,7 @@ fn parse_additive(input: &str) -> Result<Expr, FolioError> {
 }
 
 fn parse_multiplicative(input: &str) -> Result<Expr, FolioError> {
+    let marks = transpose_marks(input);
     let mut paren_depth = 0;
     let mut bracket_depth = 0;
     let mut in_double_quote = false;
@@ -349,16 +480,24 @@ fn parse_multiplicative(input: &str) -> Result<Expr, FolioError> {
         let (byte_pos, c) = char_indices[idx];
         match c {
             '"' if !in_single_quote => in_double_quote = !in_double_quote,
-            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
             ')' if !in_double_quote && !in_single_quote => paren_depth += 1,
             '(' if !in_double_quote && !in_single_quote => paren_depth -= 1,
-            ']' if !in_double_quote && !in_single_quote => bracket_depth += 1,
-            '[' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
+            ']' | '}' if !in_double_quote && !in_single_quote => bracket_depth += 1,
+            '[' | '{' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
             '*' | '/' if paren_depth == 0 && bracket_depth == 0 && !in_double_quote && !in_single_quote => {
-                let left = input[..byte_pos].trim();
+                // A preceding '.' makes it element-wise: .* or ./
+                let elementwise = idx > 0 && char_indices[idx - 1].1 == '.';
+                let left_end = if elementwise { char_indices[idx - 1].0 } else { byte_pos };
+                let left = input[..left_end].trim();
                 let right = input[byte_pos + c.len_utf8()..].trim();
                 if !left.is_empty() && !right.is_empty() {
-                    let op = if c == '*' { BinOp::Mul } else { BinOp::Div };
+                    let op = match (c, elementwise) {
+                        ('*', false) => BinOp::Mul,
+                        ('*', true) => BinOp::ElemMul,
+                        (_, false) => BinOp::Div,
+                        (_, true) => BinOp::ElemDiv,
+                    };
                     return Ok(Expr::BinaryOp(
                         Box::new(parse_multiplicative(left)?),
                         op,
@@ -374,6 +513,7 @@ fn parse_multiplicative(input: &str) -> Result<Expr, FolioError> {
 }
 
 fn parse_power(input: &str) -> Result<Expr, FolioError> {
+    let marks = transpose_marks(input);
     let mut paren_depth = 0;
     let mut bracket_depth = 0;
     let mut in_double_quote = false;
@@ -386,15 +526,19 @@ fn parse_power(input: &str) -> Result<Expr, FolioError> {
         let (byte_pos, c) = char_indices[idx];
         match c {
             '"' if !in_single_quote => in_double_quote = !in_double_quote,
-            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
             '(' if !in_double_quote && !in_single_quote => paren_depth += 1,
             ')' if !in_double_quote && !in_single_quote => paren_depth -= 1,
-            '[' if !in_double_quote && !in_single_quote => bracket_depth += 1,
-            ']' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
+            '[' | '{' if !in_double_quote && !in_single_quote => bracket_depth += 1,
+            ']' | '}' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
             '^' if paren_depth == 0 && bracket_depth == 0 && !in_double_quote && !in_single_quote => {
                 let left = input[..byte_pos].trim();
                 let right = input[byte_pos + c.len_utf8()..].trim();
                 if !left.is_empty() && !right.is_empty() {
+                    // x^T is transpose; use x^(T) to raise to a cell named T
+                    if right == "T" {
+                        return Ok(Expr::UnaryOp(UnaryOp::Transpose, Box::new(parse_primary(left)?)));
+                    }
                     return Ok(Expr::BinaryOp(
                         Box::new(parse_primary(left)?),
                         BinOp::Pow,
@@ -412,6 +556,13 @@ fn parse_power(input: &str) -> Result<Expr, FolioError> {
 fn parse_primary(input: &str) -> Result<Expr, FolioError> {
     let input = input.trim();
 
+    // Transpose: A', (A * B)'
+    if let Some(base) = input.strip_suffix('\'') {
+        if transpose_marks(input).last() == Some(&base.len()) {
+            return Ok(Expr::UnaryOp(UnaryOp::Transpose, Box::new(parse_primary(base)?)));
+        }
+    }
+
     // String literal (double-quoted)
     if input.starts_with('"') && input.ends_with('"') && input.len() >= 2 {
         let content = &input[1..input.len()-1];
@@ -424,6 +575,23 @@ fn parse_primary(input: &str) -> Result<Expr, FolioError> {
         return Ok(Expr::StringLiteral(content.to_string()));
     }
 
+    // Indexing: x[i], x[a:b], m[i, j], f(x)[0]
+    if input.ends_with(']') {
+        if let Some(open) = find_index_open(input) {
+            let target = input[..open].trim();
+            if !target.is_empty() {
+                let args = parse_index_args(&input[open + 1..input.len() - 1])?;
+                return Ok(Expr::Index(Box::new(parse_primary(target)?), args));
+            }
+        }
+    }
+
+    // Record literal: {name: expr, ...}
+    if input.starts_with('{') && input.ends_with('}') {
+        let fields = parse_record_fields(&input[1..input.len()-1])?;
+        return Ok(Expr::Record(fields));
+    }
+
     // List literal: [a, b, c]
     if input.starts_with('[') && input.ends_with(']') && input.len() >= 2 {
         let content = &input[1..input.len()-1];
@@ -445,10 +613,11 @@ fn parse_primary(input: &str) -> Result<Expr, FolioError> {
         let mut close_pos = None;
         let mut in_double_quote = false;
         let mut in_single_quote = false;
+        let marks = transpose_marks(after_open);
         for (i, c) in after_open.char_indices() {
             match c {
                 '"' if !in_single_quote => in_double_quote = !in_double_quote,
-                '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+                '\'' if !in_double_quote && !marks.contains(&i) => in_single_quote = !in_single_quote,
                 '(' if !in_double_quote && !in_single_quote => depth += 1,
                 ')' if !in_double_quote && !in_single_quote => {
                     depth -= 1;
@@ -488,8 +657,135 @@ fn parse_primary(input: &str) -> Result<Expr, FolioError> {
     Ok(Expr::Variable(parts))
 }
 
+/// Find the '[' matching the trailing ']' of an index expression
+fn find_index_open(input: &str) -> Option<usize> {
+    let marks = transpose_marks(input);
+    let mut depth = 0;
+    let mut in_double_quote = false;
+    let mut in_single_quote = false;
+
+    for (byte_pos, c) in input.char_indices().rev() {
+        match c {
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
+            ']' | ')' | '}' if !in_double_quote && !in_single_quote => depth += 1,
+            '[' | '(' | '{' if !in_double_quote && !in_single_quote => {
+                depth -= 1;
+                if depth == 0 {
+                    return if c == '[' { Some(byte_pos) } else { None };
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse the inside of `[...]`: i | a:b | i, j | :, j
+fn parse_index_args(input: &str) -> Result<Vec<IndexArg>, FolioError> {
+    let marks = transpose_marks(input);
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut in_double_quote = false;
+    let mut in_single_quote = false;
+    let mut current_start = 0;
+    let mut colon: Option<usize> = None;
+
+    let mut finish = |start: usize, end: usize, colon: Option<usize>| -> Result<(), FolioError> {
+        let part = input[start..end].trim();
+        let bound = |s: &str| -> Result<Option<Expr>, FolioError> {
+            if s.trim().is_empty() { Ok(None) } else { parse_expr(s).map(Some) }
+        };
+        match colon {
+            Some(c) => args.push(IndexArg::Slice(bound(&input[start..c])?, bound(&input[c + 1..end])?)),
+            None if part.is_empty() => {
+                return Err(FolioError::parse_error("Empty index")
+                    .with_suggestion("Use x[i], x[a:b], m[i, j] or m[:, j]"));
+            }
+            None => args.push(IndexArg::At(parse_expr(part)?)),
+        }
+        Ok(())
+    };
+
+    for (byte_pos, c) in input.char_indices() {
+        match c {
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
+            '(' | '[' | '{' if !in_double_quote && !in_single_quote => depth += 1,
+            ')' | ']' | '}' if !in_double_quote && !in_single_quote => depth -= 1,
+            ':' if depth == 0 && !in_double_quote && !in_single_quote && colon.is_none() => {
+                colon = Some(byte_pos);
+            }
+            ',' if depth == 0 && !in_double_quote && !in_single_quote => {
+                finish(current_start, byte_pos, colon.take())?;
+                current_start = byte_pos + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    finish(current_start, input.len(), colon)?;
+    Ok(args)
+}
+
+/// Parse record literal fields: name: expr, "quoted key": expr, shorthand
+fn parse_record_fields(input: &str) -> Result<Vec<(String, Expr)>, FolioError> {
+    let marks = transpose_marks(input);
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut fields: Vec<(String, Expr)> = Vec::new();
+    let mut depth = 0;
+    let mut in_double_quote = false;
+    let mut in_single_quote = false;
+    let mut current_start = 0;
+    let mut colon: Option<usize> = None;
+
+    let mut finish = |start: usize, end: usize, colon: Option<usize>| -> Result<(), FolioError> {
+        let (key, value) = match colon {
+            Some(c) => (input[start..c].trim(), parse_expr(&input[c + 1..end])?),
+            None => {
+                let name = input[start..end].trim();
+                (name, Expr::Variable(name.split('.').map(|s| s.trim().to_string()).collect()))
+            }
+        };
+        let key = key.trim_matches(|c| c == '"' || c == '\'');
+        if key.is_empty() {
+            return Err(FolioError::parse_error("Record field without a name")
+                .with_suggestion("Use {name: expr, other: expr}"));
+        }
+        if fields.iter().any(|(k, _)| k == key) {
+            return Err(FolioError::parse_error(format!("Duplicate record field '{}'", key)));
+        }
+        fields.push((key.to_string(), value));
+        Ok(())
+    };
+
+    for (byte_pos, c) in input.char_indices() {
+        match c {
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
+            '(' | '[' | '{' if !in_double_quote && !in_single_quote => depth += 1,
+            ')' | ']' | '}' if !in_double_quote && !in_single_quote => depth -= 1,
+            ':' if depth == 0 && !in_double_quote && !in_single_quote && colon.is_none() => {
+                colon = Some(byte_pos);
+            }
+            ',' if depth == 0 && !in_double_quote && !in_single_quote => {
+                finish(current_start, byte_pos, colon.take())?;
+                current_start = byte_pos + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    finish(current_start, input.len(), colon)?;
+    Ok(fields)
+}
+
 /// Parse list literal elements: a, b, c (similar to args but for lists)
 fn parse_list_elements(input: &str) -> Result<Vec<Expr>, FolioError> {
+    let marks = transpose_marks(input);
     if input.trim().is_empty() {
         return Ok(Vec::new());
     }
@@ -505,11 +801,11 @@ fn parse_list_elements(input: &str) -> Result<Vec<Expr>, FolioError> {
     for (byte_pos, c) in input.char_indices() {
         match c {
             '"' if !in_single_quote => in_double_quote = !in_double_quote,
-            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
             '(' if !in_double_quote && !in_single_quote => paren_depth += 1,
             ')' if !in_double_quote && !in_single_quote => paren_depth -= 1,
-            '[' if !in_double_quote && !in_single_quote => bracket_depth += 1,
-            ']' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
+            '[' | '{' if !in_double_quote && !in_single_quote => bracket_depth += 1,
+            ']' | '}' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
             ',' if paren_depth == 0 && bracket_depth == 0 && !in_double_quote && !in_single_quote => {
                 elements.push(parse_expr(&input[current_start..byte_pos])?);
                 current_start = byte_pos + c.len_utf8();
@@ -523,6 +819,7 @@ fn parse_list_elements(input: &str) -> Result<Vec<Expr>, FolioError> {
 }
 
 fn parse_args(input: &str) -> Result<Vec<Expr>, FolioError> {
+    let marks = transpose_marks(input);
     if input.trim().is_empty() {
         return Ok(Vec::new());
     }
@@ -538,11 +835,11 @@ fn parse_args(input: &str) -> Result<Vec<Expr>, FolioError> {
     for (byte_pos, c) in input.char_indices() {
         match c {
             '"' if !in_single_quote => in_double_quote = !in_double_quote,
-            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '\'' if !in_double_quote && !marks.contains(&byte_pos) => in_single_quote = !in_single_quote,
             '(' if !in_double_quote && !in_single_quote => paren_depth += 1,
             ')' if !in_double_quote && !in_single_quote => paren_depth -= 1,
-            '[' if !in_double_quote && !in_single_quote => bracket_depth += 1,
-            ']' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
+            '[' | '{' if !in_double_quote && !in_single_quote => bracket_depth += 1,
+            ']' | '}' if !in_double_quote && !in_single_quote => bracket_depth -= 1,
             ',' if paren_depth == 0 && bracket_depth == 0 && !in_double_quote && !in_single_quote => {
                 args.push(parse_expr(&input[current_start..byte_pos])?);
                 current_start = byte_pos + c.len_utf8();
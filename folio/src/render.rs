@@ -2,17 +2,42 @@
 //!
 //! Renders evaluated document back to markdown with results.
 
-use crate::ast::Document;
-use folio_core::Value;
+use crate::ast::{Attributes, Block, Document, Expr};
+use crate::math::to_text;
+use crate::format::{NumberLocale, NumberPattern};
+use folio_core::{FolioError, Value};
 use std::collections::HashMap;
 
+/// A back-end that turns an evaluated document into text
+///
+/// [`Renderer`] writes markdown; the HTML, LaTeX and JSON back-ends share the
+/// same section attributes for number and date formatting.
+pub trait DocumentRenderer {
+    /// Render document with computed values
+    fn render(
+        &self,
+        doc: &Document,
+        values: &HashMap<String, Value>,
+        external: &HashMap<String, Value>,
+    ) -> String;
+
+    /// Output for a template that failed to parse
+    fn render_parse_error(&self, error: &FolioError) -> String;
+}
+
+/// Attributes that choose the number format; a cell setting any of them
+/// replaces the section's choice instead of mixing with it
+const NUMBER_FORMAT_ATTRS: [&str; 3] = ["format", "sigfigs", "decimals"];
+
 /// Display format for numbers
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum NumberFormat {
     /// Fixed decimal places (default)
     Decimal(u32),
     /// Significant figures with scientific notation for large/small values
     SigFigs(u32),
+    /// Excel-style pattern such as `#,##0.00` or `0.0%`
+    Pattern(Box<NumberPattern>),
 }
 
 impl Default for NumberFormat {
@@ -21,6 +46,48 @@ impl Default for NumberFormat {
     }
 }
 
+/// How matrices are drawn when expanded with `@expand`
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum MatrixStyle {
+    /// Aligned markdown sub-table (default)
+    #[default]
+    Table,
+    /// LaTeX `pmatrix` block (`@matrix:latex`)
+    Latex,
+}
+
+/// Typeset formula column added with `@formula:latex` or `@formula:mathml`
+#[derive(Clone, Copy, PartialEq)]
+pub enum FormulaStyle {
+    /// Inline `$...$` math
+    Latex,
+    /// Inline `<math>` element
+    MathMl,
+}
+
+/// Everything that affects how one value is displayed
+#[derive(Clone)]
+pub struct CellFormat {
+    pub number: NumberFormat,
+    pub locale: NumberLocale,
+    pub datetime: DateTimeFormats,
+    /// Lists longer than this are shortened inline (`@listMax:N`, default 10)
+    pub list_max: usize,
+    pub matrix_style: MatrixStyle,
+}
+
+impl Default for CellFormat {
+    fn default() -> Self {
+        Self {
+            number: NumberFormat::default(),
+            locale: NumberLocale::default(),
+            datetime: DateTimeFormats::default(),
+            list_max: 10,
+            matrix_style: MatrixStyle::default(),
+        }
+    }
+}
+
 /// Display formats for datetime values
 #[derive(Clone, Default)]
 pub struct DateTimeFormats {
@@ -41,6 +108,10 @@ impl Renderer {
     }
 
     /// Render document with computed values
+    ///
+    /// The source is reproduced line for line: prose, headings, column names,
+    /// alignment and extra columns are kept, and only the result column is
+    /// filled in. Tables without a result column get one appended.
     pub fn render(
         &self,
         doc: &Document,
@@ -54,66 +125,273 @@ impl Renderer {
             output.push_str("## External Variables\n\n");
             output.push_str("| name | value |\n");
             output.push_str("|------|-------|\n");
-            let default_dt_formats = DateTimeFormats::default();
-            for (name, value) in external {
-                output.push_str(&format!("| {} | {} |\n", name, self.render_value(value, NumberFormat::default(), &default_dt_formats)));
+            let default_format = CellFormat::default();
+            let mut names: Vec<&String> = external.keys().collect();
+            names.sort();
+            for name in names {
+                output.push_str(&format!("| {} | {} |\n", name, self.render_value(&external[name], &default_format)));
             }
             output.push('\n');
         }
 
+        for line in &doc.preamble {
+            output.push_str(line);
+            output.push('\n');
+        }
+
         // Render each section
         for section in &doc.sections {
-            output.push_str(&format!("## {}", section.name));
+            if let Some(heading) = &section.heading {
+                output.push_str(heading);
+                output.push('\n');
+            }
 
-            // Add attributes if any
-            if !section.attributes.is_empty() {
-                let attrs: Vec<String> = section.attributes
-                    .iter()
-                    .map(|(k, v)| format!("{}:{}", k, v))
+            // Determine formats from section attributes
+            let section_format = self.get_cell_format(&section.attributes);
+            let expand = self.get_expand(&section.attributes);
+
+            let math = self.get_math_style(&section.attributes);
+            let show_work = section.attributes.get("showWork").is_some_and(|v| v.trim() == "true");
+
+            // Column that receives results in the current table
+            let mut result_col = 0;
+            // Columns for typeset formulas (`@formula:latex`) and substituted values (`@showWork:true`)
+            let mut math_col: Option<usize> = None;
+            let mut work_col: Option<usize> = None;
+            // Rows are padded so every filled column exists
+            let mut width = 0;
+            // Expanded values of the current table, written once it ends
+            let mut pending: Vec<(&str, &Value, CellFormat)> = Vec::new();
+            for block in &section.body {
+                if !matches!(block, Block::Row(_)) {
+                    self.flush_expanded(&mut output, &mut pending);
+                }
+                match block {
+                    Block::Text(line) => {
+                        output.push_str(line);
+                        output.push('\n');
+                    }
+                    Block::Header { cells, result_column } => {
+                        let mut cells = cells.clone();
+                        result_col = result_column.unwrap_or_else(|| {
+                            cells.push("result".to_string());
+                            cells.len() - 1
+                        });
+                        math_col = math.map(|_| extra_column(&mut cells, "math"));
+                        work_col = show_work.then(|| extra_column(&mut cells, "work"));
+                        width = 1 + result_col.max(math_col.unwrap_or(0)).max(work_col.unwrap_or(0));
+                        output.push_str(&table_line(&cells));
+                    }
+                    Block::Separator(cells) => {
+                        let mut cells = cells.clone();
+                        while cells.len() < width {
+                            cells.push("--------".to_string());
+                        }
+                        output.push_str(&table_line(&cells));
+                    }
+                    Block::Row(index) => {
+                        let row = &section.table.rows[*index];
+                        let mut cells = row.raw_cells.clone();
+                        while cells.len() < width {
+                            cells.push(String::new());
+                        }
+                        if let Some(cell) = row.cells.first() {
+                            if let (Some(col), Some(style), Some(expr)) = (math_col, math, &cell.formula) {
+                                cells[col] = match style {
+                                    FormulaStyle::Latex => format!("${}$", expr.to_latex()),
+                                    FormulaStyle::MathMl => expr.to_mathml(),
+                                };
+                            }
+                            let cell_format = if cell.attributes.is_empty() {
+                                section_format.clone()
+                            } else {
+                                self.cell_format(&section.attributes, &cell.attributes)
+                            };
+
+                            if let Some(value) = values.get(&cell.name) {
+                                cells[result_col] = self.render_value(value, &cell_format);
+                                if let (Some(col), Some(expr)) = (work_col, &cell.formula) {
+                                    cells[col] = self.show_work(expr, &|name| values.get(name).cloned(), value, &cell_format);
+                                }
+                                if expand.matches(&cell.name) && is_expandable(value) {
+                                    pending.push((&cell.name, value, cell_format));
+                                }
+                            }
+                        }
+                        output.push_str(&table_line(&cells));
+                    }
+                }
+            }
+            self.flush_expanded(&mut output, &mut pending);
+        }
+
+        output
+    }
+
+    /// Formula with the values it read substituted in, followed by its result:
+    /// `300000 × 0.0054 × (1 + 0.0054)^360 / ((1 + 0.0054)^360 − 1) = 1892.26`
+    ///
+    /// Only the result takes the cell's format; inputs keep enough digits to
+    /// recompute it. Names that `lookup` does not know (constants, functions)
+    /// stay as written.
+    pub(crate) fn show_work(
+        &self,
+        expr: &Expr,
+        lookup: &dyn Fn(&str) -> Option<Value>,
+        result: &Value,
+        format: &CellFormat,
+    ) -> String {
+        let subst = |parts: &[String]| {
+            let mut value = lookup(parts.first()?)?;
+            for field in &parts[1..] {
+                value = value.get(field);
+            }
+            match value {
+                Value::Error(_) => None,
+                other => Some(self.render_input(&other, format)),
+            }
+        };
+        format!("{} = {}", to_text(expr, &subst), self.render_value(result, format))
+    }
+
+    /// Write the sub-tables of expanded values below the table that produced them
+    fn flush_expanded(&self, output: &mut String, pending: &mut Vec<(&str, &Value, CellFormat)>) {
+        for (name, value, format) in pending.drain(..) {
+            output.push_str(&format!("\n**{}**\n\n", name));
+            output.push_str(&self.render_expanded(value, &format));
+        }
+    }
+
+    /// Render a list, object or matrix as a block below the main table
+    fn render_expanded(&self, value: &Value, format: &CellFormat) -> String {
+        let mut out = String::new();
+        if let Some(rows) = matrix_rows(value) {
+            if format.matrix_style == MatrixStyle::Latex {
+                out.push_str("$$\n\\begin{pmatrix}\n");
+                let lines: Vec<String> = rows.iter()
+                    .map(|row| row.iter().map(|v| self.render_value(v, format)).collect::<Vec<_>>().join(" & "))
                     .collect();
-                output.push_str(&format!(" @{}", attrs.join(",")));
+                out.push_str(&lines.join(" \\\\\n"));
+                out.push_str("\n\\end{pmatrix}\n$$\n");
+                return out;
+            }
+            let cols = rows.first().map_or(0, |r| r.len());
+            let mut header = vec![String::new()];
+            header.extend((0..cols).map(|j| j.to_string()));
+            out.push_str(&table_line(&header));
+            let mut separator = vec!["---".to_string()];
+            separator.extend((0..cols).map(|_| "---:".to_string()));
+            out.push_str(&table_line(&separator));
+            for (i, row) in rows.iter().enumerate() {
+                let mut cells = vec![format!("**{}**", i)];
+                cells.extend(row.iter().map(|v| self.render_value(v, format)));
+                out.push_str(&table_line(&cells));
             }
-            output.push_str("\n\n");
+            return out;
+        }
 
-            // Determine formats from section attributes
-            let num_format = self.get_number_format(&section.attributes);
-            let dt_formats = self.get_datetime_formats(&section.attributes);
-
-            // Render table header
-            output.push_str("| name | formula | result |\n");
-            output.push_str("|------|---------|--------|\n");
-
-            // Rows
-            for row in &section.table.rows {
-                for cell in &row.cells {
-                    let result = values.get(&cell.name)
-                        .map(|v| self.render_value(v, num_format, &dt_formats))
-                        .unwrap_or_default();
-                    output.push_str(&format!("| {} | {} | {} |\n",
-                        cell.name, cell.raw_text, result));
+        match value {
+            Value::List(items) => {
+                out.push_str("| index | value |\n");
+                out.push_str("|------:|-------|\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&table_line(&[i.to_string(), self.render_value(item, format)]));
+                }
+            }
+            Value::Object(map) => {
+                out.push_str("| field | value |\n");
+                out.push_str("|-------|-------|\n");
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                for key in keys {
+                    out.push_str(&table_line(&[key.clone(), self.render_value(&map[key], format)]));
                 }
             }
+            _ => {}
+        }
+        out
+    }
 
-            output.push('\n');
+    /// Display format for one cell: per-cell @format/@sigfigs/@decimals/@locale
+    /// override the section
+    pub(crate) fn cell_format(&self, section: &Attributes, cell: &Attributes) -> CellFormat {
+        let mut attrs = section.clone();
+        if NUMBER_FORMAT_ATTRS.iter().any(|k| cell.contains_key(k)) {
+            for k in NUMBER_FORMAT_ATTRS {
+                attrs.remove(k);
+            }
+        }
+        for (k, v) in cell.iter() {
+            attrs.insert(k.clone(), v.clone());
         }
+        self.get_cell_format(&attrs)
+    }
 
-        output
+    /// Build the display format for a section or cell from its attributes
+    fn get_cell_format(&self, attrs: &Attributes) -> CellFormat {
+        CellFormat {
+            number: self.get_number_format(attrs),
+            locale: self.get_locale(attrs),
+            datetime: self.get_datetime_formats(attrs),
+            list_max: attrs.get("listMax")
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(10),
+            matrix_style: match attrs.get("matrix").map(|s| s.as_str()) {
+                Some("latex") => MatrixStyle::Latex,
+                _ => MatrixStyle::Table,
+            },
+        }
+    }
+
+    /// Get the typeset formula column style from @formula:latex or @formula:mathml
+    fn get_math_style(&self, attrs: &Attributes) -> Option<FormulaStyle> {
+        match attrs.get("formula").map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+            Some("latex") | Some("tex") => Some(FormulaStyle::Latex),
+            Some("mathml") => Some(FormulaStyle::MathMl),
+            _ => None,
+        }
+    }
+
+    /// Get the cells to expand from @expand:a,b or @expand:all
+    fn get_expand(&self, attrs: &Attributes) -> Expand {
+        match attrs.get("expand").map(|s| s.trim()) {
+            None | Some("") => Expand::Names(Vec::new()),
+            Some("all") | Some("*") => Expand::All,
+            Some(list) => Expand::Names(list.split(',').map(|s| s.trim().to_string()).collect()),
+        }
     }
 
     /// Get number format from section attributes
-    fn get_number_format(&self, attrs: &HashMap<String, String>) -> NumberFormat {
-        // Check for @sigfigs first (takes precedence)
+    fn get_number_format(&self, attrs: &Attributes) -> NumberFormat {
+        // An explicit @format pattern wins
+        if let Some(pattern) = attrs.get("format").and_then(|p| NumberPattern::parse(p)) {
+            return NumberFormat::Pattern(Box::new(pattern));
+        }
+        // Then @sigfigs
         if let Some(sigfigs) = attrs.get("sigfigs") {
             if let Ok(n) = sigfigs.parse::<u32>() {
                 return NumberFormat::SigFigs(n);
             }
         }
+        // Then @decimals
+        if let Some(decimals) = attrs.get("decimals") {
+            if let Ok(n) = decimals.parse::<u32>() {
+                return NumberFormat::Decimal(n);
+            }
+        }
         // Fall back to decimal places (default 10)
         NumberFormat::Decimal(10)
     }
 
+    /// Get number locale from @locale (default: en-US conventions)
+    fn get_locale(&self, attrs: &Attributes) -> NumberLocale {
+        attrs.get("locale")
+            .and_then(|tag| NumberLocale::from_tag(tag))
+            .unwrap_or_default()
+    }
+
     /// Get datetime formats from section attributes
-    fn get_datetime_formats(&self, attrs: &HashMap<String, String>) -> DateTimeFormats {
+    fn get_datetime_formats(&self, attrs: &Attributes) -> DateTimeFormats {
         DateTimeFormats {
             date_fmt: attrs.get("dateFmt").cloned(),
             time_fmt: attrs.get("timeFmt").cloned(),
@@ -121,19 +399,20 @@ impl Renderer {
         }
     }
 
-    fn render_value(&self, value: &Value, num_format: NumberFormat, dt_formats: &DateTimeFormats) -> String {
+    pub(crate) fn render_value(&self, value: &Value, format: &CellFormat) -> String {
         match value {
-            Value::Number(n) => match num_format {
-                NumberFormat::Decimal(places) => n.as_decimal(places),
-                NumberFormat::SigFigs(sigfigs) => n.as_sigfigs(sigfigs),
+            Value::Number(n) => match &format.number {
+                NumberFormat::Decimal(places) => format.locale.localize(&n.as_decimal(*places)),
+                NumberFormat::SigFigs(sigfigs) => format.locale.localize(&n.as_sigfigs(*sigfigs)),
+                NumberFormat::Pattern(pattern) => pattern.format(n, &format.locale),
             },
             Value::Text(s) => s.clone(),
             Value::Bool(b) => b.to_string(),
             Value::DateTime(dt) => {
                 // Use section datetime format if specified
-                if let Some(ref fmt) = dt_formats.datetime_fmt {
+                if let Some(ref fmt) = format.datetime.datetime_fmt {
                     dt.format(fmt)
-                } else if let Some(ref fmt) = dt_formats.date_fmt {
+                } else if let Some(ref fmt) = format.datetime.date_fmt {
                     // If only date format is specified, use it
                     dt.format(fmt)
                 } else {
@@ -142,11 +421,163 @@ impl Renderer {
             },
             Value::Duration(d) => d.to_string(),
             Value::Object(_) => value.to_string(), // Use Display trait for smart formatting
-            Value::List(l) => format!("[{}]", l.len()),
+            Value::List(l) => self.render_inline_list(l, format),
+            Value::Custom(_) => match matrix_rows(value) {
+                Some(rows) if rows.len() <= format.list_max => {
+                    let rows: Vec<Value> = rows.into_iter().map(Value::List).collect();
+                    self.render_inline_list(&rows, format)
+                }
+                Some(rows) => format!("Matrix {}×{}", rows.len(), rows.first().map_or(0, |r| r.len())),
+                None => value.to_string(),
+            },
             Value::Null => "null".to_string(),
             Value::Error(e) => format!("#ERROR: {}", e.code),
         }
     }
+
+    /// A value substituted into show-work: numbers without padding, to 15
+    /// significant digits
+    fn render_input(&self, value: &Value, format: &CellFormat) -> String {
+        match value {
+            Value::Number(n) => match n.to_i64() {
+                Some(i) if n.is_integer() => i.to_string(),
+                _ => format.locale.localize(&trim_zeros(&n.as_sigfigs(15))),
+            },
+            Value::Text(s) => format!("\"{}\"", s),
+            Value::List(items) => self.join_list(items, format, &|v| self.render_input(v, format)),
+            other => self.render_value(other, format),
+        }
+    }
+
+    /// `[1, 2, 3]`, shortened to `[1, 2, …] (25 items)` past `list_max`
+    fn render_inline_list(&self, items: &[Value], format: &CellFormat) -> String {
+        self.join_list(items, format, &|v| match v {
+            Value::Text(s) => format!("\"{}\"", s),
+            other => self.render_value(other, format),
+        })
+    }
+
+    fn join_list(&self, items: &[Value], format: &CellFormat, item: &dyn Fn(&Value) -> String) -> String {
+        // A decimal comma would be ambiguous next to the list separator
+        let sep = if format.locale.decimal == ',' { "; " } else { ", " };
+        let shown: Vec<String> = items.iter().take(format.list_max).map(item).collect();
+        if items.len() > format.list_max {
+            format!("[{}{}…] ({} items)", shown.join(sep), if shown.is_empty() { "" } else { sep }, items.len())
+        } else {
+            format!("[{}]", shown.join(sep))
+        }
+    }
+}
+
+impl DocumentRenderer for Renderer {
+    fn render(
+        &self,
+        doc: &Document,
+        values: &HashMap<String, Value>,
+        external: &HashMap<String, Value>,
+    ) -> String {
+        Renderer::render(self, doc, values, external)
+    }
+
+    fn render_parse_error(&self, error: &FolioError) -> String {
+        format!("# Parse Error\n\n{}", error)
+    }
+}
+
+/// Column alignment from a separator cell such as `:---:` or `---:`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Align {
+    Default,
+    Left,
+    Center,
+    Right,
+}
+
+pub(crate) fn column_alignment(marker: &str) -> Align {
+    let marker = marker.trim();
+    match (marker.starts_with(':'), marker.ends_with(':') && marker.len() > 1) {
+        (true, true) => Align::Center,
+        (false, true) => Align::Right,
+        (true, false) => Align::Left,
+        (false, false) => Align::Default,
+    }
+}
+
+/// Heading level and text of a markdown heading line ("### Notes" → (3, "Notes"))
+pub(crate) fn heading_parts(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim();
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 || !line[level..].starts_with(' ') {
+        return None;
+    }
+    Some((level, line[level..].trim()))
+}
+
+/// Which cells `@expand` selects
+enum Expand {
+    All,
+    Names(Vec<String>),
+}
+
+impl Expand {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Expand::All => true,
+            Expand::Names(names) => names.iter().any(|n| n == name),
+        }
+    }
+}
+
+/// `0.00540000` → `0.0054`, `1.500e-9` → `1.5e-9`
+fn trim_zeros(digits: &str) -> String {
+    let (mantissa, exponent) = digits.split_once('e').map_or((digits, None), |(m, e)| (m, Some(e)));
+    let mantissa = if mantissa.contains('.') { mantissa.trim_end_matches('0').trim_end_matches('.') } else { mantissa };
+    match exponent {
+        Some(e) => format!("{}e{}", mantissa, e),
+        None => mantissa.to_string(),
+    }
+}
+
+fn is_expandable(value: &Value) -> bool {
+    matches!(value, Value::List(_) | Value::Object(_)) || matrix_rows(value).is_some()
+}
+
+/// Rows of a Matrix, or None for any other value
+pub(crate) fn matrix_rows(value: &Value) -> Option<Vec<Vec<Value>>> {
+    match value {
+        Value::Custom(ext) if ext.type_name() == "Matrix" => match ext.field("data")? {
+            Value::List(rows) => rows.into_iter()
+                .map(|row| match row {
+                    Value::List(cells) => Some(cells),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Index of the header column with this name, appending it if missing
+fn extra_column(cells: &mut Vec<String>, name: &str) -> usize {
+    cells.iter().position(|c| c.eq_ignore_ascii_case(name)).unwrap_or_else(|| {
+        cells.push(name.to_string());
+        cells.len() - 1
+    })
+}
+
+/// Format one markdown table line
+pub(crate) fn table_line(cells: &[String]) -> String {
+    let mut line = String::from("|");
+    for cell in cells {
+        if cell.is_empty() {
+            line.push_str(" |");
+        } else {
+            line.push_str(&format!(" {} |", cell));
+        }
+    }
+    line.push('\n');
+    line
 }
 
 impl Default for Renderer {